@@ -1,16 +1,12 @@
+// Generated by cargo-generate-type 0.1.4
+// cargo generate-type examples/iris.csv --strings enum -o examples/iris.rs --force
+
+#[derive(Debug)]
 pub enum IrisError {
     CsvError(csv::Error),
-    ColumnNotFound {
-        linenum: u64,
-        column_name: &'static str,
-    },
-    InvalidColumnValue {
-        linenum: u64,
-        column_name: &'static str,
-        value: String,
-    },
+    ColumnNotFound { linenum: u64, column_name: &'static str },
+    InvalidColumnValue { linenum: u64, column_name: &'static str, value: String },
 }
-
 impl From<csv::Error> for IrisError {
     fn from(e: csv::Error) -> Self {
         Self::CsvError(e)
@@ -24,7 +20,6 @@ impl From<(u64, &'static str)> for IrisError {
         }
     }
 }
-
 impl<S> From<(u64, &'static str, S)> for IrisError
 where
     S: Into<String>,
@@ -37,8 +32,48 @@ where
         }
     }
 }
-
-#[derive(Clone, Debug)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Class {
+    /// From the input string 'Iris-setosa'
+    IrisSetosa,
+    /// From the input string 'Iris-versicolor'
+    IrisVersicolor,
+    /// From the input string 'Iris-virginica'
+    IrisVirginica,
+}
+impl Class {
+    /// The variants of the values seen when this code was generated
+    pub const ALL: [Self; 3] = [
+        Self::IrisSetosa,
+        Self::IrisVersicolor,
+        Self::IrisVirginica,
+    ];
+    /// The input string the value is parsed from
+    pub fn as_str(&self) -> &str {
+        match self {
+            Self::IrisSetosa => "Iris-setosa",
+            Self::IrisVersicolor => "Iris-versicolor",
+            Self::IrisVirginica => "Iris-virginica",
+        }
+    }
+}
+impl std::str::FromStr for Class {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "Iris-setosa" => Ok(Self::IrisSetosa),
+            "Iris-versicolor" => Ok(Self::IrisVersicolor),
+            "Iris-virginica" => Ok(Self::IrisVirginica),
+            _ => Err(s.to_string()),
+        }
+    }
+}
+impl std::fmt::Display for Class {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+#[derive(Copy, Clone, Debug)]
 /// This represents [Fisher's Iris data set](https://en.wikipedia.org/wiki/Iris_flower_data_set)
 pub struct Iris {
     /// The length of the flower's sepal, in centimeters.
@@ -50,99 +85,146 @@ pub struct Iris {
     /// The width of the flower's petal, in centimeters.
     pub petal_width_in_cm: f64,
     /// The species of flower, one of: Iris-setosa, Iris-virginica, Iris-versicolor.
-    pub class: String,
+    pub class: Class,
 }
-
 impl Iris {
-    pub const COLUMN_NAMES: [&str; 5] = [
-        "sepal_length_in_cm",
-        "sepal_width_in_cm",
-        "petal_length_in_cm",
-        "petal_width_in_cm",
-        "class",
+    /// The `(field name, type)` associated with each column.
+    pub const COLUMNS: [(&'static str, &'static str); 5] = [
+        ("sepal_length_in_cm", "f64"),
+        ("sepal_width_in_cm", "f64"),
+        ("petal_length_in_cm", "f64"),
+        ("petal_width_in_cm", "f64"),
+        ("class", "Class"),
     ];
-
-    /// A string representation of the Rust type associated with each type.
-    pub const COLUMN_TYPES: [&str; 5] = [
-        "f64", // sepal_length_in_cm
-        "f64", // sepal_width_in_cm
-        "f64", // petal_length_in_cm
-        "f64", // petal_width_in_cm
-        "String", // class
+    /// The header text each column is read from.
+    pub const HEADERS: [&'static str; 5] = [
+        "sepal length in cm",
+        "sepal width in cm",
+        "petal length in cm",
+        "petal width in cm",
+        "class",
     ];
-
+    /// A fingerprint of the column headers and types this code was generated from.
+    pub const SCHEMA_HASH: u64 = 0x4d9a900ee2ed7610;
     pub fn load_csv<P>(filename: P) -> Result<IrisIterator, csv::Error>
     where
         P: AsRef<std::path::Path>,
     {
-        let reader = csv::ReaderBuilder::new()
+        let mut reader = csv::ReaderBuilder::new()
             .has_headers(true)
             .delimiter(b',')
             .from_path(filename)?;
-
+        let indices = check_headers(reader.headers()?, &Self::HEADERS)?;
         let records = reader.into_records();
         let row = csv::StringRecord::default();
-        Ok(IrisIterator { records, row })
+        Ok(IrisIterator {
+            records,
+            row,
+            indices,
+        })
     }
 }
-
+/// Checks that the header row exactly matches `expected`, including order.
+/// Returns the position of each expected column within the header row.
+fn check_headers<const N: usize>(
+    headers: &csv::StringRecord,
+    expected: &[&str; N],
+) -> Result<[usize; N], csv::Error> {
+    let mismatch = |message: String| {
+        csv::Error::from(std::io::Error::new(std::io::ErrorKind::InvalidData, message))
+    };
+    if headers.len() != N {
+        return Err(
+            mismatch(
+                format!(
+                    "Expected {N} columns but found {} in header {headers:?}", headers
+                    .len()
+                ),
+            ),
+        );
+    }
+    let mut indices = [0; N];
+    for (i, name) in expected.iter().enumerate() {
+        if headers.get(i) != Some(*name) {
+            return Err(
+                mismatch(
+                    format!(
+                        "Expected column '{name}' at position {i} in header {headers:?}"
+                    ),
+                ),
+            );
+        }
+        indices[i] = i;
+    }
+    Ok(indices)
+}
 pub struct IrisIterator {
     records: csv::StringRecordsIntoIter<std::fs::File>,
     row: csv::StringRecord,
+    /// The position of each column in the input
+    indices: [usize; 5],
 }
-
 impl Iterator for IrisIterator {
     type Item = Result<Iris, IrisError>;
-
     fn next(&mut self) -> Option<Self::Item> {
         self.row = match self.records.next()? {
             Ok(r) => r,
             Err(e) => return Some(Err(e.into())),
         };
-
         let linenum = self.row.position().unwrap().line();
-
-        let sepal_length_in_cm = match self.row.get(0) {
+        let sepal_length_in_cm = match self.row.get(self.indices[0]) {
             None => return Some(Err((linenum, "sepal_length_in_cm").into())),
-            Some(val) => match val.parse() {
-                Ok(v) => v,
-
-                Err(_) => return Some(Err((linenum, "sepal_length_in_cm", val).into())),
-            },
+            Some(val) => {
+                match val.parse() {
+                    Ok(v) => v,
+                    Err(_) => {
+                        return Some(Err((linenum, "sepal_length_in_cm", val).into()));
+                    }
+                }
+            }
         };
-
-        let sepal_width_in_cm = match self.row.get(1) {
+        let sepal_width_in_cm = match self.row.get(self.indices[1]) {
             None => return Some(Err((linenum, "sepal_width_in_cm").into())),
-            Some(val) => match val.parse() {
-                Ok(v) => v,
-
-                Err(_) => return Some(Err((linenum, "sepal_width_in_cm", val).into())),
-            },
+            Some(val) => {
+                match val.parse() {
+                    Ok(v) => v,
+                    Err(_) => {
+                        return Some(Err((linenum, "sepal_width_in_cm", val).into()));
+                    }
+                }
+            }
         };
-
-        let petal_length_in_cm = match self.row.get(2) {
+        let petal_length_in_cm = match self.row.get(self.indices[2]) {
             None => return Some(Err((linenum, "petal_length_in_cm").into())),
-            Some(val) => match val.parse() {
-                Ok(v) => v,
-
-                Err(_) => return Some(Err((linenum, "petal_length_in_cm", val).into())),
-            },
+            Some(val) => {
+                match val.parse() {
+                    Ok(v) => v,
+                    Err(_) => {
+                        return Some(Err((linenum, "petal_length_in_cm", val).into()));
+                    }
+                }
+            }
         };
-
-        let petal_width_in_cm = match self.row.get(3) {
+        let petal_width_in_cm = match self.row.get(self.indices[3]) {
             None => return Some(Err((linenum, "petal_width_in_cm").into())),
-            Some(val) => match val.parse() {
-                Ok(v) => v,
-
-                Err(_) => return Some(Err((linenum, "petal_width_in_cm", val).into())),
-            },
+            Some(val) => {
+                match val.parse() {
+                    Ok(v) => v,
+                    Err(_) => {
+                        return Some(Err((linenum, "petal_width_in_cm", val).into()));
+                    }
+                }
+            }
         };
-
-        let class = match self.row.get(4) {
+        let class = match self.row.get(self.indices[4]) {
             None => return Some(Err((linenum, "class").into())),
-            Some(val) => val.to_owned(),
+            Some(val) => {
+                match val.parse() {
+                    Ok(v) => v,
+                    Err(_) => return Some(Err((linenum, "class", val).into())),
+                }
+            }
         };
-
         let res = Iris {
             sepal_length_in_cm,
             sepal_width_in_cm,
@@ -150,10 +232,18 @@ impl Iterator for IrisIterator {
             petal_width_in_cm,
             class,
         };
-
         Some(Ok(res))
     }
 }
+#[test]
+fn test_load() {
+    for row in Iris::load_csv("examples/iris.csv")
+        .expect("Couldn't load file")
+        .map_while(Result::ok)
+    {
+        println!("Got row: {row:?}");
+    }
+}
 
 fn main() {
     for row in Iris::load_csv("examples/iris.csv")
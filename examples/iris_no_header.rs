@@ -1,16 +1,12 @@
+// Generated by cargo-generate-type 0.1.4
+// cargo generate-type examples/iris_no_header.csv --no-header --typename IrisNoHeader -o examples/iris_no_header.rs --force
+
+#[derive(Debug)]
 pub enum IrisNoHeaderError {
     CsvError(csv::Error),
-    ColumnNotFound {
-        linenum: u64,
-        column_name: &'static str,
-    },
-    InvalidColumnValue {
-        linenum: u64,
-        column_name: &'static str,
-        value: String,
-    },
+    ColumnNotFound { linenum: u64, column_name: &'static str },
+    InvalidColumnValue { linenum: u64, column_name: &'static str, value: String },
 }
-
 impl From<csv::Error> for IrisNoHeaderError {
     fn from(e: csv::Error) -> Self {
         Self::CsvError(e)
@@ -24,7 +20,6 @@ impl From<(u64, &'static str)> for IrisNoHeaderError {
         }
     }
 }
-
 impl<S> From<(u64, &'static str, S)> for IrisNoHeaderError
 where
     S: Into<String>,
@@ -37,7 +32,6 @@ where
         }
     }
 }
-
 #[derive(Clone, Debug)]
 pub struct IrisNoHeader {
     pub column_0: f64,
@@ -46,16 +40,25 @@ pub struct IrisNoHeader {
     pub column_3: f64,
     pub column_4: String,
 }
-
 impl IrisNoHeader {
-    pub const COLUMNS: [&str; 5] = [
+    /// The `(field name, type)` associated with each column.
+    pub const COLUMNS: [(&'static str, &'static str); 5] = [
+        ("column_0", "f64"),
+        ("column_1", "f64"),
+        ("column_2", "f64"),
+        ("column_3", "f64"),
+        ("column_4", "String"),
+    ];
+    /// The header text each column is read from.
+    pub const HEADERS: [&'static str; 5] = [
         "column_0",
         "column_1",
         "column_2",
         "column_3",
         "column_4",
     ];
-
+    /// A fingerprint of the column headers and types this code was generated from.
+    pub const SCHEMA_HASH: u64 = 0xee4a5f0a8d0b8a15;
     pub fn load_csv<P>(filename: P) -> Result<IrisNoHeaderIterator, csv::Error>
     where
         P: AsRef<std::path::Path>,
@@ -64,70 +67,70 @@ impl IrisNoHeader {
             .has_headers(false)
             .delimiter(b',')
             .from_path(filename)?;
-
+        let indices = [0, 1, 2, 3, 4];
         let records = reader.into_records();
         let row = csv::StringRecord::default();
-        Ok(IrisNoHeaderIterator { records, row })
+        Ok(IrisNoHeaderIterator {
+            records,
+            row,
+            indices,
+        })
     }
 }
-
 pub struct IrisNoHeaderIterator {
     records: csv::StringRecordsIntoIter<std::fs::File>,
     row: csv::StringRecord,
+    /// The position of each column in the input
+    indices: [usize; 5],
 }
-
 impl Iterator for IrisNoHeaderIterator {
     type Item = Result<IrisNoHeader, IrisNoHeaderError>;
-
     fn next(&mut self) -> Option<Self::Item> {
         self.row = match self.records.next()? {
             Ok(r) => r,
             Err(e) => return Some(Err(e.into())),
         };
-
         let linenum = self.row.position().unwrap().line();
-
-        let column_0 = match self.row.get(0) {
+        let column_0 = match self.row.get(self.indices[0]) {
             None => return Some(Err((linenum, "column_0").into())),
-            Some(val) => match val.parse() {
-                Ok(v) => v,
-
-                Err(_) => return Some(Err((linenum, "column_0", val).into())),
-            },
+            Some(val) => {
+                match val.parse() {
+                    Ok(v) => v,
+                    Err(_) => return Some(Err((linenum, "column_0", val).into())),
+                }
+            }
         };
-
-        let column_1 = match self.row.get(1) {
+        let column_1 = match self.row.get(self.indices[1]) {
             None => return Some(Err((linenum, "column_1").into())),
-            Some(val) => match val.parse() {
-                Ok(v) => v,
-
-                Err(_) => return Some(Err((linenum, "column_1", val).into())),
-            },
+            Some(val) => {
+                match val.parse() {
+                    Ok(v) => v,
+                    Err(_) => return Some(Err((linenum, "column_1", val).into())),
+                }
+            }
         };
-
-        let column_2 = match self.row.get(2) {
+        let column_2 = match self.row.get(self.indices[2]) {
             None => return Some(Err((linenum, "column_2").into())),
-            Some(val) => match val.parse() {
-                Ok(v) => v,
-
-                Err(_) => return Some(Err((linenum, "column_2", val).into())),
-            },
+            Some(val) => {
+                match val.parse() {
+                    Ok(v) => v,
+                    Err(_) => return Some(Err((linenum, "column_2", val).into())),
+                }
+            }
         };
-
-        let column_3 = match self.row.get(3) {
+        let column_3 = match self.row.get(self.indices[3]) {
             None => return Some(Err((linenum, "column_3").into())),
-            Some(val) => match val.parse() {
-                Ok(v) => v,
-
-                Err(_) => return Some(Err((linenum, "column_3", val).into())),
-            },
+            Some(val) => {
+                match val.parse() {
+                    Ok(v) => v,
+                    Err(_) => return Some(Err((linenum, "column_3", val).into())),
+                }
+            }
         };
-
-        let column_4 = match self.row.get(4) {
+        let column_4 = match self.row.get(self.indices[4]) {
             None => return Some(Err((linenum, "column_4").into())),
             Some(val) => val.to_owned(),
         };
-
         let res = IrisNoHeader {
             column_0,
             column_1,
@@ -135,10 +138,18 @@ impl Iterator for IrisNoHeaderIterator {
             column_3,
             column_4,
         };
-
         Some(Ok(res))
     }
 }
+#[test]
+fn test_load() {
+    for row in IrisNoHeader::load_csv("examples/iris_no_header.csv")
+        .expect("Couldn't load file")
+        .map_while(Result::ok)
+    {
+        println!("Got row: {row:?}");
+    }
+}
 
 fn main() {
     for row in IrisNoHeader::load_csv("examples/iris_no_header.csv")
@@ -0,0 +1,321 @@
+use crate::{err::TypeGenErrors, generate_csv::CsvFileInfo, input_args::Commands, syntax, util};
+use quote::quote;
+use std::{
+    collections::HashMap,
+    fs::File,
+    io::{BufWriter, Write},
+    path::{Path, PathBuf},
+};
+
+/// Generates a type for every file in `--dir` that matches `--pattern`, writing each to its own
+/// source file in `--out` along with a `common.rs` of shared error types and helpers, and a
+/// `mod.rs` that declares and re-exports them.
+pub fn generate_dir(args: Commands) -> Result<(), TypeGenErrors> {
+    let (dir, out) = match (&args.dir, &args.out) {
+        (Some(dir), Some(out)) => (dir, out),
+        _ => return Err(TypeGenErrors::Other("--dir requires --out".into())),
+    };
+
+    let files = util::expand_globs(&[dir.join(&args.pattern)])?;
+    std::fs::create_dir_all(out)?;
+
+    let mut modules: Vec<(PathBuf, Commands)> = Vec::new();
+    for file in files {
+        let mut file_args = args.clone();
+        file_args.input_files = vec![file];
+
+        let module = file_args.get_output_filename();
+        let typename = file_args.get_typename();
+        if module == Path::new("common.rs") || module == Path::new("mod.rs") {
+            return Err(TypeGenErrors::Other(
+                format!(
+                    "{:?} would generate {module:?}, which is reserved for the shared module",
+                    file_args.input_file()
+                )
+                .into(),
+            ));
+        }
+        if let Some((_, existing)) = modules.iter().find(|(m, _)| *m == module) {
+            return Err(TypeGenErrors::Other(
+                format!(
+                    "{:?} and {:?} would both generate {module:?}",
+                    existing.input_file(),
+                    file_args.input_file()
+                )
+                .into(),
+            ));
+        }
+        if typename.is_empty() {
+            return Err(TypeGenErrors::Other(
+                format!("Can't name a type for {:?}", file_args.input_file()).into(),
+            ));
+        }
+        let stem = module
+            .file_stem()
+            .and_then(|s| s.to_str())
+            .unwrap_or_default();
+        if syntax::check_ident(stem).is_err() {
+            return Err(TypeGenErrors::Other(
+                format!(
+                    "{:?} would generate {module:?}, but {stem:?} is not a Rust identifier",
+                    file_args.input_file()
+                )
+                .into(),
+            ));
+        }
+
+        // The shared module's error types and helpers are those of a single input format
+        if let Some((_, first)) = modules.first() {
+            if first.input_format() != file_args.input_format() {
+                return Err(TypeGenErrors::Other(
+                    format!(
+                        "{:?} is {:?} input but {:?} is {:?}; the files in --dir must have the same format",
+                        first.input_file(),
+                        first.input_format(),
+                        file_args.input_file(),
+                        file_args.input_format()
+                    )
+                    .into(),
+                ));
+            }
+        }
+
+        modules.push((module, file_args));
+    }
+
+    // Every output file is checked before any are written, so a failure doesn't leave a partial module
+    let mut outputs = modules
+        .iter()
+        .map(|(module, _)| out.join(module))
+        .collect::<Vec<_>>();
+    outputs.push(out.join("common.rs"));
+    outputs.push(out.join("mod.rs"));
+    if !args.force {
+        if let Some(existing) = outputs.iter().find(|p| p.exists()) {
+            return Err(TypeGenErrors::Other(
+                format!("{existing:?} already exists; use --force to overwrite it").into(),
+            ));
+        }
+    }
+
+    // Every file is analyzed before any are generated, since a type's code depends on the files
+    // that refer to it
+    let mut paths = Vec::new();
+    let mut infos = Vec::new();
+    for (module, file_args) in modules {
+        let explain = file_args.explain;
+        let info = CsvFileInfo::new(file_args)
+            .with_common_module()
+            .analyze_input()?
+            .load_data_def();
+
+        if explain {
+            info.write_explanation();
+        }
+        paths.push(out.join(module));
+        infos.push(info);
+    }
+    CsvFileInfo::link_references(&mut infos);
+
+    // The shared module depends on the arguments common to every file, and on whether any type is
+    // keyed, which adds a duplicate key error
+    let common = infos
+        .iter()
+        .find(|info| info.is_keyed())
+        .unwrap_or(&infos[0]);
+    let mut buf = BufWriter::new(File::create(out.join("common.rs"))?);
+    common.generate_common(&mut buf)?;
+    buf.flush()?;
+
+    let mut reexports = Vec::new();
+    for (path, info) in paths.into_iter().zip(infos) {
+        let mut buf = BufWriter::new(File::create(&path)?);
+        info.generate(&mut buf)?;
+        buf.flush()?;
+        println!("Generated {path:?}");
+
+        reexports.push((info.module_name(), info.exported_names()));
+    }
+
+    // A name generated in more than one module, such as the enums of two columns with the same
+    // header, would be ambiguous, so it's only reachable through its module
+    let mut counts = HashMap::new();
+    for name in reexports.iter().flat_map(|(_, names)| names) {
+        *counts.entry(name.clone()).or_insert(0) += 1;
+    }
+    for (module, names) in &mut reexports {
+        names.retain(|name| {
+            let unique = counts[name] == 1;
+            if !unique {
+                eprintln!("Warning: {name} is generated in several modules, so {module}::{name} isn't re-exported");
+            }
+            unique
+        });
+    }
+
+    let mut buf = BufWriter::new(File::create(out.join("mod.rs"))?);
+    write_mod(&mut buf, &args, &reexports)?;
+    buf.flush()?;
+    println!("Generated {:?}", out.join("mod.rs"));
+
+    Ok(())
+}
+
+/// Writes `mod.rs`, which declares each generated module and re-exports its types.
+fn write_mod(
+    buf: &mut BufWriter<File>,
+    args: &Commands,
+    modules: &[(String, Vec<String>)],
+) -> Result<(), TypeGenErrors> {
+    // The directory is debug formatted, which escapes any control characters in it
+    let doc = format!(
+        " Types generated from the files in {:?}.",
+        args.dir.as_ref().unwrap()
+    );
+    let mods = modules.iter().map(|(module, _)| syntax::ident(module));
+    let reexports = modules.iter().map(|(module, names)| {
+        let module = syntax::ident(module);
+        let names = names.iter().map(|name| syntax::ident(name));
+        quote! { pub use #module::{#(#names),*}; }
+    });
+    let code = quote! {
+        #![doc = #doc]
+        pub mod common;
+        #(pub mod #mods;)*
+        #(#reexports)*
+    };
+    syntax::write_formatted(&code.to_string(), buf)
+}
+
+#[test]
+fn test_generate_dir() {
+    use clap::Parser;
+
+    let dir = std::env::temp_dir().join(format!("generate-dir-{}", std::process::id()));
+    let out = dir.join("out");
+    std::fs::create_dir_all(&dir).unwrap();
+    std::fs::write(
+        dir.join("customers.csv"),
+        "customer_id,name\n1,ann\n2,bob\n3,cy\n",
+    )
+    .unwrap();
+    std::fs::write(
+        dir.join("orders.csv"),
+        "order_id,customer_id,amount\n10,1,2.5\n11,1,3\n12,3,4\n",
+    )
+    .unwrap();
+
+    let args = Commands::try_parse_from([
+        "cargo-generate-type",
+        "generate-type",
+        "--dir",
+        dir.to_str().unwrap(),
+        "--out",
+        out.to_str().unwrap(),
+    ])
+    .unwrap();
+    let result = generate_dir(args);
+    let read = |name: &str| std::fs::read_to_string(out.join(name)).unwrap_or_default();
+    let (module, common, orders) = (read("mod.rs"), read("common.rs"), read("orders.rs"));
+    std::fs::remove_dir_all(&dir).unwrap();
+    result.unwrap();
+
+    // Each type is declared and re-exported, with the key and index of the type that's referred to
+    let declared = "pub mod common;\npub mod customers;\npub mod orders;\n";
+    assert!(module.contains(declared));
+    assert!(module.contains(
+        "pub use customers::{\n    Customers, CustomersIterator, CustomersError, CustomersKey, CustomersIndex,\n};"
+    ));
+    assert!(module.contains("pub use orders::{Orders, OrdersIterator, OrdersError};"));
+
+    // The shared error includes duplicate keys, since one type is keyed
+    assert!(common.contains("pub enum Error {"));
+    assert!(common.contains("DuplicateKey { linenum: u64, key: String },"));
+    assert!(common.contains("pub fn check_headers<const N: usize>("));
+
+    // Types use the shared error and helpers, and refer to each other through their modules
+    assert!(orders.contains("use super::common::check_headers;"));
+    assert!(orders.contains("pub type OrdersError = super::common::Error;"));
+    assert!(!orders.contains("pub enum OrdersError"));
+    assert!(orders.contains("index: &'a super::customers::CustomersIndex,"));
+    assert!(orders.contains(") -> Option<&'a super::customers::Customers> {"));
+    assert!(orders.contains(r#"("customer_id", "Customers", "customer_id"),"#));
+}
+
+#[test]
+fn test_generate_dir_enum() {
+    use clap::Parser;
+
+    let dir = std::env::temp_dir().join(format!("generate-dir-enum-{}", std::process::id()));
+    let out = dir.join("out");
+    std::fs::create_dir_all(&dir).unwrap();
+    std::fs::write(
+        dir.join("customers.csv"),
+        "customer_id,name,status\n1,ann,new\n2,bob,new\n3,cy,vip\n",
+    )
+    .unwrap();
+    std::fs::write(
+        dir.join("orders.csv"),
+        "order_id,customer_id,status\n10,1,open\n11,1,shipped\n12,3,open\n",
+    )
+    .unwrap();
+
+    let args = Commands::try_parse_from([
+        "cargo-generate-type",
+        "generate-type",
+        "--dir",
+        dir.to_str().unwrap(),
+        "--out",
+        out.to_str().unwrap(),
+        "-s",
+        "enum",
+    ])
+    .unwrap();
+    let result = generate_dir(args);
+    let orders = std::fs::read_to_string(out.join("orders.rs")).unwrap_or_default();
+    let module = std::fs::read_to_string(out.join("mod.rs")).unwrap_or_default();
+    std::fs::remove_dir_all(&dir).unwrap();
+    result.unwrap();
+
+    // Integer keys are still referred to when strings are enums
+    assert!(orders.contains("index: &'a super::customers::CustomersIndex,"));
+    assert!(orders.contains(") -> Option<&'a super::customers::Customers> {"));
+    assert!(orders.contains(r#"("customer_id", "Customers", "customer_id"),"#));
+
+    // Enums are re-exported with their types, except those that more than one module generates
+    assert!(module.contains("CustomersKey, CustomersIndex, Name,\n};"));
+    assert!(module.contains("pub use orders::{Orders, OrdersIterator, OrdersError};"));
+    assert!(orders.contains("pub enum Status {"));
+}
+
+#[test]
+fn test_generate_dir_mixed_formats() {
+    use clap::Parser;
+
+    let dir = std::env::temp_dir().join(format!("generate-dir-mixed-{}", std::process::id()));
+    let out = dir.join("out");
+    std::fs::create_dir_all(&dir).unwrap();
+    std::fs::write(dir.join("a.csv"), "id\n1\n").unwrap();
+    std::fs::write(dir.join("b.parquet"), "").unwrap();
+
+    let args = Commands::try_parse_from([
+        "cargo-generate-type",
+        "generate-type",
+        "--dir",
+        dir.to_str().unwrap(),
+        "--pattern",
+        "*",
+        "--out",
+        out.to_str().unwrap(),
+    ])
+    .unwrap();
+    let result = generate_dir(args);
+    let written = out.join("common.rs").exists();
+    std::fs::remove_dir_all(&dir).unwrap();
+
+    // Files of different formats are rejected before anything is written
+    let message = result.unwrap_err().to_string();
+    assert!(message.contains("is Csv input but"), "{message}");
+    assert!(message.contains("is Parquet;"), "{message}");
+    assert!(!written);
+}
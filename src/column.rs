@@ -1,4 +1,4 @@
-use std::str::FromStr;
+use std::{mem::Discriminant, str::FromStr};
 
 #[derive(PartialEq, Debug)]
 /// Stores the type of an output CSV; for non-unit types,
@@ -19,6 +19,21 @@ pub enum ColumnType {
 }
 
 impl ColumnType {
+    /// The smallest and largest values representable by an integer type
+    pub fn integer_bounds(&self) -> Option<(i128, i128)> {
+        match self {
+            ColumnType::I8(_) => Some((i8::MIN as i128, i8::MAX as i128)),
+            ColumnType::I16(_) => Some((i16::MIN as i128, i16::MAX as i128)),
+            ColumnType::I32(_) => Some((i32::MIN as i128, i32::MAX as i128)),
+            ColumnType::I64(_) => Some((i64::MIN as i128, i64::MAX as i128)),
+            ColumnType::U8(_) => Some((0, u8::MAX as i128)),
+            ColumnType::U16(_) => Some((0, u16::MAX as i128)),
+            ColumnType::U32(_) => Some((0, u32::MAX as i128)),
+            ColumnType::U64(_) => Some((0, u64::MAX as i128)),
+            _ => None,
+        }
+    }
+
     pub fn is_optional(&self) -> bool {
         match self {
             ColumnType::Unit => false,
@@ -55,8 +70,47 @@ impl Default for IntermediateColumnType {
     }
 }
 
+impl std::fmt::Display for IntermediateColumnType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.is_optional() && !matches!(self, IntermediateColumnType::Unknown(_)) {
+            write!(f, "optional ")?;
+        }
+
+        match self {
+            IntermediateColumnType::Unknown(false) => write!(f, "unknown"),
+            IntermediateColumnType::Unknown(true) => write!(f, "empty"),
+            IntermediateColumnType::Bool(_) => write!(f, "bool"),
+            IntermediateColumnType::Integer(_, _, _) => write!(f, "integer"),
+            IntermediateColumnType::Float(_) => write!(f, "float"),
+            IntermediateColumnType::String(_) => write!(f, "string"),
+        }
+    }
+}
+
 impl IntermediateColumnType {
-    fn set_optional(self) -> Self {
+    pub(crate) fn is_optional(&self) -> bool {
+        match self {
+            IntermediateColumnType::Unknown(b) => *b,
+            IntermediateColumnType::Bool(b) => *b,
+            IntermediateColumnType::Integer(_, _, b) => *b,
+            IntermediateColumnType::Float(b) => *b,
+            IntermediateColumnType::String(b) => *b,
+        }
+    }
+
+    /// Whether going from `self` to `other` loosened the type, either by becoming less
+    /// specific (eg, integer to float) or by becoming optional.
+    pub(crate) fn is_widened_by(&self, other: &IntermediateColumnType) -> bool {
+        if let IntermediateColumnType::Unknown(_) = self {
+            // Learning the type of a column isn't a widening, but learning that it has empty values is
+            return !self.is_optional() && other.is_optional();
+        }
+
+        std::mem::discriminant(self) != std::mem::discriminant(other)
+            || self.is_optional() != other.is_optional()
+    }
+
+    pub(crate) fn set_optional(self) -> Self {
         match self {
             IntermediateColumnType::Unknown(_) => IntermediateColumnType::Unknown(true),
             IntermediateColumnType::Bool(_) => IntermediateColumnType::Bool(true),
@@ -68,67 +122,73 @@ impl IntermediateColumnType {
         }
     }
 
-    pub(crate) fn agg(&mut self, input_value: &str) {
-        let input_type: IntermediateColumnType = input_value.parse().unwrap();
-
-        match (&self, input_type) {
+    /// Combines two inferred types into one that accommodates the values of both.
+    ///
+    /// This is associative and commutative, so columns can be inferred from separate
+    /// parts of an input and merged in any grouping.
+    pub(crate) fn merge(self, other: IntermediateColumnType) -> IntermediateColumnType {
+        match (self, other) {
             // If one is a string, then we must already know it doesn't parse to something more specific
-            (IntermediateColumnType::String(true), _) => {}
-            (_, IntermediateColumnType::String(true)) => {
-                *self = IntermediateColumnType::String(true);
-            }
+            (IntermediateColumnType::String(true), _) => IntermediateColumnType::String(true),
+            (_, IntermediateColumnType::String(true)) => IntermediateColumnType::String(true),
             (IntermediateColumnType::String(false), _)
             | (_, IntermediateColumnType::String(false)) => {
-                *self = IntermediateColumnType::String(input_value.is_empty());
+                IntermediateColumnType::String(self.is_optional() || other.is_optional())
             }
 
             // First iteration with an unknown column, always keep the other
-            (IntermediateColumnType::Unknown(false), it) => *self = it,
-            (_, IntermediateColumnType::Unknown(false)) => {}
+            (IntermediateColumnType::Unknown(false), it) => it,
+            (s, IntermediateColumnType::Unknown(false)) => s,
 
             // When one is unknown, keep the known value, but it is now known to be nullable
-            (IntermediateColumnType::Unknown(true), it) => *self = it.set_optional(),
-            (s, IntermediateColumnType::Unknown(true)) => *self = s.set_optional(),
+            (IntermediateColumnType::Unknown(true), it) => it.set_optional(),
+            (s, IntermediateColumnType::Unknown(true)) => s.set_optional(),
 
             // When types are identical:
             (IntermediateColumnType::Bool(so), IntermediateColumnType::Bool(oo)) => {
-                *self = IntermediateColumnType::Bool(*so || oo)
+                IntermediateColumnType::Bool(so || oo)
             }
             (IntermediateColumnType::Float(so), IntermediateColumnType::Float(oo)) => {
-                *self = IntermediateColumnType::Float(*so || oo)
+                IntermediateColumnType::Float(so || oo)
             }
 
             (
                 IntermediateColumnType::Integer(self_min, self_max, so),
                 IntermediateColumnType::Integer(other_min, other_max, oo),
-            ) => {
-                *self = IntermediateColumnType::Integer(
-                    *self_min.min(&other_min),
-                    *self_max.max(&other_max),
-                    *so || oo,
-                )
-            }
+            ) => IntermediateColumnType::Integer(
+                self_min.min(other_min),
+                self_max.max(other_max),
+                so || oo,
+            ),
 
             // Always take floats over ints
             (
                 IntermediateColumnType::Integer(_, _, self_optional),
                 IntermediateColumnType::Float(other_optional),
-            ) => *self = IntermediateColumnType::Float(*self_optional || other_optional),
+            ) => IntermediateColumnType::Float(self_optional || other_optional),
             (
                 IntermediateColumnType::Float(self_optional),
                 IntermediateColumnType::Integer(_, _, other_optional),
-            ) => *self = IntermediateColumnType::Float(*self_optional || other_optional),
+            ) => IntermediateColumnType::Float(self_optional || other_optional),
 
             // All other cases result in a string
             (IntermediateColumnType::Bool(so), IntermediateColumnType::Integer(_, _, oo))
             | (IntermediateColumnType::Bool(so), IntermediateColumnType::Float(oo))
             | (IntermediateColumnType::Integer(_, _, so), IntermediateColumnType::Bool(oo))
             | (IntermediateColumnType::Float(so), IntermediateColumnType::Bool(oo)) => {
-                *self = IntermediateColumnType::String(*so || oo);
+                IntermediateColumnType::String(so || oo)
             }
         }
     }
 
+    /// The minimum and maximum values seen, if this is an integer column
+    pub(crate) fn range(&self) -> Option<(i128, i128)> {
+        match self {
+            IntermediateColumnType::Integer(min, max, _) => Some((*min, *max)),
+            _ => None,
+        }
+    }
+
     pub(crate) fn finish(self) -> ColumnType {
         match self {
             IntermediateColumnType::Unknown(_) => ColumnType::Unit,
@@ -169,6 +229,196 @@ impl IntermediateColumnType {
     }
 }
 
+/// How many examples of each kind of widening are kept per column
+const MAX_EXAMPLES_PER_WIDENING: usize = 3;
+
+/// The ways in which a value can loosen the type of a column
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum WideningKind {
+    /// An integer column saw a non-integer number
+    IntegerToFloat,
+    /// A numeric or boolean column saw a value that isn't one
+    ToString,
+    /// A required column saw an empty value
+    ToOptional,
+}
+
+impl std::fmt::Display for WideningKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            WideningKind::IntegerToFloat => write!(f, "integer to float"),
+            WideningKind::ToString => write!(f, "to string"),
+            WideningKind::ToOptional => write!(f, "required to optional"),
+        }
+    }
+}
+
+/// A value that caused a column's type to widen
+#[derive(Clone, Debug)]
+pub struct Widening {
+    pub line: u64,
+    pub value: String,
+    pub from: IntermediateColumnType,
+    pub to: IntermediateColumnType,
+}
+
+impl Widening {
+    pub fn kind(&self) -> WideningKind {
+        match (self.from, self.to) {
+            (_, to) if to.is_optional() && !self.from.is_optional() => WideningKind::ToOptional,
+            (IntermediateColumnType::Integer(..), IntermediateColumnType::Float(_)) => {
+                WideningKind::IntegerToFloat
+            }
+            _ => WideningKind::ToString,
+        }
+    }
+}
+
+impl std::fmt::Display for Widening {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "line {}: {:?} widened {} to {}",
+            self.line, self.value, self.from, self.to
+        )
+    }
+}
+
+/// Infers the type of a column, keeping the first few values responsible for each widening
+/// of its type as evidence.
+#[derive(Clone, Debug, Default)]
+pub struct ColumnInference {
+    pub state: IntermediateColumnType,
+    widenings: Vec<Widening>,
+    /// The line, value and kind of the first empty, bool, integer, float, and string value seen
+    representatives: Vec<(u64, String, Discriminant<IntermediateColumnType>)>,
+}
+
+impl ColumnInference {
+    /// An inference of a column whose type is already known, such as from a Parquet file's schema
+    #[cfg(any(feature = "parquet", feature = "json-schema"))]
+    pub fn from_state(state: IntermediateColumnType) -> Self {
+        ColumnInference {
+            state,
+            ..Default::default()
+        }
+    }
+
+    /// Aggregates `value`, read from `line`, into the inferred type.
+    pub fn agg(&mut self, value: &str, line: u64) {
+        let before = self.state;
+        let input_type: IntermediateColumnType = value.parse().unwrap();
+        self.state = self.state.merge(input_type);
+
+        // Once there's one of each kind, there's no need to compare kinds
+        if self.representatives.len() < 5 {
+            let kind = std::mem::discriminant(&input_type);
+            if self.representatives.iter().all(|(_, _, k)| *k != kind) {
+                self.representatives.push((line, value.to_string(), kind));
+            }
+        }
+
+        // A column starting out as a string is not a widening, but it's still useful to see why
+        let first_string = matches!(before, IntermediateColumnType::Unknown(_))
+            && matches!(self.state, IntermediateColumnType::String(_));
+
+        if before.is_widened_by(&self.state) || first_string {
+            self.push_widening(Widening {
+                line,
+                value: value.to_string(),
+                from: before,
+                to: self.state,
+            });
+        }
+    }
+
+    fn push_widening(&mut self, widening: Widening) {
+        let kind = widening.kind();
+        let same_kind = self.widenings.iter().filter(|w| w.kind() == kind);
+
+        if same_kind.clone().all(|w| w.line != widening.line)
+            && same_kind.count() < MAX_EXAMPLES_PER_WIDENING
+        {
+            self.widenings.push(widening);
+        }
+    }
+
+    /// Combines the inference of a later part of the same column into this one.
+    pub fn merge(&mut self, other: ColumnInference) {
+        // Only the first value of each kind can widen a type, so replaying the other part's
+        // first values finds the same widenings as reading both parts in order would. The other
+        // part's own widenings started from an unknown type, so they don't apply here.
+        for (line, value, _) in &other.representatives {
+            self.agg(value, *line);
+        }
+        self.state = self.state.merge(other.state);
+    }
+
+    /// Makes the column optional because it's absent from some of the input.
+    pub fn mark_missing(&mut self) {
+        self.state = self.state.set_optional();
+    }
+
+    /// Adjusts recorded line numbers for a part of the input that didn't start on line 1.
+    pub fn offset_lines(&mut self, offset: u64) {
+        for widening in &mut self.widenings {
+            widening.line += offset;
+        }
+        for (line, _, _) in &mut self.representatives {
+            *line += offset;
+        }
+    }
+
+    /// The values that widened the type of this column, in the order they were seen
+    pub fn widenings(&self) -> &[Widening] {
+        &self.widenings
+    }
+
+    /// Explains why the column was inferred as its current type.
+    pub fn reason(&self) -> String {
+        let first_of = |kind| self.widenings.iter().find(|w| w.kind() == kind);
+
+        let mut reason = match self.state {
+            IntermediateColumnType::Unknown(_) => "every value was empty".to_string(),
+            IntermediateColumnType::Bool(_) => "every value was 'true' or 'false'".to_string(),
+            IntermediateColumnType::Integer(min, max, _) => {
+                format!("every value was an integer between {min} and {max}")
+            }
+            IntermediateColumnType::Float(_) => match first_of(WideningKind::IntegerToFloat) {
+                Some(w) => format!(
+                    "every value was numeric, but {:?} on line {} is not an integer",
+                    w.value, w.line
+                ),
+                None => "every value was numeric, but not every value was an integer".to_string(),
+            },
+            IntermediateColumnType::String(_) => match first_of(WideningKind::ToString) {
+                Some(w) => format!(
+                    "{:?} on line {} is not {}",
+                    w.value,
+                    w.line,
+                    match w.from {
+                        IntermediateColumnType::Bool(_) => "a bool",
+                        IntermediateColumnType::Integer(..) | IntermediateColumnType::Float(_) => {
+                            "a number"
+                        }
+                        _ => "a number or bool",
+                    }
+                ),
+                None => "values were not numeric or boolean".to_string(),
+            },
+        };
+
+        if self.state.is_optional() && !matches!(self.state, IntermediateColumnType::Unknown(_)) {
+            match first_of(WideningKind::ToOptional) {
+                Some(w) => reason += &format!(", and line {} is empty", w.line),
+                None => reason += ", and some values are empty",
+            }
+        }
+
+        reason
+    }
+}
+
 impl FromStr for IntermediateColumnType {
     // All values will 'parse' -- default is a String
     type Err = ();
@@ -187,3 +437,60 @@ impl FromStr for IntermediateColumnType {
         }
     }
 }
+
+#[test]
+fn test_column_inference_widenings() {
+    let mut inference = ColumnInference::default();
+    for (line, value) in ["1", "2", "", "A3", "4"].iter().enumerate() {
+        inference.agg(value, line as u64 + 2);
+    }
+
+    assert_eq!(inference.state, IntermediateColumnType::String(true));
+
+    let kinds = inference
+        .widenings()
+        .iter()
+        .map(|w| (w.line, w.kind()))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        kinds,
+        vec![(4, WideningKind::ToOptional), (5, WideningKind::ToString)]
+    );
+}
+
+#[test]
+fn test_merge() {
+    let values = ["7", "", "8.5", "true", "-3", "x"];
+    let types = values
+        .iter()
+        .map(|v| v.parse::<IntermediateColumnType>().unwrap())
+        .collect::<Vec<_>>();
+
+    // Merging is associative and commutative, so any split of the values gives the same type
+    let expected = types
+        .iter()
+        .fold(IntermediateColumnType::default(), |a, b| a.merge(*b));
+    for split in 0..types.len() {
+        let left = types[..split]
+            .iter()
+            .fold(IntermediateColumnType::default(), |a, b| a.merge(*b));
+        let right = types[split..]
+            .iter()
+            .fold(IntermediateColumnType::default(), |a, b| a.merge(*b));
+        assert_eq!(left.merge(right), expected);
+        assert_eq!(right.merge(left), expected);
+    }
+
+    // Merged inference keeps evidence of widening caused by the other part
+    let mut first = ColumnInference::default();
+    first.agg("1", 2);
+    first.agg("2", 3);
+    let mut second = ColumnInference::default();
+    second.agg("2.5", 1);
+    second.offset_lines(3);
+    first.merge(second);
+
+    assert_eq!(first.state, IntermediateColumnType::Float(false));
+    assert_eq!(first.widenings()[0].line, 4);
+    assert_eq!(first.widenings()[0].kind(), WideningKind::IntegerToFloat);
+}
@@ -0,0 +1,125 @@
+//! The outputs that can be generated from the inferred columns, chosen with `--emit`. Every
+//! output other than Rust code is written from a `TypeDef`, so that they all name, document, and
+//! make optional the same fields in the same way.
+
+use crate::{
+    column::ColumnType,
+    err::TypeGenErrors,
+    generate_csv::CsvFileInfo,
+    input_args::{Emit, StringHandling},
+    json_schema, python, typescript,
+};
+use std::{fs::File, io::BufWriter};
+
+/// Writes the output for a type to a file.
+pub trait Emitter {
+    fn emit(&self, info: &CsvFileInfo, buf: &mut BufWriter<File>) -> Result<(), TypeGenErrors>;
+}
+
+impl Emit {
+    /// The emitter that writes this output
+    pub fn emitter(self) -> Box<dyn Emitter> {
+        match self {
+            Emit::Rust => Box::new(RustEmitter),
+            Emit::Sql => Box::new(SqlEmitter),
+            Emit::JsonSchema => Box::new(JsonSchemaEmitter),
+            Emit::TypeScript => Box::new(TypeScriptEmitter),
+            Emit::Python => Box::new(PythonEmitter { pydantic: false }),
+            Emit::Pydantic => Box::new(PythonEmitter { pydantic: true }),
+        }
+    }
+}
+
+/// A type and its fields, as every emitter names and documents them
+pub struct TypeDef<'a> {
+    pub typename: String,
+    pub docs: &'a [String],
+    pub fields: Vec<FieldDef<'a>>,
+    /// The fields that are the type's key, from `--key`
+    pub key: &'a [usize],
+    pub string_handling: StringHandling,
+    /// Whether enums also hold values that weren't seen, with `--catch-all` or 'enum-open'
+    pub catch_all: bool,
+}
+
+/// A field of a type, for a column of the input
+pub struct FieldDef<'a> {
+    /// The header text as it appears in the input
+    pub header: &'a str,
+    /// The name of the column, from the header, which no other column has even if their headers
+    /// are the same or empty
+    pub column: &'a str,
+    /// The name of the field, from the column name
+    pub name: String,
+    /// The name of the enum of a string field's values, with `--strings enum`
+    pub enum_name: String,
+    pub r#type: &'a ColumnType,
+    /// The values a string field can only have with 'static' or 'enum' string handling, in order,
+    /// or else empty
+    pub values: Vec<&'a str>,
+    /// The name of the enum variant for each of `values`
+    pub variants: Vec<String>,
+    pub docs: &'a [String],
+    /// Regular expressions that values must match, from the '.def' file
+    pub patterns: &'a [String],
+}
+
+impl FieldDef<'_> {
+    /// Whether the field is one of a set of values with its own named type
+    pub fn is_enum(&self, string_handling: StringHandling) -> bool {
+        string_handling.is_enum() && !self.values.is_empty()
+    }
+
+    /// Whether the field is an enum that also holds values other than `values`
+    pub fn is_open_enum(&self, def: &TypeDef) -> bool {
+        def.catch_all && self.is_enum(def.string_handling)
+    }
+}
+
+/// Rust code, from `CsvFileInfo::generate`
+struct RustEmitter;
+
+impl Emitter for RustEmitter {
+    fn emit(&self, info: &CsvFileInfo, buf: &mut BufWriter<File>) -> Result<(), TypeGenErrors> {
+        info.generate(buf)
+    }
+}
+
+/// A SQL `CREATE TABLE` statement, and the statements that load the input into it
+struct SqlEmitter;
+
+impl Emitter for SqlEmitter {
+    fn emit(&self, info: &CsvFileInfo, buf: &mut BufWriter<File>) -> Result<(), TypeGenErrors> {
+        info.write_sql(buf)
+    }
+}
+
+struct JsonSchemaEmitter;
+
+impl Emitter for JsonSchemaEmitter {
+    fn emit(&self, info: &CsvFileInfo, buf: &mut BufWriter<File>) -> Result<(), TypeGenErrors> {
+        json_schema::write_schema(buf, &info.type_def())?;
+        Ok(())
+    }
+}
+
+struct TypeScriptEmitter;
+
+impl Emitter for TypeScriptEmitter {
+    fn emit(&self, info: &CsvFileInfo, buf: &mut BufWriter<File>) -> Result<(), TypeGenErrors> {
+        typescript::write_interface(buf, &info.type_def())?;
+        Ok(())
+    }
+}
+
+/// A Python dataclass, or else a pydantic model
+struct PythonEmitter {
+    pydantic: bool,
+}
+
+impl Emitter for PythonEmitter {
+    fn emit(&self, info: &CsvFileInfo, buf: &mut BufWriter<File>) -> Result<(), TypeGenErrors> {
+        python::write_class(buf, &info.type_def(), self.pydantic)?;
+        Ok(())
+    }
+}
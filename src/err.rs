@@ -5,6 +5,13 @@ pub enum TypeGenErrors {
     IO(std::io::Error),
     Other(Cow<'static, str>),
     Csv(csv::Error),
+    Template(minijinja::Error),
+    #[cfg(feature = "parquet")]
+    Parquet(parquet::errors::ParquetError),
+    #[cfg(feature = "spreadsheet")]
+    Spreadsheet(calamine::Error),
+    #[cfg(feature = "sqlite")]
+    Sqlite(rusqlite::Error),
 }
 
 impl From<String> for TypeGenErrors {
@@ -30,3 +37,49 @@ impl From<csv::Error> for TypeGenErrors {
         TypeGenErrors::Csv(e)
     }
 }
+
+impl From<minijinja::Error> for TypeGenErrors {
+    fn from(e: minijinja::Error) -> Self {
+        TypeGenErrors::Template(e)
+    }
+}
+
+#[cfg(feature = "parquet")]
+impl From<parquet::errors::ParquetError> for TypeGenErrors {
+    fn from(e: parquet::errors::ParquetError) -> Self {
+        TypeGenErrors::Parquet(e)
+    }
+}
+
+#[cfg(feature = "spreadsheet")]
+impl From<calamine::Error> for TypeGenErrors {
+    fn from(e: calamine::Error) -> Self {
+        TypeGenErrors::Spreadsheet(e)
+    }
+}
+
+#[cfg(feature = "sqlite")]
+impl From<rusqlite::Error> for TypeGenErrors {
+    fn from(e: rusqlite::Error) -> Self {
+        TypeGenErrors::Sqlite(e)
+    }
+}
+
+impl std::fmt::Display for TypeGenErrors {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TypeGenErrors::IO(e) => write!(f, "{e}"),
+            TypeGenErrors::Other(s) => write!(f, "{s}"),
+            TypeGenErrors::Csv(e) => write!(f, "{e}"),
+            TypeGenErrors::Template(e) => write!(f, "{e:#}"),
+            #[cfg(feature = "parquet")]
+            TypeGenErrors::Parquet(e) => write!(f, "{e}"),
+            #[cfg(feature = "spreadsheet")]
+            TypeGenErrors::Spreadsheet(e) => write!(f, "{e}"),
+            #[cfg(feature = "sqlite")]
+            TypeGenErrors::Sqlite(e) => write!(f, "{e}"),
+        }
+    }
+}
+
+impl std::error::Error for TypeGenErrors {}
@@ -0,0 +1,172 @@
+//! Splits the lines of fixed-width text files into columns by byte ranges, which are either given
+//! in the '.def' file or detected from the positions that are blank in every line.
+
+use crate::inference::{InferenceOptions, InputStats};
+use std::{
+    fmt::Display,
+    fs::File,
+    io::{BufRead, BufReader},
+    path::Path,
+};
+
+/// The bytes of a column within each line. The last column may extend to the end of the line.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ColumnRange {
+    pub start: usize,
+    pub end: Option<usize>,
+}
+
+impl ColumnRange {
+    /// Parses a range of the form `start..end`, or `start..` for a column that extends to the end
+    /// of the line, with positions counted in bytes from 0.
+    pub fn parse(s: &str) -> Option<Self> {
+        let (start, end) = s.trim().split_once("..")?;
+        let start = start.trim().parse().ok()?;
+        let end = match end.trim() {
+            "" => None,
+            end => Some(end.parse().ok().filter(|&end| end > start)?),
+        };
+
+        Some(ColumnRange { start, end })
+    }
+}
+
+impl Display for ColumnRange {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.end {
+            Some(end) => write!(f, "{}..{end}", self.start),
+            None => write!(f, "{}..", self.start),
+        }
+    }
+}
+
+/// Reads the lines of a file that aren't blank, one at a time, along with their line numbers and
+/// without their line endings.
+pub fn read_lines(
+    path: &Path,
+) -> Result<impl Iterator<Item = Result<(u64, Vec<u8>), std::io::Error>>, std::io::Error> {
+    let lines = BufReader::new(File::open(path)?).split(b'\n').enumerate();
+    Ok(lines.filter_map(|(i, line)| {
+        let mut line = match line {
+            Ok(line) => line,
+            Err(e) => return Some(Err(e)),
+        };
+        if line.last() == Some(&b'\r') {
+            line.pop();
+        }
+        let blank = line.iter().all(u8::is_ascii_whitespace);
+        (!blank).then_some(Ok((i as u64 + 1, line)))
+    }))
+}
+
+/// The positions that are blank in every line seen, from which columns are detected
+#[derive(Debug, Default)]
+pub struct BlankPositions {
+    blank: Vec<bool>,
+}
+
+impl BlankPositions {
+    pub fn observe(&mut self, line: &[u8]) {
+        if line.len() > self.blank.len() {
+            self.blank.resize(line.len(), true);
+        }
+        for (i, b) in line.iter().enumerate() {
+            if !b.is_ascii_whitespace() {
+                self.blank[i] = false;
+            }
+        }
+    }
+
+    /// The columns of the lines seen. Each column starts where a run of positions that aren't
+    /// always blank begins, and extends to the start of the next.
+    pub fn ranges(&self) -> Vec<ColumnRange> {
+        let blank = &self.blank;
+        let starts = (0..blank.len())
+            .filter(|&i| !blank[i] && (i == 0 || blank[i - 1]))
+            .collect::<Vec<_>>();
+        starts
+            .iter()
+            .enumerate()
+            .map(|(j, &start)| ColumnRange {
+                start,
+                end: starts.get(j + 1).copied(),
+            })
+            .collect()
+    }
+}
+
+/// Splits a line into the trimmed text of each column, in the same way as the generated code.
+/// Columns past the end of the line are empty.
+pub fn split_line(line: &[u8], ranges: &[ColumnRange]) -> csv::StringRecord {
+    let mut record = csv::StringRecord::new();
+    for range in ranges {
+        let end = range.end.unwrap_or(usize::MAX).min(line.len());
+        let field = line.get(range.start..end).unwrap_or_default();
+        record.push_field(String::from_utf8_lossy(field).trim());
+    }
+
+    record
+}
+
+/// Infers the column types from the lines of a file, after any header line, reading no more than
+/// `InferenceOptions::num_rows` of them.
+pub fn analyze_lines(
+    lines: impl Iterator<Item = Result<(u64, Vec<u8>), std::io::Error>>,
+    ranges: &[ColumnRange],
+    options: &InferenceOptions,
+) -> Result<InputStats, std::io::Error> {
+    let mut stats = InputStats::new(options);
+    for (i, line) in lines.take(options.num_rows).enumerate() {
+        let (linenum, line) = line?;
+        let mut record = split_line(&line, ranges);
+        let mut position = csv::Position::new();
+        position.set_record(i as u64).set_line(linenum);
+        record.set_position(Some(position));
+        stats.observe(&record, options);
+    }
+
+    Ok(stats)
+}
+
+#[test]
+fn test_detect_ranges() {
+    let lines = [
+        "ID   NAME        AMOUNT",
+        "1    Ann Lee        1.5",
+        "22   Bob          100.0",
+        "",
+        "333  Cy Young          ",
+    ];
+    let mut blank = BlankPositions::default();
+    for line in lines {
+        blank.observe(line.as_bytes());
+    }
+    let ranges = blank.ranges();
+    assert_eq!(
+        ranges,
+        vec![
+            ColumnRange {
+                start: 0,
+                end: Some(5)
+            },
+            ColumnRange {
+                start: 5,
+                end: Some(17)
+            },
+            ColumnRange {
+                start: 17,
+                end: None
+            },
+        ]
+    );
+
+    let record = split_line(lines[4].as_bytes(), &ranges);
+    assert_eq!(
+        record.iter().collect::<Vec<_>>(),
+        vec!["333", "Cy Young", ""]
+    );
+
+    assert_eq!(ColumnRange::parse("5..17"), Some(ranges[1]));
+    assert_eq!(ColumnRange::parse("17.."), Some(ranges[2]));
+    assert_eq!(ColumnRange::parse("17..5"), None);
+}
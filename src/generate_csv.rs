@@ -1,56 +1,367 @@
 use crate::{
-    column::{ColumnType, IntermediateColumnType},
+    column::{ColumnInference, ColumnType},
+    emit::{FieldDef, TypeDef},
     err::TypeGenErrors,
-    input_args::{Commands, ErrorHandling, StringHandling},
-    util,
+    fixed_width::{self, ColumnRange},
+    inference::{self, ColumnStats, InferenceOptions, InputStats, Progress},
+    input_args::{
+        Commands, ErrorHandling, HeaderCheck, InputFormat, KeyMap, SampleStrategy, SqlLoad,
+        StringHandling,
+    },
+    naming,
+    profile::{self, ColumnProfile},
+    sample::SampleOptions,
+    sql, syntax, template,
+    util::{self, DefSection},
 };
+use minijinja::context;
+use proc_macro2::{Ident, Literal, TokenStream};
+use quote::{format_ident, quote, ToTokens};
 use std::{
     borrow::Cow,
-    collections::HashSet,
+    collections::{HashMap, HashSet},
     fs::File,
     io::{BufWriter, Write},
+    path::{Path, PathBuf},
 };
 
 #[derive(Debug)]
 struct CsvColumnInfo {
     column_docs: Vec<String>,
+    /// The header text as it appears in the input
+    header: String,
     name: String,
+    /// The name of the enum of a string column's values, with `--strings enum`, which no other
+    /// type has
+    enum_name: String,
     r#type: ColumnType,
+    /// The inference from which `r#type` was determined
+    inference: ColumnInference,
+    /// Statistics about the column's values, when profiling
+    profile: Option<ColumnProfile>,
     seen_values: HashSet<String>,
+    /// Regular expressions from the '.def' file that values must match when validating
+    patterns: Vec<String>,
+    /// Whether the column is absent from some of the input files
+    may_be_missing: bool,
+    /// A hash of the value in each row analyzed, for finding references between files
+    value_hashes: Option<Vec<u64>>,
+    /// The column's type in the input's own schema, when its values are converted
+    source_type: Option<String>,
+    /// The number of decimal places of a decimal column, whose values are the integer of its digits
+    scale: Option<u32>,
     error_handling: ErrorHandling,
 }
 
+/// A column whose values are all keys of another type, in batch mode
+#[derive(Debug)]
+struct Reference {
+    /// The index of the referring column
+    column: usize,
+    /// The name of the method that looks up the referenced row
+    method: String,
+    /// The module and type of the referenced rows
+    target_module: String,
+    target_type: String,
+    /// The header and Rust type of the referenced key column
+    target_column: String,
+    target_key_type: String,
+}
+
+/// The columns of a single input file and what was learned about them
+struct FileAnalysis {
+    headers: Vec<String>,
+    /// The identifier generated from each header
+    columns: Vec<String>,
+    options: InferenceOptions,
+    stats: InputStats,
+    /// The bytes of each column within a line, for fixed-width input
+    ranges: Vec<ColumnRange>,
+    /// The worksheet that was read, for spreadsheet input
+    sheet: Option<String>,
+}
+
 #[derive(Debug)]
 pub struct CsvFileInfo {
     args: crate::Commands,
+    /// The input files, after expanding any wildcards
+    input_files: Vec<PathBuf>,
+    /// Whether error types and helpers come from a shared `common` module, in batch mode
+    common_module: bool,
+    /// The columns, or combinations of columns, that are unique and never empty in the rows analyzed
+    key_candidates: Vec<Vec<usize>>,
+    /// The columns `load_map` is keyed by, from the `--key` argument
+    key: Vec<usize>,
+    /// Whether to generate an index of rows by key, because other types refer to this one
+    indexed: bool,
+    /// The columns that refer to rows of other types
+    references: Vec<Reference>,
     struct_docs: Vec<String>,
     columns: Vec<CsvColumnInfo>,
+    /// The bytes of each column within a line, for fixed-width input
+    ranges: Vec<ColumnRange>,
+    /// The worksheet that was read, for spreadsheet input
+    sheet: Option<String>,
+    /// The documentation and patterns of a `--schema`, which take the place of a '.def' file
+    schema_docs: Option<HashMap<String, DefSection>>,
 }
 
 impl CsvFileInfo {
     pub fn new(args: crate::Commands) -> Self {
         CsvFileInfo {
             args,
+            input_files: Vec::new(),
+            common_module: false,
+            key_candidates: Vec::new(),
+            key: Vec::new(),
+            indexed: false,
+            references: Vec::new(),
             struct_docs: Vec::new(),
             columns: Vec::new(),
+            ranges: Vec::new(),
+            sheet: None,
+            schema_docs: None,
         }
     }
 
+    /// Uses the error types and helpers of a shared `common` module, written by `generate_common`,
+    /// instead of generating them alongside the type.
+    pub fn with_common_module(mut self) -> Self {
+        self.common_module = true;
+        self
+    }
+
     /// Analyzes the input column types and returns the best guess for each type
     /// along with the name of each columns.
     ///
     /// If a header row is present, the values will be used and cleaned for use as
     /// a Rust identifier. If not, values will be generated from `column_0`,
     /// `column_1`, and so on.
+    ///
+    /// When there are several input files, their columns are combined. Columns missing
+    /// from some files become optional, and types are widened to fit every file.
     pub fn analyze_input(mut self) -> Result<Self, TypeGenErrors> {
-        //Result<Vec<(String, ColumnType)>, TypeGenErrors> {
+        // Arrow dictionaries are built from the variants, so they can't hold other values
+        if self.args.arrow && self.args.catch_all().is_some() {
+            return Err(
+                "--arrow can't be used with enums that hold values that weren't seen".into(),
+            );
+        }
+
+        self.input_files = match &self.args.sqlite {
+            Some(db) => vec![db.clone()],
+            None => util::expand_globs(&self.args.input_files)?,
+        };
+
+        // The generated code reads a single format
+        let format = self.args.input_format();
+        if let Some(other) = self.input_files.iter().find(|path| {
+            self.args.format.is_none()
+                && format != InputFormat::Sqlite
+                && InputFormat::from_path(path) != format
+        }) {
+            return Err(TypeGenErrors::Other(
+                format!(
+                    "{} is a different format than {}",
+                    other.display(),
+                    self.args.input_file().display()
+                )
+                .into(),
+            ));
+        }
+
+        let mut analyses = match &self.args.schema {
+            #[cfg(feature = "json-schema")]
+            Some(schema) => vec![self.analyze_schema(&schema.clone())?],
+            #[cfg(not(feature = "json-schema"))]
+            Some(_) => return Err("--schema requires the `json-schema` feature".into()),
+            None => self
+                .input_files
+                .iter()
+                .map(|path| self.analyze_file(path))
+                .collect::<Result<Vec<_>, _>>()?,
+        };
+
+        // Spreadsheets are all read from the same worksheet
+        if let Some((_, other)) = analyses
+            .iter()
+            .zip(&self.input_files)
+            .find(|(a, _)| a.sheet != analyses[0].sheet)
+        {
+            return Err(TypeGenErrors::Other(
+                format!(
+                    "{} has no worksheet named '{}'; use --sheet to choose one that all files have",
+                    other.display(),
+                    analyses[0].sheet.as_deref().unwrap_or_default(),
+                )
+                .into(),
+            ));
+        }
+
+        // Fixed-width files are all split the same way
+        if let Some((_, other)) = analyses
+            .iter()
+            .zip(&self.input_files)
+            .find(|(a, _)| a.ranges != analyses[0].ranges)
+        {
+            return Err(TypeGenErrors::Other(
+                format!(
+                    "{} has different columns than {}",
+                    other.display(),
+                    self.args.input_file().display()
+                )
+                .into(),
+            ));
+        }
+        self.ranges = analyses[0].ranges.clone();
+        self.sheet = analyses[0].sheet.clone();
+
+        let unified = if analyses.len() == 1 {
+            let analysis = analyses.pop().unwrap();
+            analysis
+                .headers
+                .into_iter()
+                .zip(analysis.columns)
+                .zip(analysis.stats.columns)
+                .map(|((header, name), stats)| (header, name, stats, false))
+                .collect::<Vec<_>>()
+        } else {
+            self.unify(analyses)
+        };
+
+        let stats = unified.iter().map(|(_, _, s, _)| s).collect::<Vec<_>>();
+        self.key_candidates = inference::detect_keys(&stats);
+        if let Some(key) = &self.args.key {
+            self.key = key
+                .split(',')
+                .map(|k| {
+                    let k = k.trim();
+                    unified
+                        .iter()
+                        .position(|(header, name, _, _)| header == k || name == k)
+                        .ok_or_else(|| {
+                            TypeGenErrors::Other(format!("Unknown key column: {k}").into())
+                        })
+                })
+                .collect::<Result<_, _>>()?;
+
+            let key_stats = self.key.iter().map(|&i| stats[i]).collect::<Vec<_>>();
+            if inference::is_unique(&key_stats) == Some(false) {
+                eprintln!("Warning: the key {key} is not unique in the rows analyzed");
+            }
+        }
+
+        self.columns = unified
+            .into_iter()
+            .map(|(header, name, stats, may_be_missing)| {
+                let r#type = stats.inference.state.finish();
+                let is_string = matches!(r#type, ColumnType::String(_));
+
+                if is_string
+                    && stats.too_many_values
+                    && self.args.string_handling != StringHandling::Owned
+                {
+                    return Err(TypeGenErrors::Other(
+                        format!("Too many unique strings in column {name}").into(),
+                    ));
+                }
+
+                Ok(CsvColumnInfo {
+                    // The generated code compares trimmed headers when the input is trimmed
+                    header: if self.args.trim_input {
+                        header.trim().to_string()
+                    } else {
+                        header
+                    },
+                    name,
+                    enum_name: String::new(),
+                    r#type,
+                    inference: stats.inference,
+                    profile: stats.profile,
+                    // Converted values are documented unless the '.def' file documents the column
+                    column_docs: stats
+                        .source_type
+                        .iter()
+                        .map(|s| s[..1].to_uppercase() + &s[1..])
+                        .collect(),
+                    seen_values: if is_string {
+                        stats.seen_values
+                    } else {
+                        HashSet::new()
+                    },
+                    patterns: Vec::new(),
+                    may_be_missing,
+                    value_hashes: stats.value_hashes,
+                    source_type: stats.source_type,
+                    scale: stats.scale,
+                    error_handling: self.args.error_handling,
+                })
+            })
+            .collect::<Result<_, TypeGenErrors>>()?;
+
+        // Only the columns that are enums are named, so other columns don't take their names
+        if self.args.string_handling.is_enum() {
+            let typename = self.args.get_typename();
+            let enums = self
+                .columns
+                .iter_mut()
+                .filter(|c| matches!(c.r#type, ColumnType::String(_)))
+                .collect::<Vec<_>>();
+            let columns = enums.iter().map(|c| c.name.as_str()).collect::<Vec<_>>();
+            let names = naming::type_names(&typename, &columns);
+            for (col, name) in enums.into_iter().zip(names) {
+                col.enum_name = name;
+            }
+        }
+
+        for &i in &self.key {
+            let col = &self.columns[i];
+            if matches!(col.r#type, ColumnType::F64(_) | ColumnType::Unit) {
+                return Err(TypeGenErrors::Other(
+                    format!(
+                        "Column {} can't be a key because it's {}",
+                        col.header, col.inference.state
+                    )
+                    .into(),
+                ));
+            }
+        }
+
+        Ok(self)
+    }
+
+    /// Analyzes the column types of a single input file.
+    fn analyze_file(&self, path: &Path) -> Result<FileAnalysis, TypeGenErrors> {
+        match self.args.input_format() {
+            InputFormat::Csv => {}
+            InputFormat::FixedWidth => return self.analyze_fixed_width(path),
+            #[cfg(feature = "parquet")]
+            InputFormat::Parquet => return self.analyze_parquet(path),
+            #[cfg(not(feature = "parquet"))]
+            InputFormat::Parquet => {
+                return Err("Parquet input requires the `parquet` feature".into());
+            }
+            #[cfg(feature = "spreadsheet")]
+            InputFormat::Spreadsheet => return self.analyze_spreadsheet(path),
+            #[cfg(not(feature = "spreadsheet"))]
+            InputFormat::Spreadsheet => {
+                return Err("Spreadsheet input requires the `spreadsheet` feature".into());
+            }
+            #[cfg(feature = "sqlite")]
+            InputFormat::Sqlite => return self.analyze_sqlite(path),
+            #[cfg(not(feature = "sqlite"))]
+            InputFormat::Sqlite => {
+                return Err("SQLite input requires the `sqlite` feature".into());
+            }
+        }
+
         let mut reader = csv::ReaderBuilder::new()
             .has_headers(!self.args.no_header)
             .delimiter(self.args.delimiter as u8)
             .flexible(true)
-            .from_path(&self.args.input_file)?;
+            .from_path(path)?;
 
-        let columns = if self.args.no_header {
+        let headers: Vec<String> = if self.args.no_header {
             // We don't know what columns we have, so we'll read the first column:
             let mut record = Default::default();
             reader.read_record(&mut record)?;
@@ -61,556 +372,3125 @@ impl CsvFileInfo {
             // Create placeholder column names
             (0..record.len()).map(|i| format!("column_{i}")).collect()
         } else {
-            reader
-                .headers()?
+            reader.headers()?.iter().map(|h| h.to_string()).collect()
+        };
+
+        let columns = naming::column_names(&headers);
+
+        let options = self.inference_options(columns.len());
+        let num_rows = options.num_rows;
+
+        let strategy = self.args.sample_strategy();
+        let stats = if num_rows == usize::MAX || strategy != SampleStrategy::Head {
+            // The whole file is read, so it's split up and analyzed in parallel
+            let threads = match self.args.threads {
+                0 => std::thread::available_parallelism().map_or(1, |n| n.get()),
+                n => n,
+            };
+            let sampling = (num_rows != usize::MAX)
+                .then(|| self.sample_options(strategy, num_rows, &headers, &columns))
+                .transpose()?;
+            let start = reader.position().clone();
+            inference::analyze_file(path, &start, &options, threads, sampling.as_ref())?
+        } else {
+            let mut progress = std::fs::metadata(path)
+                .ok()
+                .and_then(|m| Progress::new(m.len()));
+            inference::analyze_reader(&mut reader, &options, progress.as_mut())?
+        };
+        stats.report_malformed(path);
+
+        Ok(FileAnalysis {
+            headers,
+            columns,
+            options,
+            stats,
+            ranges: Vec::new(),
+            sheet: None,
+        })
+    }
+
+    /// Analyzes the column types of a Parquet file, which come from its footer.
+    #[cfg(feature = "parquet")]
+    fn analyze_parquet(&self, path: &Path) -> Result<FileAnalysis, TypeGenErrors> {
+        if self.args.no_header {
+            return Err(
+                "--no-header doesn't apply to Parquet input, which always has column names".into(),
+            );
+        }
+
+        let headers = crate::parquet_input::read_headers(path)?;
+        let columns = naming::column_names(&headers);
+        let options = self.inference_options(columns.len());
+        let stats = crate::parquet_input::analyze_file(path, &options)?;
+
+        Ok(FileAnalysis {
+            headers,
+            columns,
+            options,
+            stats,
+            ranges: Vec::new(),
+            sheet: None,
+        })
+    }
+
+    /// Analyzes the column types of a fixed-width text file. Its columns are given by `@range`
+    /// directives in its '.def' file, or else detected from the positions that are blank in every
+    /// line read, including the header. Lines are read one at a time, so detecting the columns
+    /// reads the file a second time rather than holding its lines in memory.
+    fn analyze_fixed_width(&self, path: &Path) -> Result<FileAnalysis, TypeGenErrors> {
+        let header_lines = if self.args.no_header { 0 } else { 1 };
+
+        let mut defined = Vec::new();
+        for (name, section) in util::read_doc_file(path).unwrap_or_default() {
+            if let Some(range) = section.directive("range").next() {
+                let range = ColumnRange::parse(range).ok_or_else(|| {
+                    TypeGenErrors::Other(
+                        format!("Invalid @range '{range}' for {name}; expected start..end").into(),
+                    )
+                })?;
+                defined.push((range, name));
+            }
+        }
+        defined.sort_by_key(|(range, _)| range.start);
+
+        let ranges = if defined.is_empty() {
+            // The lines read are scanned first for their blank positions, including the header
+            let mut blank = fixed_width::BlankPositions::default();
+            let read = self.num_rows().saturating_add(header_lines);
+            for line in fixed_width::read_lines(path)?.take(read) {
+                blank.observe(&line?.1);
+            }
+            let ranges = blank.ranges();
+            eprintln!(
+                "Detected columns at {}",
+                ranges
+                    .iter()
+                    .map(|r| r.to_string())
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            );
+            ranges
+        } else {
+            defined.iter().map(|(range, _)| *range).collect()
+        };
+        if ranges.is_empty() {
+            return Err(TypeGenErrors::Other(
+                format!("No columns found in {}", path.display()).into(),
+            ));
+        }
+
+        let mut lines = fixed_width::read_lines(path)?;
+        let header = if self.args.no_header {
+            None
+        } else {
+            lines.next().transpose()?
+        };
+        let headers: Vec<String> = match header {
+            Some((_, header)) => fixed_width::split_line(&header, &ranges)
                 .iter()
-                .map(crate::util::header_to_identifier)
-                .take_while(|s| !s.is_empty())
-                .collect::<Vec<_>>()
+                .enumerate()
+                .map(|(i, h)| match h {
+                    "" => format!("column_{i}"),
+                    h => h.to_string(),
+                })
+                .collect(),
+            _ if !defined.is_empty() => defined.into_iter().map(|(_, name)| name).collect(),
+            _ => (0..ranges.len()).map(|i| format!("column_{i}")).collect(),
         };
+        let columns = naming::column_names(&headers);
+
+        let options = self.inference_options(columns.len());
+        let stats = fixed_width::analyze_lines(lines, &ranges, &options)?;
+
+        Ok(FileAnalysis {
+            headers,
+            columns,
+            options,
+            stats,
+            ranges,
+            sheet: None,
+        })
+    }
 
-        let removed_unnamed_columns = columns.len() != reader.headers().unwrap().len();
+    /// Analyzes the column types of a worksheet of a spreadsheet, chosen by `--sheet` and `--range`.
+    /// The types of the cells are hints, so a column of text cells is a string even if the text is
+    /// numeric.
+    #[cfg(feature = "spreadsheet")]
+    fn analyze_spreadsheet(&self, path: &Path) -> Result<FileAnalysis, TypeGenErrors> {
+        use crate::spreadsheet_input;
 
-        let mut intermediates = (0..columns.len())
-            .map(|_| IntermediateColumnType::default())
-            .collect::<Vec<_>>();
+        let sheet =
+            spreadsheet_input::read_sheet(path, self.args.sheet.as_deref(), self.args.range)?;
+        let header_rows = if self.args.no_header { 0 } else { 1 };
+        let width = sheet.rows.first().map_or(0, |(_, cells)| cells.len());
 
-        let num_rows = match self.args.num_rows {
-            Some(0) => usize::MAX,
-            Some(n) => n,
-            None => Commands::DEFAULT_NUM_ROWS,
+        let headers: Vec<String> = match sheet.rows.first() {
+            Some((_, cells)) if !self.args.no_header => cells
+                .iter()
+                .enumerate()
+                .map(|(i, cell)| match spreadsheet_input::text(cell) {
+                    h if h.is_empty() => format!("column_{i}"),
+                    h => h,
+                })
+                .collect(),
+            _ => (0..width).map(|i| format!("column_{i}")).collect(),
         };
+        let columns = naming::column_names(&headers);
+
+        let options = self.inference_options(columns.len());
+        let rows = &sheet.rows[header_rows.min(sheet.rows.len())..];
+        let stats = spreadsheet_input::analyze_rows(rows, &options);
+
+        Ok(FileAnalysis {
+            headers,
+            columns,
+            options,
+            stats,
+            ranges: Vec::new(),
+            sheet: Some(sheet.name),
+        })
+    }
 
-        let start_position = reader.position().clone();
+    /// Analyzes the column types of the `--table` of a SQLite database, from its first rows and the
+    /// declared types of its columns.
+    #[cfg(feature = "sqlite")]
+    fn analyze_sqlite(&self, path: &Path) -> Result<FileAnalysis, TypeGenErrors> {
+        use crate::sqlite_input;
 
-        for (linenum, row) in reader
-            .records()
-            .map_while(Result::ok)
-            .take(num_rows)
-            .enumerate()
+        if self.args.no_header {
+            return Err(
+                "--no-header doesn't apply to SQLite input, which always has column names".into(),
+            );
+        }
+
+        let table = self.args.table.as_deref().unwrap_or_default();
+        let conn = sqlite_input::open(path)?;
+        let table_columns = sqlite_input::read_columns(&conn, path, table)?;
+        let headers = table_columns
+            .iter()
+            .map(|c| c.name.clone())
+            .collect::<Vec<_>>();
+        let columns = naming::column_names(&headers);
+
+        let options = self.inference_options(columns.len());
+        let stats = sqlite_input::analyze_table(&conn, table, &table_columns, &options)?;
+
+        Ok(FileAnalysis {
+            headers,
+            columns,
+            options,
+            stats,
+            ranges: Vec::new(),
+            sheet: None,
+        })
+    }
+
+    /// Reads the columns from the properties of a JSON Schema, instead of analyzing input files.
+    /// Its descriptions and patterns are kept to document the columns, in place of a '.def' file.
+    #[cfg(feature = "json-schema")]
+    fn analyze_schema(&mut self, path: &Path) -> Result<FileAnalysis, TypeGenErrors> {
+        use crate::column::IntermediateColumnType;
+
+        let (docs, properties) = crate::json_schema::read_schema(path)?;
+        let headers = properties
+            .iter()
+            .map(|p| p.header.clone())
+            .collect::<Vec<_>>();
+        let columns = naming::column_names(&headers);
+
+        let options = self.inference_options(columns.len());
+        let mut stats = InputStats::new(&options);
+        let mut sections = HashMap::from([(
+            String::new(),
+            DefSection {
+                docs,
+                directives: Vec::new(),
+            },
+        )]);
+
+        for ((property, name), column) in
+            properties.into_iter().zip(&columns).zip(&mut stats.columns)
         {
-            if !removed_unnamed_columns && row.len() != columns.len() {
+            if self.args.string_handling != StringHandling::Owned
+                && matches!(property.state, IntermediateColumnType::String(_))
+                && property.values.is_empty()
+            {
                 return Err(TypeGenErrors::Other(
                     format!(
-                        "Expected {} columns but found {} on line {linenum}",
-                        columns.len(),
-                        row.len()
+                        "Property {:?} has no enum of values for --strings static or enum",
+                        property.header
                     )
                     .into(),
                 ));
             }
 
-            for index in 0..columns.len() {
-                //println!("{} @ {index}", &row[index]);
-                if self.args.trim_input {
-                    intermediates[index].agg(row[index].trim());
-                } else {
-                    intermediates[index].agg(&row[index]);
+            column.inference = ColumnInference::from_state(property.state);
+            column.seen_values = property.values.into_iter().collect();
+            // There are no rows to find keys in
+            column.value_hashes = None;
+            sections.insert(
+                name.clone(),
+                DefSection {
+                    docs: property.docs,
+                    directives: property
+                        .patterns
+                        .into_iter()
+                        .map(|p| ("pattern".to_string(), p))
+                        .collect(),
+                },
+            );
+        }
+        self.schema_docs = Some(sections);
+
+        Ok(FileAnalysis {
+            headers,
+            columns,
+            options,
+            stats,
+            ranges: Vec::new(),
+            sheet: None,
+        })
+    }
+
+    /// How many rows are analyzed, from `--num-rows`
+    fn num_rows(&self) -> usize {
+        match self.args.num_rows {
+            Some(0) => usize::MAX,
+            Some(n) => n,
+            None => Commands::DEFAULT_NUM_ROWS,
+        }
+    }
+
+    /// What to collect while analyzing an input with `num_columns` columns, according to the arguments
+    fn inference_options(&self, num_columns: usize) -> InferenceOptions {
+        InferenceOptions {
+            num_columns,
+            num_rows: self.num_rows(),
+            trim_input: self.args.trim_input,
+            // If we're not going to yield owned strings, we will need to collect the set of known values.
+            // When checking the values of owned strings, they are collected too, but only used if
+            // there are few enough of them.
+            collect_values: self.args.string_handling != StringHandling::Owned
+                || self.args.validate_values,
+            max_values: self.args.max_strings.unwrap_or(usize::MAX),
+            profile: self.args.profile.is_some(),
+            // In batch mode, keys are detected to find the columns that refer to other types
+            detect_keys: self.args.detect_keys
+                || self.args.key.is_some()
+                || self.args.dir.is_some(),
+            delimiter: self.args.delimiter as u8,
+        }
+    }
+
+    /// Combines the columns of several input files, in the order they're first seen, and prints
+    /// a summary of where the files disagree with the combined schema. Returns the header, name,
+    /// and statistics of each column, and whether it's missing from some files.
+    fn unify(&self, analyses: Vec<FileAnalysis>) -> Vec<(String, String, ColumnStats, bool)> {
+        let mut unified: Vec<(String, String, ColumnStats, usize)> = Vec::new();
+        let mut file_types = Vec::new();
+
+        for analysis in analyses {
+            let types = analysis
+                .columns
+                .iter()
+                .zip(&analysis.stats.columns)
+                .map(|(name, stats)| (name.clone(), stats.inference.state))
+                .collect::<Vec<_>>();
+            file_types.push((analysis.stats.rows, types));
+
+            let columns = analysis.headers.into_iter().zip(analysis.columns);
+            for ((header, name), stats) in columns.zip(analysis.stats.columns) {
+                match unified.iter_mut().find(|(_, n, _, _)| *n == name) {
+                    Some((_, _, existing, present)) => {
+                        existing.merge(stats, &analysis.options);
+                        *present += 1;
+                    }
+                    None => unified.push((header, name, stats, 1)),
+                }
+            }
+        }
+
+        for (_, _, stats, present) in &mut unified {
+            if *present < file_types.len() {
+                stats.inference.mark_missing();
+            }
+        }
+
+        eprintln!("Inferred from {} files:", file_types.len());
+        for (path, (rows, types)) in self.input_files.iter().zip(&file_types) {
+            let mut differences = Vec::new();
+            for (_, name, stats, _) in &unified {
+                match types.iter().find(|(n, _)| n == name) {
+                    None => differences.push(format!("missing {name}")),
+                    Some((_, t)) if *t != stats.inference.state => {
+                        // Integer ranges differ between almost any two files, so only the kinds are compared
+                        if t.to_string() != stats.inference.state.to_string() {
+                            differences.push(format!("{name} is {t}"));
+                        }
+                    }
+                    Some(_) => {}
+                }
+            }
+
+            if differences.is_empty() {
+                eprintln!("    {}: {rows} rows", path.display());
+            } else {
+                eprintln!(
+                    "    {}: {rows} rows; {}",
+                    path.display(),
+                    differences.join(", ")
+                );
+            }
+        }
+        eprintln!();
+
+        unified
+            .into_iter()
+            .map(|(header, name, stats, present)| (header, name, stats, present < file_types.len()))
+            .collect()
+    }
+
+    /// Determines how to sample `num_rows` rows of the input, given its headers and the
+    /// identifiers generated from them.
+    fn sample_options(
+        &self,
+        strategy: SampleStrategy,
+        num_rows: usize,
+        headers: &[String],
+        columns: &[String],
+    ) -> Result<SampleOptions, TypeGenErrors> {
+        let stratify = match &self.args.stratify {
+            Some(key) => {
+                let found = headers
+                    .iter()
+                    .zip(columns)
+                    .position(|(h, c)| h == key || c == key);
+                match found {
+                    Some(i) => Some(i),
+                    None => {
+                        return Err(TypeGenErrors::Other(
+                            format!("Unknown column to stratify by: {key}").into(),
+                        ))
+                    }
                 }
             }
+            None => None,
+        };
+
+        Ok(SampleOptions {
+            strategy,
+            size: num_rows,
+            seed: self.args.seed.unwrap_or_default(),
+            stratify,
+            trim_input: self.args.trim_input,
+        })
+    }
+
+    /// Prints the type inferred for each column to stderr, along with the values that caused its type to widen.
+    pub fn write_explanation(&self) {
+        for col in &self.columns {
+            eprintln!("{}: {}", col.header, col.as_str(self.args.string_handling));
+            eprintln!("    {}", col.reason());
+            if col.may_be_missing {
+                eprintln!("    missing from some input files");
+            }
+            for widening in col.inference.widenings() {
+                eprintln!("    {widening}");
+            }
+        }
+        eprintln!();
+    }
+
+    /// Writes a report of the statistics collected for each column during `analyze_input`,
+    /// according to the `--profile` and `--profile-output` arguments.
+    pub fn write_profile(&self) -> Result<(), TypeGenErrors> {
+        let format = self
+            .args
+            .profile
+            .unwrap_or(crate::input_args::ProfileFormat::Text);
+        let default_profile = ColumnProfile::default();
+        let columns = self
+            .columns
+            .iter()
+            .map(|c| {
+                let profile = c.profile.as_ref().unwrap_or(&default_profile);
+                (c.header.as_str(), &c.inference, profile)
+            })
+            .collect::<Vec<_>>();
+
+        match &self.args.profile_output {
+            Some(path) => {
+                let mut buf = BufWriter::new(File::create(path)?);
+                profile::write_profile(&mut buf, format, &columns)?;
+            }
+            None => {
+                let mut buf = std::io::stdout().lock();
+                profile::write_profile(&mut buf, format, &columns)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Writes a SQL `CREATE TABLE` statement for the columns in `--dialect`, for `--emit sql`,
+    /// followed by the statements from `--sql-load`. The table is named like the output file.
+    pub fn write_sql(&self, buf: &mut impl Write) -> Result<(), TypeGenErrors> {
+        let table = self.args.output_name();
+
+        let def = self.type_def();
+        sql::write_create_table(buf, self.args.dialect, &table, &def)?;
+
+        if let Some(load) = self.args.sql_load {
+            if load == SqlLoad::Copy && self.args.input_format() != InputFormat::Csv {
+                return Err("--sql-load copy requires CSV input; use --sql-load insert".into());
+            }
+            writeln!(buf)?;
+            sql::write_load(
+                buf,
+                &self.args,
+                load,
+                &table,
+                &def.fields,
+                &self.input_files,
+            )?;
+        }
+
+        Ok(())
+    }
+
+    /// The type and its fields, as the emitters other than Rust see them
+    pub fn type_def(&self) -> TypeDef<'_> {
+        TypeDef {
+            typename: self.args.get_typename(),
+            docs: &self.struct_docs,
+            fields: self
+                .columns
+                .iter()
+                .map(|c| FieldDef {
+                    header: &c.header,
+                    column: &c.name,
+                    name: c.field_name(),
+                    enum_name: c.enum_name(),
+                    r#type: &c.r#type,
+                    values: c.known_values(self.args.string_handling),
+                    variants: c.variants(&self.args),
+                    docs: &c.column_docs,
+                    patterns: &c.patterns,
+                })
+                .collect(),
+            key: &self.key,
+            string_handling: self.args.string_handling,
+            catch_all: self.args.catch_all().is_some(),
         }
+    }
+
+    pub fn load_data_def(mut self) -> Self {
+        let sections = match self.schema_docs.take() {
+            Some(sections) => Some(sections),
+            None => {
+                // A SQLite table is documented by a '.def' file named after it, next to the database
+                let documented = match &self.args.table {
+                    Some(table) => self.input_files[0].with_file_name(format!("{table}.db")),
+                    None => self.input_files[0].clone(),
+                };
+                util::read_doc_file(&documented)
+            }
+        };
+        if let Some(mut sections) = sections {
+            if let Some(section) = sections.remove("") {
+                self.struct_docs = section.docs;
+            }
+
+            for column in self.columns.iter_mut() {
+                if let Some(section) = sections.remove(&column.name) {
+                    column.patterns = section.directive("pattern").map(String::from).collect();
+                    column.column_docs = section.docs;
+                }
+            }
+        };
+
+        self
+    }
+
+    /// Generates the Rust code for the type by rendering the `--template`, or else the built-in
+    /// template, with the inferred columns. The rendered code is parsed and formatted, so a
+    /// template that writes invalid Rust is an error.
+    pub fn generate(&self, buf: &mut impl Write) -> Result<(), TypeGenErrors> {
+        let code = template::code(|code| {
+            template::render(
+                self.args.template.as_deref(),
+                self.template_context()?,
+                code,
+            )
+        })?;
+        syntax::write_formatted(&code, buf)
+    }
 
-        reader.seek(start_position).unwrap();
+    /// The context the template is rendered with. The struct and the enums of its string columns
+    /// are written by the template from their names, types and docs, and the rest of the code is
+    /// written here.
+    fn template_context(&self) -> Result<minijinja::Value, TypeGenErrors> {
+        self.check_names()?;
+        let typename = self.args.get_typename();
+        let string_handling = self.args.string_handling;
+
+        // If string handling is 'static' or 'enum', we can derive 'Copy' on the type, unless its
+        // enums hold unseen values
+        let derives = if self.strings_are_copy() {
+            vec!["Copy", "Clone", "Debug"]
+        } else {
+            vec!["Clone", "Debug"]
+        };
 
-        // If we're not going to yield owned strings, we will need to collect the set of known values
-        let mut seen_values = (0..columns.len())
-            .map(|_| std::collections::HashSet::new())
+        let fields = self
+            .columns
+            .iter()
+            .map(|col| {
+                let mut comments = Vec::new();
+                if self.args.explain {
+                    comments.push(format!("Inferred because {}", col.reason()));
+                    comments.extend(col.inference.widenings().iter().map(|w| w.to_string()));
+                    if col.may_be_missing {
+                        comments.push("Missing from some input files".to_string());
+                    }
+                }
+
+                context! {
+                    name => col.field_name(),
+                    header => col.header,
+                    column => col.name,
+                    type => col.as_str(string_handling),
+                    optional => col.r#type.is_optional(),
+                    values => col.known_values(string_handling),
+                    docs => col.column_docs,
+                    patterns => col.patterns,
+                    comments,
+                }
+            })
             .collect::<Vec<_>>();
 
-        if self.args.string_handling != StringHandling::Owned {
-            for row in reader.records().map_while(Result::ok).take(num_rows) {
-                for index in 0..columns.len() {
-                    // Only need to do anything if this is a string column
-                    if matches!(intermediates[index], IntermediateColumnType::String(_)) {
-                        seen_values[index].insert(row[index].to_string());
-
-                        // Now that we've inserted a value, check if we exceed a (possibly) specified cardinality limit
-                        if let Some(max_strings) = self.args.max_strings {
-                            if seen_values[index].len() > max_strings {
-                                return Err(TypeGenErrors::Other(
-                                    format!("Too many unique strings in column {}", columns[index])
-                                        .into(),
-                                ));
-                            }
-                        }
+        let mut enums = Vec::new();
+        if string_handling.is_enum() {
+            for (i, col) in self.columns.iter().enumerate() {
+                if !matches!(col.r#type, ColumnType::String(_)) {
+                    continue;
+                }
+
+                // Key columns are also hashable and ordered, so they can be used in maps
+                let mut derives = vec!["Copy", "Clone", "Debug", "PartialEq", "Eq"];
+                if !self.strings_are_copy() {
+                    derives.remove(0);
+                }
+                if self.key.contains(&i) {
+                    derives.extend(["Hash", "PartialOrd", "Ord"]);
+                }
+                let variants = col
+                    .known_values(self.args.string_handling)
+                    .into_iter()
+                    .zip(col.variants(&self.args))
+                    .map(|(value, name)| {
+                        let literal = syntax::str_lit(value).to_string();
+                        context! { name, value, literal }
+                    })
+                    .collect::<Vec<_>>();
+
+                enums.push(context! {
+                    name => col.enum_name(),
+                    derives,
+                    variants,
+                    catch_all => self.args.catch_all(),
+                });
+            }
+        }
+
+        let key_type = (!self.key.is_empty()).then(|| self.key_type());
+        let error_type =
+            (self.args.error_handling == ErrorHandling::Result).then(|| format!("{typename}Error"));
+        let error_handling = match self.args.error_handling {
+            ErrorHandling::IgnoreRow => "ignore",
+            ErrorHandling::Result => "result",
+            ErrorHandling::Panic => "panic",
+        };
+        let string_handling = match string_handling {
+            StringHandling::Owned => "owned",
+            StringHandling::Static => "static",
+            StringHandling::Enum(_) => "enum",
+            StringHandling::EnumOpen => "enum-open",
+        };
+
+        let code = context! {
+            errors => self.error_types(&typename).to_string(),
+            methods => self.methods(&typename)?.to_string(),
+            reader => self.reader(&typename).to_string(),
+            test => self.test(&typename).to_string(),
+        };
+
+        Ok(context! {
+            typename,
+            docs => self.struct_docs,
+            derives,
+            fields,
+            enums,
+            key_type,
+            error_type,
+            error_handling,
+            string_handling,
+            code,
+        })
+    }
+
+    /// Fails if a name that the generated code is built from isn't an identifier. Names come from
+    /// the input, so they're checked once here rather than wherever the code uses them.
+    fn check_names(&self) -> Result<(), TypeGenErrors> {
+        syntax::check_ident(&self.args.get_typename())?;
+        for col in &self.columns {
+            syntax::check_ident(&col.field_name())?;
+            if self.args.string_handling.is_enum() && matches!(col.r#type, ColumnType::String(_)) {
+                syntax::check_ident(&col.enum_name())?;
+                for variant in col.variants(&self.args) {
+                    syntax::check_ident(&variant)?;
+                }
+            }
+        }
+        for r in &self.references {
+            syntax::check_ident(&r.method)?;
+            syntax::check_ident(&r.target_module)?;
+        }
+        Ok(())
+    }
+
+    /// The error types, or in batch mode the imports of those in the `common` module.
+    fn error_types(&self, typename: &str) -> TokenStream {
+        if !self.common_module {
+            let error = (self.args.error_handling == ErrorHandling::Result)
+                .then(|| self.error_type(typename));
+            let validation_error = self
+                .args
+                .validate
+                .then(|| self.validation_error_type(typename));
+            return quote! { #error #validation_error };
+        }
+
+        let mut helpers = Vec::new();
+        if !self.args.no_header {
+            helpers.push(format_ident!("check_headers"));
+        }
+        match self.args.input_format() {
+            InputFormat::FixedWidth => helpers.push(format_ident!("split_fixed_width")),
+            InputFormat::Spreadsheet => helpers.push(format_ident!("cell_text")),
+            InputFormat::Sqlite => helpers.push(format_ident!("sqlite_text")),
+            InputFormat::Parquet => helpers.push(format_ident!("parquet_text")),
+            InputFormat::Csv => {}
+        }
+        let imports = match &helpers[..] {
+            [] => quote! {},
+            [helper] => quote! { use super::common::#helper; },
+            _ => quote! { use super::common::{#(#helpers),*}; },
+        };
+        let error = (self.args.error_handling == ErrorHandling::Result).then(|| {
+            let error = format_ident!("{typename}Error");
+            quote! { pub type #error = super::common::Error; }
+        });
+        let validation_error = self.args.validate.then(|| {
+            let validation_error = format_ident!("{typename}ValidationError");
+            quote! { pub type #validation_error = super::common::ValidationError; }
+        });
+
+        quote! { #imports #error #validation_error }
+    }
+
+    /// The `impl` block of the type's constants and the functions that load it.
+    fn methods(&self, typename: &str) -> Result<TokenStream, TypeGenErrors> {
+        let name = format_ident!("{typename}");
+        let num_columns = Literal::usize_unsuffixed(self.columns.len());
+        let columns = self.columns.iter().map(|col| {
+            let name = col.field_name();
+            let r#type = col.as_str(self.args.string_handling);
+            quote! { (#name, #r#type) }
+        });
+        let headers = self.columns.iter().map(|col| col.header.as_str());
+        let may_be_missing = self.may_be_missing().then(|| {
+            let values = self.columns.iter().map(|col| col.may_be_missing);
+            quote! {
+                /// Whether each column may be absent from an input file, in which case its values are `None`.
+                pub const MAY_BE_MISSING: [bool; #num_columns] = [#(#values),*];
+            }
+        });
+        let scales = self.columns.iter().filter_map(|col| {
+            let scale = Literal::u32_unsuffixed(col.scale?);
+            let field_name = col.field_name();
+            let name = format_ident!("{}_SCALE", field_name.to_uppercase());
+            let doc = syntax::doc(&format!(
+                "The number of decimal places of `{field_name}`, whose values are the integer of the decimal's digits."
+            ));
+            Some(quote! {
+                #doc
+                pub const #name: u32 = #scale;
+            })
+        });
+        let schema_hash = format!("{:#018x}", self.schema_hash())
+            .parse::<Literal>()
+            .unwrap();
+
+        let validate = if self.args.validate {
+            Some(self.validate(typename)?)
+        } else {
+            None
+        };
+        let load = match self.args.input_format() {
+            InputFormat::Csv => self.load_csv(typename),
+            InputFormat::Parquet => self.load_parquet(typename),
+            InputFormat::FixedWidth => self.load_fixed_width(typename),
+            InputFormat::Spreadsheet => self.load_sheet(typename),
+            InputFormat::Sqlite => self.load_sqlite(typename),
+        };
+        let load_map = (!self.key.is_empty()).then(|| self.load_map(typename));
+        let references = (!self.references.is_empty()).then(|| self.reference_methods(typename));
+        let load_columns = self.args.columns.then(|| self.load_columns(typename));
+        let arrow_schema = self.args.arrow.then(|| self.arrow_schema(typename));
+
+        Ok(quote! {
+            impl #name {
+                /// The `(field name, type)` associated with each column.
+                pub const COLUMNS: [(&'static str, &'static str); #num_columns] = [#(#columns),*];
+
+                /// The header text each column is read from.
+                pub const HEADERS: [&'static str; #num_columns] = [#(#headers),*];
+
+                #may_be_missing
+                #(#scales)*
+
+                /// A fingerprint of the column headers and types this code was generated from.
+                pub const SCHEMA_HASH: u64 = #schema_hash;
+
+                #validate
+                #load
+                #load_map
+                #references
+                #load_columns
+                #arrow_schema
+            }
+        })
+    }
+
+    /// The iterator that reads rows, and the types and helpers it's used with.
+    fn reader(&self, typename: &str) -> TokenStream {
+        let name = format_ident!("{typename}");
+        let iterator = format_ident!("{typename}Iterator");
+        let index = self.indexed.then(|| self.index_type(typename));
+        let columns = self.args.columns.then(|| self.columns_type(typename));
+        let record_batches = self.args.arrow.then(|| self.record_batches(typename));
+        let helpers = (!self.common_module).then(|| self.helpers());
+
+        let source = match self.args.input_format() {
+            InputFormat::Csv => quote! { records: csv::StringRecordsIntoIter<std::fs::File>, },
+            InputFormat::Parquet => quote! { rows: parquet::record::reader::RowIter<'static>, },
+            InputFormat::FixedWidth => quote! {
+                lines: std::io::Split<std::io::BufReader<std::fs::File>>,
+                /// The line number of the line most recently read
+                linenum: u64,
+            },
+            InputFormat::Spreadsheet => quote! {
+                /// The text of each row, with its row number in the worksheet
+                rows: std::vec::IntoIter<(u64, csv::StringRecord)>,
+            },
+            InputFormat::Sqlite => {
+                let (error, _) = self.sqlite_row_error(typename);
+                quote! {
+                    /// The text of each row, positioned at its row number
+                    rows: Box<dyn Iterator<Item = Result<csv::StringRecord, #error>> + Send>,
+                }
+            }
+        };
+        let num_columns = Literal::usize_unsuffixed(self.columns.len());
+
+        let (next_record, read_record) = match self.args.input_format() {
+            InputFormat::Csv => (quote! { self.records.next()? }, None),
+            InputFormat::Parquet => (
+                quote! { self.next_record()? },
+                Some(self.next_parquet_record(typename)),
+            ),
+            InputFormat::FixedWidth => (
+                quote! { self.next_record()? },
+                Some(self.next_fixed_width_record(typename)),
+            ),
+            InputFormat::Spreadsheet => (
+                quote! { self.next_record()? },
+                Some(self.next_sheet_record(typename)),
+            ),
+            InputFormat::Sqlite => (
+                quote! { self.next_record()? },
+                Some(self.next_sqlite_record(typename)),
+            ),
+        };
+
+        // Duplicate keys and missing references are reported by line number
+        let linenum = ((!self.key.is_empty()
+            && self.args.error_handling != ErrorHandling::IgnoreRow)
+            || !self.references.is_empty())
+        .then(|| {
+            quote! {
+                impl #iterator {
+                    /// The line number of the row most recently read
+                    fn linenum(&self) -> u64 {
+                        self.row.position().map_or(0, |p| p.line())
+                    }
+                }
+            }
+        });
+
+        let item = match self.args.error_handling {
+            ErrorHandling::Result => {
+                let error = format_ident!("{typename}Error");
+                quote! { Result<#name, #error> }
+            }
+            _ => quote! { #name },
+        };
+
+        let read_row = match self.args.error_handling {
+            // Because we're ignoring errors, the caller loops until a row is valid
+            ErrorHandling::IgnoreRow => quote! {
+                self.row = match #next_record {
+                    Ok(r) => r,
+                    Err(_) => continue,
+                };
+            },
+            ErrorHandling::Result => {
+                // SQLite rows are read with their errors already converted
+                let error = if self.args.input_format() == InputFormat::Sqlite {
+                    quote! { e }
+                } else {
+                    quote! { e.into() }
+                };
+                quote! {
+                    self.row = match #next_record {
+                        Ok(r) => r,
+                        Err(e) => return Some(Err(#error)),
+                    };
+                let linenum = self.row.position().unwrap().line();
+                }
+            }
+            ErrorHandling::Panic => quote! {
+                self.row = match #next_record {
+                    Ok(r) => r,
+                    Err(_) => panic!("Failed to get row"),
+                };
+                let linenum = self.row.position().unwrap().line();
+            },
+        };
+
+        // Extract each column, then create the struct that will be returned
+        let values = self
+            .columns
+            .iter()
+            .enumerate()
+            .map(|(i, col)| self.read_value(i, col));
+        let fields = self
+            .columns
+            .iter()
+            .map(|col| syntax::ident(&col.field_name()));
+
+        let validate = self.args.validate.then(|| match self.args.error_handling {
+            ErrorHandling::IgnoreRow => quote! {
+                if res.validate().is_err() {
+                    continue;
+                }
+            },
+            ErrorHandling::Result => quote! {
+                if let Err(e) = res.validate() {
+                    return Some(Err((linenum, e).into()));
+                }
+            },
+            ErrorHandling::Panic => quote! {
+                if let Err(e) = res.validate() {
+                    panic!("Invalid row at line={linenum}: {e:?}");
+                }
+            },
+        });
+
+        let row = quote! {
+            #read_row
+            #(#values)*
+            let res = #name { #(#fields),* };
+            #validate
+        };
+        let next = match self.args.error_handling {
+            ErrorHandling::IgnoreRow => quote! {
+                loop {
+                    #row
+                    return Some(res);
+                }
+            },
+            ErrorHandling::Result => quote! {
+                #row
+                Some(Ok(res))
+            },
+            ErrorHandling::Panic => quote! {
+                #row
+                Some(res)
+            },
+        };
+
+        quote! {
+            #index
+            #columns
+            #record_batches
+            #helpers
+
+            pub struct #iterator {
+                #source
+                row: csv::StringRecord,
+                /// The position of each column in the input
+                indices: [usize; #num_columns],
+            }
+
+            #read_record
+            #linenum
+
+            impl Iterator for #iterator {
+                type Item = #item;
+
+                fn next(&mut self) -> Option<Self::Item> {
+                    #next
+                }
+            }
+        }
+    }
+
+    /// The statement that reads the value of column `i` from the row, in the iterator's `next`.
+    fn read_value(&self, i: usize, col: &CsvColumnInfo) -> TokenStream {
+        let CsvColumnInfo {
+            column_docs: _,
+            header: _,
+            name: _,
+            enum_name: _,
+            r#type,
+            inference: _,
+            profile: _,
+            seen_values: _,
+            patterns: _,
+            may_be_missing,
+            value_hashes: _,
+            source_type: _,
+            scale: _,
+            error_handling,
+        } = col;
+
+        let optional = r#type.is_optional();
+        let snake_name = col.field_name();
+        let field = syntax::ident(&snake_name);
+
+        if r#type == &ColumnType::Unit {
+            return quote! { let #field = (); };
+        }
+
+        let index = Literal::usize_unsuffixed(i);
+        let value = if self.args.trim_input {
+            quote! { self.row.get(self.indices[#index]).map(|s| s.trim()) }
+        } else {
+            quote! { self.row.get(self.indices[#index]) }
+        };
+
+        // Can't get a value from the CSV reader
+        let missing = if *may_be_missing {
+            // The column isn't in this file
+            quote! { None }
+        } else {
+            match self.args.error_handling {
+                ErrorHandling::IgnoreRow => quote! { continue },
+                ErrorHandling::Result => quote! { return Some(Err((linenum, #snake_name).into())) },
+                ErrorHandling::Panic => {
+                    let message =
+                        format!("Failed to get '{snake_name}' at line={{linenum}} column={i}");
+                    quote! { panic!(#message) }
+                }
+            }
+        };
+        let empty = optional.then(|| quote! { Some("") => None, });
+
+        let error = match error_handling {
+            ErrorHandling::IgnoreRow => quote! { continue },
+            ErrorHandling::Result => {
+                quote! { return Some(Err((linenum, #snake_name, val).into())) }
+            }
+            ErrorHandling::Panic => {
+                let message = format!(
+                    "Unexpected '{snake_name}' value '{{val}}' at line={{linenum}} column={i}"
+                );
+                quote! { panic!(#message) }
+            }
+        };
+        let some = |value: TokenStream| {
+            if optional {
+                quote! { Some(#value) }
+            } else {
+                value
+            }
+        };
+        let parsed = some(quote! { v });
+        let parse = quote! {
+            Some(val) => match val.parse() {
+                Ok(v) => #parsed,
+                Err(_) => #error,
+            },
+        };
+
+        let values = match r#type {
+            ColumnType::String(_) => match self.args.string_handling {
+                StringHandling::Owned => {
+                    let owned = some(quote! { val.to_owned() });
+                    quote! { Some(val) => #owned, }
+                }
+                StringHandling::Static => {
+                    let known =
+                        col.known_values(self.args.string_handling)
+                            .into_iter()
+                            .map(|value| {
+                                let known = some(quote! { #value });
+                                quote! { Some(#value) => #known, }
+                            });
+                    quote! {
+                        #(#known)*
+                        Some(val) => #error,
+                    }
+                }
+                StringHandling::Enum(_) | StringHandling::EnumOpen => parse,
+            },
+            ColumnType::Bool(_) => {
+                // Bools will do case-insensitive comparisons for 'true'/'false'
+                let (t, f) = (some(quote! { true }), some(quote! { false }));
+                quote! {
+                    Some(val) if val.eq_ignore_ascii_case("true") => #t,
+                    Some(val) if val.eq_ignore_ascii_case("false") => #f,
+                    Some(val) => #error,
+                }
+            }
+            _ => parse,
+        };
+
+        quote! {
+            let #field = match #value {
+                None => #missing,
+                #empty
+                #values
+            };
+        }
+    }
+
+    /// A test that loads the first input file.
+    fn test(&self, typename: &str) -> TokenStream {
+        // A type generated from a schema alone has no file to test with
+        let Some(input_file) = self.input_files.first() else {
+            return TokenStream::new();
+        };
+
+        let name = format_ident!("{typename}");
+        let (load, _) = self.loader();
+        let input_file = input_file.to_string_lossy();
+        let rows = match self.args.error_handling {
+            // for the result type, our sample will flatten Result<T,E> out to T
+            ErrorHandling::Result => quote! {
+                #name::#load(#input_file)
+                    .expect("Couldn't load file")
+                    .map_while(Result::ok)
+            },
+            // other kinds will always get T
+            _ => quote! { #name::#load(#input_file).expect("Couldn't load file") },
+        };
+
+        quote! {
+            #[test]
+            fn test_load() {
+                for row in #rows {
+                    println!("Got row: {row:?}");
+                }
+            }
+        }
+    }
+
+    /// Computes a fingerprint of the inferred schema from each column's header, type, and (for
+    /// 'static' and 'enum' string handling) the set of known values.
+    fn schema_hash(&self) -> u64 {
+        let mut hasher = util::Fnv1a::new();
+
+        for col in &self.columns {
+            hasher.write(col.header.as_bytes());
+            hasher.write(b"\0");
+            hasher.write(col.as_str(self.args.string_handling).as_bytes());
+
+            // Owned strings may still collect their values, such as for `--validate-values`, but those
+            // values aren't part of the type
+            if self.args.string_handling != StringHandling::Owned {
+                let mut seen_values = col.seen_values.iter().collect::<Vec<_>>();
+                seen_values.sort();
+                for value in seen_values {
+                    hasher.write(b"\0");
+                    hasher.write(value.as_bytes());
+                }
+            }
+
+            hasher.write(b"\n");
+        }
+
+        hasher.finish()
+    }
+
+    /// Writes the contents of the `common` module shared by the types generated in batch mode:
+    /// the error types and the `check_headers` function.
+    pub fn generate_common(&self, buf: &mut impl Write) -> Result<(), TypeGenErrors> {
+        syntax::write_formatted(&self.common().to_string(), buf)
+    }
+
+    fn common(&self) -> TokenStream {
+        let error =
+            (self.args.error_handling == ErrorHandling::Result).then(|| self.error_type(""));
+        let validation_error = self.args.validate.then(|| self.validation_error_type(""));
+        let helpers = self.helpers();
+
+        quote! {
+            //! Error types and helpers shared by the types in this module.
+
+            #error
+            #validation_error
+            #helpers
+        }
+    }
+
+    /// The helper functions that load functions use: `check_headers`, and the function that
+    /// reads the text of each value, for input formats that need one.
+    fn helpers(&self) -> TokenStream {
+        let check_headers = (!self.args.no_header).then(|| self.check_headers());
+        let text = match self.args.input_format() {
+            InputFormat::FixedWidth => Some(self.split_fixed_width()),
+            InputFormat::Spreadsheet => Some(self.cell_text()),
+            InputFormat::Sqlite => Some(self.sqlite_text()),
+            InputFormat::Parquet => Some(self.parquet_text()),
+            InputFormat::Csv => None,
+        };
+
+        quote! { #check_headers #text }
+    }
+
+    /// The `{typename}Error` type returned when rows can't be read, and its conversions.
+    fn error_type(&self, typename: &str) -> TokenStream {
+        let error = format_ident!("{typename}Error");
+        let validation_error = format_ident!("{typename}ValidationError");
+        let (_, load_error) = self.loader();
+        let variant = match self.args.input_format() {
+            InputFormat::Csv => format_ident!("CsvError"),
+            InputFormat::Parquet => format_ident!("ParquetError"),
+            InputFormat::FixedWidth => format_ident!("IoError"),
+            InputFormat::Spreadsheet => format_ident!("SpreadsheetError"),
+            InputFormat::Sqlite => format_ident!("SqliteError"),
+        };
+
+        // From `load_csv`, which reads a CSV export of the table
+        let sqlite = self.args.input_format() == InputFormat::Sqlite;
+        let csv_error = sqlite.then(|| quote! { CsvError(csv::Error), });
+        let from_csv_error = sqlite.then(|| {
+            quote! {
+                impl From<csv::Error> for #error {
+                    fn from(e: csv::Error) -> Self {
+                        Self::CsvError(e)
+                    }
+                }
+            }
+        });
+
+        let duplicate_key = (!self.key.is_empty()).then(|| {
+            quote! {
+                DuplicateKey {
+                    linenum: u64,
+                    key: String,
+                },
+            }
+        });
+
+        let validation_failed = self.args.validate.then(|| {
+            quote! {
+                ValidationFailed {
+                    linenum: u64,
+                    error: #validation_error,
+                },
+            }
+        });
+        let from_validation_error = self.args.validate.then(|| {
+            quote! {
+                impl From<(u64, #validation_error)> for #error {
+                    fn from((linenum, error): (u64, #validation_error)) -> Self {
+                        Self::ValidationFailed { linenum, error }
+                    }
+                }
+            }
+        });
+
+        quote! {
+            #[derive(Debug)]
+            pub enum #error {
+                #variant(#load_error),
+                #csv_error
+                ColumnNotFound {
+                    linenum: u64,
+                    column_name: &'static str,
+                },
+                InvalidColumnValue {
+                    linenum: u64,
+                    column_name: &'static str,
+                    value: String,
+                },
+                #duplicate_key
+                #validation_failed
+            }
+
+            impl From<#load_error> for #error {
+                fn from(e: #load_error) -> Self {
+                    Self::#variant(e)
+                }
+            }
+            #from_csv_error
+            #from_validation_error
+
+            impl From<(u64, &'static str)> for #error {
+                fn from((linenum, column_name): (u64, &'static str)) -> Self {
+                    Self::ColumnNotFound {
+                        linenum,
+                        column_name,
+                    }
+                }
+            }
+
+            impl<S> From<(u64, &'static str, S)> for #error
+            where
+                S: Into<String>,
+            {
+                fn from((linenum, column_name, value): (u64, &'static str, S)) -> Self {
+                    Self::InvalidColumnValue {
+                        linenum,
+                        column_name,
+                        value: value.into(),
+                    }
+                }
+            }
+        }
+    }
+
+    /// The error type returned by the generated `validate` method.
+    fn validation_error_type(&self, typename: &str) -> TokenStream {
+        let validation_error = format_ident!("{typename}ValidationError");
+
+        quote! {
+            #[derive(Debug)]
+            pub enum #validation_error {
+                /// The value is outside the range seen in the input
+                OutOfRange {
+                    column_name: &'static str,
+                    value: String,
+                },
+                /// The value is an empty string
+                Empty { column_name: &'static str },
+                /// The value is not one of the strings seen in the input
+                UnknownValue {
+                    column_name: &'static str,
+                    value: String,
+                },
+                /// The value doesn't match the pattern from the '.def' file
+                PatternMismatch {
+                    column_name: &'static str,
+                    value: String,
+                },
+            }
+        }
+    }
+
+    /// The `validate` method, which checks each value against what was seen during inference:
+    /// integer ranges (widened by `--validate-slack`), non-empty strings, the set of known
+    /// strings, and any `@pattern` directives from the '.def' file.
+    fn validate(&self, typename: &str) -> Result<TokenStream, TypeGenErrors> {
+        let validation_error = format_ident!("{typename}ValidationError");
+        let mut blocks = Vec::new();
+
+        for col in &self.columns {
+            let snake_name = col.field_name();
+            let field = syntax::ident(&snake_name);
+            let optional = col.r#type.is_optional();
+
+            // Each check is written against `v`, which is bound to the value, or the contents of the Option
+            let mut checks = Vec::new();
+
+            if let (Some((min, max)), Some((type_min, type_max))) =
+                (col.inference.state.range(), col.r#type.integer_bounds())
+            {
+                let slack = ((max - min) as f64 * self.args.validate_slack).ceil() as i128;
+                let lo = min.saturating_sub(slack).max(type_min);
+                let hi = max.saturating_add(slack).min(type_max);
+
+                // A range covering the entire type can't fail
+                if lo > type_min || hi < type_max {
+                    let (lo, hi) = (Literal::i128_unsuffixed(lo), Literal::i128_unsuffixed(hi));
+                    checks.push(quote! {
+                        if !(#lo..=#hi).contains(v) {
+                            return Err(#validation_error::OutOfRange {
+                                column_name: #snake_name,
+                                value: v.to_string(),
+                            });
+                        }
+                    });
+                }
+            }
+
+            if matches!(col.r#type, ColumnType::String(_)) {
+                // Enum values aren't strings, whether or not the column is optional
+                if self.args.string_handling.is_enum() && !col.patterns.is_empty() {
+                    return Err(format!(
+                        "@pattern can't be used with enum column '{}'",
+                        col.header
+                    )
+                    .into());
+                }
+
+                // Empty values of optional columns are already parsed as `None`
+                if !optional && !self.args.string_handling.is_enum() {
+                    checks.push(quote! {
+                        if v.is_empty() {
+                            return Err(#validation_error::Empty { column_name: #snake_name });
+                        }
+                    });
+                }
+
+                // Static and enum strings can only hold known values already, so only owned strings
+                // are checked, and only when asked to. A column may have no values other than
+                // empty ones, such as a SQLite column typed by its declaration.
+                if self.args.string_handling == StringHandling::Owned
+                    && self.args.validate_values
+                    && col.seen_values.iter().any(|v| !v.is_empty())
+                {
+                    let mut seen_values = col
+                        .seen_values
+                        .iter()
+                        .filter(|v| !v.is_empty())
+                        .collect::<Vec<_>>();
+                    seen_values.sort();
+
+                    checks.push(quote! {
+                        if !matches!(v.as_str(), #(#seen_values)|*) {
+                            return Err(#validation_error::UnknownValue {
+                                column_name: #snake_name,
+                                value: v.to_string(),
+                            });
+                        }
+                    });
+                }
+
+                for (i, pattern) in col.patterns.iter().enumerate() {
+                    let static_name = format_ident!("{}_PATTERN_{i}", snake_name.to_uppercase());
+                    checks.push(quote! {
+                        static #static_name: std::sync::OnceLock<regex::Regex> = std::sync::OnceLock::new();
+                        let pattern = #static_name.get_or_init(|| regex::Regex::new(#pattern).unwrap());
+                        if !pattern.is_match(v) {
+                            return Err(#validation_error::PatternMismatch {
+                                column_name: #snake_name,
+                                value: v.to_string(),
+                            });
+                        }
+                    });
+                }
+            } else if !col.patterns.is_empty() {
+                return Err(format!(
+                    "@pattern can only be used with string columns, but '{}' is {}",
+                    col.header,
+                    col.as_str(self.args.string_handling)
+                )
+                .into());
+            }
+
+            if checks.is_empty() {
+                continue;
+            }
+
+            blocks.push(if optional {
+                quote! {
+                    if let Some(v) = &self.#field {
+                        #(#checks)*
+                    }
+                }
+            } else {
+                quote! {
+                    {
+                        let v = &self.#field;
+                        #(#checks)*
+                    }
+                }
+            });
+        }
+
+        Ok(quote! {
+            /// Checks that values are consistent with the data this type was generated from.
+            pub fn validate(&self) -> Result<(), #validation_error> {
+                #(#blocks)*
+                Ok(())
+            }
+        })
+    }
+
+    /// Finds the columns of each file whose values are all keys of another file, in batch mode.
+    ///
+    /// A column refers to another file when every non-empty value it has is a value of that file's
+    /// key column, and its name mentions the other file (eg, `customer_id` refers to `customers`) or
+    /// matches the name of the key column. Each referenced file is keyed by the referenced column.
+    pub fn link_references(infos: &mut [CsvFileInfo]) {
+        // Values are compared by their hashes, so an empty value is recognized by its hash
+        let empty = util::Fnv1a::new().finish();
+        let mut found = Vec::new();
+
+        for (t, target) in infos.iter().enumerate() {
+            let target_module = target.module_name();
+            let singular = util::singular(&target_module);
+
+            for key in target.key_candidates.iter().filter(|k| k.len() == 1) {
+                let key_col = &target.columns[key[0]];
+
+                // Enums of different columns are different types, so they can't be compared
+                if target.args.string_handling.is_enum()
+                    && matches!(key_col.r#type, ColumnType::String(_))
+                {
+                    continue;
+                }
+                let keys = match &key_col.value_hashes {
+                    Some(h) => h.iter().collect::<HashSet<_>>(),
+                    None => continue,
+                };
+
+                for (s, source) in infos.iter().enumerate() {
+                    for (c, col) in source.columns.iter().enumerate() {
+                        if (s == t && c == key[0])
+                            || found.iter().any(|(fs, fc, _, _)| (*fs, *fc) == (s, c))
+                        {
+                            continue;
+                        }
+
+                        let same_kind = match (&col.r#type, &key_col.r#type) {
+                            (ColumnType::String(_), ColumnType::String(_)) => true,
+                            (a, b) => a.integer_bounds().is_some() && b.integer_bounds().is_some(),
+                        };
+                        let named =
+                            col.name.contains(&singular) || (s != t && col.name == key_col.name);
+                        let values = match &col.value_hashes {
+                            Some(h) => h.iter().filter(|h| **h != empty).collect::<Vec<_>>(),
+                            None => continue,
+                        };
+
+                        if same_kind
+                            && named
+                            && !values.is_empty()
+                            && values.iter().all(|h| keys.contains(h))
+                        {
+                            found.push((s, c, t, key[0]));
+                        }
+                    }
+                }
+            }
+        }
+
+        for (s, c, t, k) in found {
+            // Only one key column of each file can be used for lookups
+            match infos[t].key.first() {
+                Some(&existing) if existing != k => continue,
+                Some(_) => {}
+                None => infos[t].key = vec![k],
+            }
+            infos[t].indexed = true;
+
+            let target = &infos[t];
+            let key_col = &target.columns[k];
+            let name = infos[s].columns[c].field_name();
+            let method = ["_id", "_key", "_code"]
+                .iter()
+                .find_map(|suffix| name.strip_suffix(suffix))
+                .filter(|m| !m.is_empty())
+                .unwrap_or(&name);
+            let method = match method {
+                "key" | "validate" | "load_csv" | "load_parquet" | "load_map"
+                | "check_references" => {
+                    format!("{method}_row")
+                }
+                m => m.to_string(),
+            };
+
+            let reference = Reference {
+                column: c,
+                method,
+                target_module: target.module_name(),
+                target_type: target.args.get_typename(),
+                target_column: key_col.header.clone(),
+                target_key_type: key_col.as_str(target.args.string_handling).to_string(),
+            };
+            eprintln!(
+                "{}.{} refers to {}.{}",
+                infos[s].module_name(),
+                infos[s].columns[c].header,
+                reference.target_module,
+                reference.target_column
+            );
+            infos[s].references.push(reference);
+        }
+    }
+
+    /// Whether a key was chosen for this type, with `--key` or because other types refer to it
+    pub fn is_keyed(&self) -> bool {
+        !self.key.is_empty()
+    }
+
+    /// The name of the module this type is generated into, in batch mode
+    pub fn module_name(&self) -> String {
+        let filename = self.args.get_output_filename();
+        filename
+            .file_stem()
+            .and_then(|s| s.to_str())
+            .unwrap_or_default()
+            .to_string()
+    }
+
+    /// The names of the public types generated for this type, for re-exporting in batch mode
+    pub fn exported_names(&self) -> Vec<String> {
+        let typename = self.args.get_typename();
+        let mut names = vec![typename.clone(), format!("{typename}Iterator")];
+        if self.args.error_handling == ErrorHandling::Result {
+            names.push(format!("{typename}Error"));
+        }
+        if self.args.validate {
+            names.push(format!("{typename}ValidationError"));
+        }
+        if !self.key.is_empty() {
+            names.push(format!("{typename}Key"));
+        }
+        if self.indexed {
+            names.push(format!("{typename}Index"));
+        }
+        if self.args.columns {
+            names.push(format!("{typename}Columns"));
+        }
+        if self.args.arrow {
+            names.push(format!("{typename}RecordBatches"));
+        }
+        if self.args.string_handling.is_enum() {
+            names.extend(
+                self.columns
+                    .iter()
+                    .filter(|c| !c.enum_name.is_empty())
+                    .map(|c| c.enum_name()),
+            );
+        }
+        names
+    }
+
+    /// `{typename}Index`, which other types use to look up the rows they refer to.
+    fn index_type(&self, typename: &str) -> TokenStream {
+        let name = format_ident!("{typename}");
+        let key = format_ident!("{typename}Key");
+        let index = format_ident!("{typename}Index");
+        let (_, load_error) = self.loader();
+        let map = match self.args.key_map {
+            KeyMap::Hash => quote! { std::collections::HashMap },
+            KeyMap::BTree => quote! { std::collections::BTreeMap },
+        };
+        let error = match self.args.error_handling {
+            ErrorHandling::Result => format_ident!("{typename}Error").into_token_stream(),
+            _ => load_error,
+        };
+        let doc = syntax::doc(&format!(
+            "The rows of a `{typename}` file by their key, for looking up the rows that other types refer to"
+        ));
+        let load_doc = syntax::doc(&format!(
+            "Loads every row of `filename`, as `{typename}::load_map` does."
+        ));
+
+        quote! {
+            #doc
+            pub struct #index {
+                rows: #map<#key, #name>,
+            }
+
+            impl #index {
+                #load_doc
+                pub fn load<P>(filename: P) -> Result<Self, #error>
+                where
+                    P: AsRef<std::path::Path>,
+                {
+                    Ok(Self {
+                        rows: #name::load_map(filename)?,
+                    })
+                }
+
+                pub fn get(&self, key: &#key) -> Option<&#name> {
+                    self.rows.get(key)
+                }
+            }
+        }
+    }
+
+    /// The `REFERENCES` constant, a method to look up the row each reference refers to, and
+    /// `check_references`, which finds references to rows that don't exist.
+    fn reference_methods(&self, typename: &str) -> TokenStream {
+        let (load, load_error) = self.loader();
+        let num_references = Literal::usize_unsuffixed(self.references.len());
+        let references = self.references.iter().map(|r| {
+            let header = &self.columns[r.column].header;
+            let (target_type, target_column) = (&r.target_type, &r.target_column);
+            quote! { (#header, #target_type, #target_column) }
+        });
+
+        let methods = self.references.iter().map(|r| {
+            let col = &self.columns[r.column];
+            let field = syntax::ident(&col.field_name());
+            let method = syntax::ident(&r.method);
+            let base_type = col.value_type(self.args.string_handling);
+            let module = syntax::ident(&r.target_module);
+            let target = syntax::ident(&r.target_type);
+            let target_key = format_ident!("{target}Key");
+            let target_index = format_ident!("{target}Index");
+            let doc = syntax::doc(&format!(
+                "The row of `{}` that `{}` refers to",
+                r.target_type, col.header
+            ));
+
+            let optional = col.r#type.is_optional();
+            let get = if base_type != r.target_key_type && col.r#type.integer_bounds().is_some() {
+                // Integer columns are inferred with the smallest type that fits their values,
+                // so the key may be a different type
+                let value = if optional {
+                    quote! { self.#field? }
+                } else {
+                    quote! { self.#field }
+                };
+                quote! {
+                    let key = super::#module::#target_key::try_from(#value).ok()?;
+                    index.get(&key)
+                }
+            } else if optional {
+                quote! { index.get(self.#field.as_ref()?) }
+            } else {
+                quote! { index.get(&self.#field) }
+            };
+
+            quote! {
+                #doc
+                pub fn #method<'a>(
+                    &self,
+                    index: &'a super::#module::#target_index,
+                ) -> Option<&'a super::#module::#target> {
+                    #get
+                }
+            }
+        });
+
+        // Each referenced type's index is passed once, even if several columns refer to it
+        let mut targets = Vec::new();
+        for r in &self.references {
+            if !targets.contains(&(&r.target_module, &r.target_type)) {
+                targets.push((&r.target_module, &r.target_type));
+            }
+        }
+        let params = targets.iter().map(|(module, target)| {
+            let module = syntax::ident(module);
+            let target_index = format_ident!("{target}Index");
+            quote! { #module: &super::#module::#target_index }
+        });
+        let error = match self.args.error_handling {
+            ErrorHandling::Result => format_ident!("{typename}Error").into_token_stream(),
+            _ => load_error,
+        };
+
+        let unwrap_row =
+            (self.args.error_handling == ErrorHandling::Result).then(|| quote! { let row = row?; });
+        let checks = self.references.iter().map(|r| {
+            let col = &self.columns[r.column];
+            let field = syntax::ident(&col.field_name());
+            let header = &col.header;
+            let method = syntax::ident(&r.method);
+            let index = syntax::ident(&r.target_module);
+
+            if col.r#type.is_optional() {
+                quote! {
+                    if let Some(value) = &row.#field {
+                        if row.#method(#index).is_none() {
+                            dangling.push((rows.linenum(), #header, format!("{value:?}")));
+                        }
+                    }
+                }
+            } else {
+                quote! {
+                    if row.#method(#index).is_none() {
+                        dangling.push((rows.linenum(), #header, format!("{:?}", row.#field)));
+                    }
+                }
+            }
+        });
+
+        quote! {
+            /// The columns that refer to the key of another type, as `(column, type, key column)`.
+            pub const REFERENCES: [(&'static str, &'static str, &'static str); #num_references] =
+                [#(#references),*];
+
+            #(#methods)*
+
+            /// Finds the rows of `filename` that refer to rows that don't exist in the given indexes,
+            /// returning the line number, column, and value of each reference.
+            pub fn check_references<P>(
+                filename: P,
+                #(#params,)*
+            ) -> Result<Vec<(u64, &'static str, String)>, #error>
+            where
+                P: AsRef<std::path::Path>,
+            {
+                let mut dangling = Vec::new();
+                let mut rows = Self::#load(filename)?;
+                while let Some(row) = rows.next() {
+                    #unwrap_row
+                    #(#checks)*
+                }
+
+                Ok(dangling)
+            }
+        }
+    }
+
+    /// Prints the columns that could be used with `--key`, if any were found and none was chosen.
+    pub fn write_key_candidates(&self) {
+        if !self.key.is_empty() || self.key_candidates.is_empty() {
+            return;
+        }
+
+        let candidates = self
+            .key_candidates
+            .iter()
+            .map(|key| {
+                let names = key
+                    .iter()
+                    .map(|&i| self.columns[i].header.as_str())
+                    .collect::<Vec<_>>();
+                names.join(",")
+            })
+            .collect::<Vec<_>>();
+        eprintln!(
+            "Possible keys: {}. Use --key to generate a `load_map` function.",
+            candidates.join("; ")
+        );
+    }
+
+    /// The Rust type of the key used by `load_map`: the type of the key column, or a tuple
+    /// of the types of several key columns
+    fn key_type(&self) -> String {
+        let types = self
+            .key
+            .iter()
+            .map(|&i| self.columns[i].as_str(self.args.string_handling))
+            .collect::<Vec<_>>();
+
+        match &types[..] {
+            [single] => single.to_string(),
+            _ => format!("({})", types.join(", ")),
+        }
+    }
+
+    /// The `key` method and `load_map` function, which loads rows into a map by their key,
+    /// according to the `--key` and `--key-map` arguments.
+    fn load_map(&self, typename: &str) -> TokenStream {
+        let name = format_ident!("{typename}");
+        let key_type = format_ident!("{typename}Key");
+        let error = format_ident!("{typename}Error");
+        let (load, load_error) = self.loader();
+        let (map, entry) = match self.args.key_map {
+            KeyMap::Hash => (
+                quote! { std::collections::HashMap },
+                quote! { std::collections::hash_map::Entry },
+            ),
+            KeyMap::BTree => (
+                quote! { std::collections::BTreeMap },
+                quote! { std::collections::btree_map::Entry },
+            ),
+        };
+        let key_names = self
+            .key
+            .iter()
+            .map(|&i| format!("`{}`", self.columns[i].header))
+            .collect::<Vec<_>>()
+            .join(", ");
+        let key_doc = syntax::doc(&format!("The key of this row, from {key_names}"));
+
+        // Owned strings are cloned; everything else is `Copy`
+        let fields = self
+            .key
+            .iter()
+            .map(|&i| {
+                let col = &self.columns[i];
+                let field = syntax::ident(&col.field_name());
+                if matches!(col.r#type, ColumnType::String(_)) && !self.strings_are_copy() {
+                    quote! { self.#field.clone() }
+                } else {
+                    quote! { self.#field }
+                }
+            })
+            .collect::<Vec<_>>();
+        let key = match &fields[..] {
+            [single] => single.clone(),
+            _ => quote! { (#(#fields),*) },
+        };
+
+        let (doc, map_error, occupied) = match self.args.error_handling {
+            ErrorHandling::Result => (
+                "Loads every row into a map by its key. Fails if a key appears more than once.",
+                error.to_token_stream(),
+                quote! {
+                    #entry::Occupied(e) => {
+                        return Err(#error::DuplicateKey {
+                            linenum: rows.linenum(),
+                            key: format!("{:?}", e.key()),
+                        });
+                    }
+                },
+            ),
+            ErrorHandling::IgnoreRow => (
+                "Loads every row into a map by its key. Rows with a key that was already seen are ignored.",
+                load_error,
+                quote! { #entry::Occupied(_) => {} },
+            ),
+            ErrorHandling::Panic => (
+                "Loads every row into a map by its key. Panics if a key appears more than once.",
+                load_error,
+                quote! {
+                    #entry::Occupied(e) => panic!(
+                        "Duplicate key {:?} at line={}",
+                        e.key(),
+                        rows.linenum()
+                    ),
+                },
+            ),
+        };
+        let doc = syntax::doc(doc);
+        let unwrap_row =
+            (self.args.error_handling == ErrorHandling::Result).then(|| quote! { let row = row?; });
+
+        quote! {
+            #key_doc
+            pub fn key(&self) -> #key_type {
+                #key
+            }
+
+            #doc
+            pub fn load_map<P>(filename: P) -> Result<#map<#key_type, #name>, #map_error>
+            where
+                P: AsRef<std::path::Path>,
+            {
+                let mut map = #map::new();
+                let mut rows = Self::#load(filename)?;
+                while let Some(row) = rows.next() {
+                    #unwrap_row
+                    match map.entry(row.key()) {
+                        #entry::Vacant(e) => {
+                            e.insert(row);
+                        }
+                        #occupied
+                    }
+                }
+
+                Ok(map)
+            }
+        }
+    }
+
+    /// The `load_columns` function, which loads every row into a `{typename}Columns`.
+    fn load_columns(&self, typename: &str) -> TokenStream {
+        let columns = format_ident!("{typename}Columns");
+        let (load, load_error) = self.loader();
+
+        let (doc, error, rows) = match self.args.error_handling {
+            // Collecting into a `Result` stops at the first error
+            ErrorHandling::Result => (
+                "Loads every row, stored by column. Fails at the first row that can't be read.",
+                format_ident!("{typename}Error").into_token_stream(),
+                quote! { Self::#load(filename)?.collect() },
+            ),
+            ErrorHandling::IgnoreRow | ErrorHandling::Panic => (
+                "Loads every row, stored by column.",
+                load_error,
+                quote! { Ok(Self::#load(filename)?.collect()) },
+            ),
+        };
+        let doc = syntax::doc(doc);
+
+        quote! {
+            #doc
+            pub fn load_columns<P>(filename: P) -> Result<#columns, #error>
+            where
+                P: AsRef<std::path::Path>,
+            {
+                #rows
+            }
+        }
+    }
+
+    /// `{typename}Columns`, which stores rows as a vector for each column. Rather than a vector
+    /// of `Option`s, an optional column has a vector of values, with a placeholder for missing
+    /// values, and a bitmap of which values are present.
+    fn columns_type(&self, typename: &str) -> TokenStream {
+        let string_handling = self.args.string_handling;
+        let name = format_ident!("{typename}");
+        let columns = format_ident!("{typename}Columns");
+        let fields = self
+            .columns
+            .iter()
+            .map(|col| syntax::ident(&col.field_name()))
+            .collect::<Vec<_>>();
+        let validity = fields
+            .iter()
+            .map(|field| format_ident!("{field}_validity"))
+            .collect::<Vec<_>>();
+        let optional = self
+            .columns
+            .iter()
+            .map(|col| col.r#type.is_optional())
+            .collect::<Vec<_>>();
+        let any_optional = optional.iter().any(|&o| o);
+
+        let doc = syntax::doc(&format!(
+            "The rows of a `{typename}` file stored by column. Each optional column has a validity \
+             bitmap, in which bit `i % 64` of word `i / 64` is set if row `i` has a value, and a \
+             placeholder value for each row that doesn't."
+        ));
+        let vectors = self.columns.iter().enumerate().map(|(i, col)| {
+            let docs = col.column_docs.iter().map(|doc| syntax::doc(doc));
+            let value_type = syntax::parse::<syn::Type>(&col.value_type(string_handling));
+            let field = &fields[i];
+            let validity = &validity[i];
+            let validity = optional[i].then(|| quote! { pub #validity: Vec<u64>, });
+            quote! {
+                #(#docs)*
+                pub #field: Vec<#value_type>,
+                #validity
+            }
+        });
+
+        let is_valid = any_optional.then(|| {
+            quote! {
+                /// Whether row `i` has a value, according to the validity bitmap of an optional column
+                pub fn is_valid(validity: &[u64], i: usize) -> bool {
+                    (validity[i / 64] >> (i % 64)) & 1 == 1
+                }
+            }
+        });
+
+        let bit = any_optional
+            .then(|| quote! { let (word, bit) = (self.len / 64, 1u64 << (self.len % 64)); });
+        let pushes = self.columns.iter().enumerate().map(|(i, col)| {
+            let (field, validity) = (&fields[i], &validity[i]);
+            if col.r#type == ColumnType::Unit {
+                return quote! { self.#field.push(()); };
+            }
+            if !optional[i] {
+                return quote! { self.#field.push(row.#field); };
+            }
+
+            // Missing values need a placeholder of the column's type; enums have no default,
+            // so their first variant is used
+            let placeholder = match (&col.r#type, string_handling) {
+                (ColumnType::String(_), sh) if sh.is_enum() => {
+                    let value_type = syntax::ident(&col.enum_name());
+                    let variant = syntax::ident(&col.variants(&self.args)[0]);
+                    quote! { #value_type::#variant }
+                }
+                _ => quote! { Default::default() },
+            };
+            quote! {
+                if bit == 1 {
+                    self.#validity.push(0);
+                }
+                match row.#field {
+                    Some(v) => {
+                        self.#field.push(v);
+                        self.#validity[word] |= bit;
+                    }
+                    None => self.#field.push(#placeholder),
+                }
+            }
+        });
+
+        let values = self.columns.iter().enumerate().map(|(i, col)| {
+            let (field, validity) = (&fields[i], &validity[i]);
+            let owned = matches!(col.r#type, ColumnType::String(_)) && !self.strings_are_copy();
+            match (optional[i], owned) {
+                (false, false) => quote! { #field: self.#field[i] },
+                (false, true) => quote! { #field: self.#field[i].clone() },
+                (true, false) => {
+                    quote! { #field: Self::is_valid(&self.#validity, i).then_some(self.#field[i]) }
+                }
+                (true, true) => {
+                    quote! { #field: Self::is_valid(&self.#validity, i).then(|| self.#field[i].clone()) }
+                }
+            }
+        });
+
+        let iterators = fields
+            .iter()
+            .map(|field| quote! { let mut #field = columns.#field.into_iter(); });
+        let row = if any_optional {
+            quote! { i }
+        } else {
+            quote! { _ }
+        };
+        let next_values =
+            fields
+                .iter()
+                .zip(&validity)
+                .zip(&optional)
+                .map(|((field, validity), &optional)| {
+                    if optional {
+                        quote! {
+                            #field: #field
+                                .next()
+                                .filter(|_| #columns::is_valid(&columns.#validity, i))
+                        }
+                    } else {
+                        quote! { #field: #field.next().unwrap() }
+                    }
+                });
+
+        quote! {
+            #doc
+            #[derive(Clone, Debug, Default)]
+            pub struct #columns {
+                #(#vectors)*
+                len: usize,
+            }
+
+            impl #columns {
+                /// The number of rows
+                pub fn len(&self) -> usize {
+                    self.len
+                }
+
+                pub fn is_empty(&self) -> bool {
+                    self.len == 0
+                }
+
+                #is_valid
+
+                /// Appends a row
+                pub fn push(&mut self, row: #name) {
+                    #bit
+                    #(#pushes)*
+                    self.len += 1;
+                }
+
+                /// A copy of row `i`, if there is one
+                pub fn row(&self, i: usize) -> Option<#name> {
+                    if i >= self.len {
+                        return None;
+                    }
+
+                    Some(#name {
+                        #(#values),*
+                    })
+                }
+            }
+
+            impl FromIterator<#name> for #columns {
+                fn from_iter<I: IntoIterator<Item = #name>>(iter: I) -> Self {
+                    let mut columns = Self::default();
+                    for row in iter {
+                        columns.push(row);
+                    }
+                    columns
+                }
+            }
+
+            impl From<Vec<#name>> for #columns {
+                fn from(rows: Vec<#name>) -> Self {
+                    rows.into_iter().collect()
+                }
+            }
+
+            impl From<#columns> for Vec<#name> {
+                fn from(columns: #columns) -> Self {
+                    let mut rows = Vec::with_capacity(columns.len);
+                    #(#iterators)*
+                    for #row in 0..columns.len {
+                        rows.push(#name {
+                            #(#next_values),*
+                        });
+                    }
+                    rows
+                }
+            }
+        }
+    }
+
+    /// The `arrow_schema` and `load_record_batches` functions.
+    fn arrow_schema(&self, typename: &str) -> TokenStream {
+        let record_batches = format_ident!("{typename}RecordBatches");
+        let (load, load_error) = self.loader();
+        let fields = self.columns.iter().map(|col| {
+            let name = col.field_name();
+            let data_type = syntax::parse::<syn::Expr>(&col.arrow_type(self.args.string_handling));
+            // Every value of a unit column is null
+            let nullable = col.r#type.is_optional() || col.r#type == ColumnType::Unit;
+            quote! { Field::new(#name, #data_type, #nullable) }
+        });
+        let error =
+            (self.args.error_handling == ErrorHandling::Result).then(|| quote! { error: None, });
+
+        quote! {
+            /// The Arrow schema of the record batches returned by `load_record_batches`.
+            pub fn arrow_schema() -> arrow::datatypes::Schema {
+                use arrow::datatypes::{DataType, Field};
+
+                arrow::datatypes::Schema::new(vec![#(#fields,)*])
+            }
+
+            /// Reads the rows of `filename` as Arrow record batches of up to `batch_size` rows.
+            pub fn load_record_batches<P>(
+                filename: P,
+                batch_size: usize,
+            ) -> Result<#record_batches, #load_error>
+            where
+                P: AsRef<std::path::Path>,
+            {
+                Ok(#record_batches {
+                    rows: Self::#load(filename)?,
+                    schema: std::sync::Arc::new(Self::arrow_schema()),
+                    batch_size,
+                    #error
+                })
+            }
+        }
+    }
+
+    /// `{typename}RecordBatches`, which converts rows to Arrow record batches with a builder for
+    /// each column. It's an `arrow::record_batch::RecordBatchReader`, so it can be handed to
+    /// anything that reads Arrow data.
+    fn record_batches(&self, typename: &str) -> TokenStream {
+        let string_handling = self.args.string_handling;
+        let result = self.args.error_handling == ErrorHandling::Result;
+        let record_batches = format_ident!("{typename}RecordBatches");
+        let iterator = format_ident!("{typename}Iterator");
+        let error = format_ident!("{typename}Error");
+        let doc = syntax::doc(&format!(
+            "The rows of a `{typename}` file as Arrow record batches, from `{typename}::load_record_batches`"
+        ));
+
+        let error_field = result.then(|| {
+            quote! {
+                /// An error that ended the previous batch early, returned before the next batch
+                error: Option<#error>,
+            }
+        });
+        let previous_error = result.then(|| {
+            quote! {
+                if let Some(e) = self.error.take() {
+                    return Some(Err(arrow::error::ArrowError::CsvError(format!("{e:?}"))));
+                }
+            }
+        });
+        let unwrap_row = result.then(|| {
+            quote! {
+                let row = match row {
+                    Ok(row) => row,
+                    Err(e) => {
+                        self.error = Some(e);
+                        break;
+                    }
+                };
+            }
+        });
+        let no_rows = if result {
+            // The batch may have ended at an error
+            quote! {
+                let e = self.error.take()?;
+                return Some(Err(arrow::error::ArrowError::CsvError(format!("{e:?}"))));
+            }
+        } else {
+            quote! { return None; }
+        };
+
+        let fields = self
+            .columns
+            .iter()
+            .map(|col| syntax::ident(&col.field_name()))
+            .collect::<Vec<_>>();
+        let builders = self.columns.iter().zip(&fields).filter_map(|(col, field)| {
+            let builder = col.arrow_builder(string_handling)?;
+            let builder = syntax::ident(builder);
+            Some(if builder == "StringBuilder" {
+                quote! { let mut #field = StringBuilder::new(); }
+            } else {
+                quote! { let mut #field = #builder::with_capacity(self.batch_size); }
+            })
+        });
+        let appends = self.columns.iter().zip(&fields).filter_map(|(col, field)| {
+            let optional = col.r#type.is_optional();
+            let value = match (&col.r#type, string_handling) {
+                (ColumnType::Unit, _) => return None,
+                (ColumnType::String(_), StringHandling::Enum(_)) => {
+                    let key = syntax::ident(col.dictionary_key());
+                    if optional {
+                        quote! { row.#field.map(|v| v as #key) }
+                    } else {
+                        quote! { row.#field as #key }
+                    }
+                }
+                (ColumnType::String(_), StringHandling::Owned) if optional => {
+                    quote! { row.#field.as_deref() }
+                }
+                (ColumnType::String(_), StringHandling::Owned) => quote! { &row.#field },
+                _ => quote! { row.#field },
+            };
+            Some(if optional {
+                quote! { #field.append_option(#value); }
+            } else {
+                quote! { #field.append_value(#value); }
+            })
+        });
+        let arrays = self.columns.iter().zip(&fields).map(|(col, field)| {
+            match (&col.r#type, string_handling) {
+                (ColumnType::Unit, _) => quote! { Arc::new(NullArray::new(len)) },
+                (ColumnType::String(_), StringHandling::Enum(_)) => {
+                    // Enum columns are dictionary encoded, with each variant's index as its key,
+                    // so the values are in the same order as the variants
+                    let values = col.known_values(string_handling);
+                    quote! {
+                        Arc::new(
+                            DictionaryArray::try_new(
+                                #field.finish(),
+                                Arc::new(StringArray::from(vec![#(#values),*])),
+                            )
+                            .expect("enum values are in the dictionary"),
+                        )
                     }
                 }
+                _ => quote! { Arc::new(#field.finish()) },
             }
-        }
-
-        self.columns = columns
-            .into_iter()
-            .zip(intermediates)
-            .zip(seen_values)
-            .map(|((name, coltype), seen_values)| CsvColumnInfo {
-                name,
-                r#type: coltype.finish(),
-                column_docs: Vec::new(),
-                seen_values,
-                error_handling: self.args.error_handling,
-            })
-            .collect();
-
-        Ok(self)
-    }
-
-    pub fn load_data_def(mut self) -> Self {
-        if let Some(mut docs) = crate::util::read_doc_file(&self.args.input_file) {
-            if let Some(struct_docs) = docs.remove("") {
-                self.struct_docs = struct_docs;
+        });
+
+        quote! {
+            #doc
+            pub struct #record_batches {
+                rows: #iterator,
+                schema: std::sync::Arc<arrow::datatypes::Schema>,
+                batch_size: usize,
+                #error_field
             }
 
-            for column in self.columns.iter_mut() {
-                if let Some(docs) = docs.remove(&column.name) {
-                    column.column_docs = docs;
+            impl arrow::record_batch::RecordBatchReader for #record_batches {
+                fn schema(&self) -> std::sync::Arc<arrow::datatypes::Schema> {
+                    self.schema.clone()
                 }
             }
-        };
 
-        self
-    }
+            impl Iterator for #record_batches {
+                type Item = Result<arrow::record_batch::RecordBatch, arrow::error::ArrowError>;
 
-    pub fn generate(&self, buf: &mut BufWriter<File>) -> Result<(), TypeGenErrors> {
-        let typename = self.args.get_typename();
+                fn next(&mut self) -> Option<Self::Item> {
+                    use arrow::array::*;
+                    use std::sync::Arc;
 
-        if self.args.error_handling == ErrorHandling::Result {
-            writeln!(buf, "#[derive(Debug)]")?;
-            writeln!(buf, "pub enum {typename}Error {{")?;
-            writeln!(buf, "    CsvError(csv::Error),")?;
-            writeln!(buf, "    ColumnNotFound {{")?;
-            writeln!(buf, "        linenum: u64,")?;
-            writeln!(buf, "        column_name: &'static str,")?;
-            writeln!(buf, "    }},")?;
-            writeln!(buf, "    InvalidColumnValue {{")?;
-            writeln!(buf, "        linenum: u64,")?;
-            writeln!(buf, "        column_name: &'static str,")?;
-            writeln!(buf, "        value: String,")?;
-            writeln!(buf, "    }},")?;
-            writeln!(buf, "}}")?;
-            writeln!(buf)?;
+                    #previous_error
 
-            writeln!(buf, "impl From<csv::Error> for {typename}Error {{")?;
-            writeln!(buf, "    fn from(e: csv::Error) -> Self {{")?;
-            writeln!(buf, "        Self::CsvError(e)")?;
-            writeln!(buf, "    }}")?;
-            writeln!(buf, "}}")?;
+                    #(#builders)*
 
-            writeln!(buf, "impl From<(u64, &'static str)> for {typename}Error {{")?;
-            writeln!(
-                buf,
-                "    fn from((linenum, column_name): (u64, &'static str)) -> Self {{"
-            )?;
-            writeln!(buf, "        Self::ColumnNotFound {{")?;
-            writeln!(buf, "            linenum,")?;
-            writeln!(buf, "            column_name,")?;
-            writeln!(buf, "        }}")?;
-            writeln!(buf, "    }}")?;
-            writeln!(buf, "}}")?;
-            writeln!(buf)?;
+                    let mut len = 0;
+                    for row in self.rows.by_ref().take(self.batch_size) {
+                        #unwrap_row
+                        #(#appends)*
+                        len += 1;
+                    }
 
-            writeln!(
-                buf,
-                "impl<S> From<(u64, &'static str, S)> for {typename}Error"
-            )?;
-            writeln!(buf, "where")?;
-            writeln!(buf, "    S: Into<String>,")?;
-            writeln!(buf, "{{")?;
-            writeln!(
-                buf,
-                "    fn from((linenum, column_name, value): (u64, &'static str, S)) -> Self {{"
-            )?;
-            writeln!(buf, "        Self::InvalidColumnValue {{")?;
-            writeln!(buf, "            linenum,")?;
-            writeln!(buf, "            column_name,")?;
-            writeln!(buf, "            value: value.into(),")?;
-            writeln!(buf, "        }}")?;
-            writeln!(buf, "    }}")?;
-            writeln!(buf, "}}")?;
-        }
-        writeln!(buf)?;
+                    if len == 0 {
+                        #no_rows
+                    }
 
-        if matches!(self.args.string_handling, StringHandling::Enum(_)) {
-            for col in &self.columns {
-                if matches!(col.r#type, ColumnType::String(_)) {
-                    col.write_enum(buf)?;
+                    let columns: Vec<ArrayRef> = vec![#(#arrays,)*];
+
+                    Some(arrow::record_batch::RecordBatch::try_new(self.schema.clone(), columns))
                 }
             }
         }
+    }
 
-        // If string handling is 'static' or 'enum, we can derive 'Copy' on the type
-        match self.args.string_handling {
-            StringHandling::Static => writeln!(buf, "#[derive(Copy, Clone, Debug)]")?,
-            StringHandling::Enum(_) => writeln!(buf, "#[derive(Copy, Clone, Debug)]")?,
-            StringHandling::Owned => writeln!(buf, "#[derive(Clone, Debug)]")?,
+    /// `load_csv`, which opens a CSV file and checks its header row.
+    fn load_csv(&self, typename: &str) -> TokenStream {
+        let iterator = format_ident!("{typename}Iterator");
+        let has_headers = !self.args.no_header;
+        let delimiter = Literal::byte_character(self.args.delimiter as u8);
+        // Only reading the header row needs the reader to be mutable
+        let (mutability, indices) = if self.args.no_header {
+            (None, self.ordered_indices())
+        } else {
+            let indices = self.header_indices(quote! { reader.headers()? });
+            (Some(quote! { mut }), indices)
+        };
+
+        quote! {
+            pub fn load_csv<P>(filename: P) -> Result<#iterator, csv::Error>
+            where
+                P: AsRef<std::path::Path>,
+            {
+                let #mutability reader = csv::ReaderBuilder::new()
+                    .has_headers(#has_headers)
+                    .delimiter(#delimiter)
+                    .from_path(filename)?;
+
+                #indices
+                let records = reader.into_records();
+                let row = csv::StringRecord::default();
+                Ok(#iterator { records, row, indices })
+            }
         }
+    }
+
+    /// The `indices` of input without a header, whose columns are always read in the order they
+    /// were seen
+    fn ordered_indices(&self) -> TokenStream {
+        let indices = (0..self.columns.len()).map(Literal::usize_unsuffixed);
+        quote! { let indices = [#(#indices),*]; }
+    }
 
-        for doc in &self.struct_docs {
-            writeln!(buf, "/// {doc}")?;
+    /// The `indices` of the columns in `headers`, found by `check_headers`
+    fn header_indices(&self, headers: TokenStream) -> TokenStream {
+        if self.may_be_missing() {
+            quote! { let indices = check_headers(#headers, &Self::HEADERS, &Self::MAY_BE_MISSING)?; }
+        } else {
+            quote! { let indices = check_headers(#headers, &Self::HEADERS)?; }
         }
+    }
 
-        writeln!(buf, "pub struct {typename} {{")?;
-        for col in &self.columns {
-            // TODO: For String types, we might be able to use Cow<'_, str>, referencing the StringRecord value
-            // until such time as the caller wants to incur allocation overhead.
-            for doc in &col.column_docs {
-                writeln!(buf, "    /// {doc}")?;
+    /// `load_parquet`, which opens a Parquet file and checks its column names.
+    fn load_parquet(&self, typename: &str) -> TokenStream {
+        let iterator = format_ident!("{typename}Iterator");
+        let indices = self.header_indices(quote! { &headers });
+
+        quote! {
+            pub fn load_parquet<P>(filename: P) -> Result<#iterator, parquet::errors::ParquetError>
+            where
+                P: AsRef<std::path::Path>,
+            {
+                use parquet::file::reader::FileReader;
+
+                let reader = parquet::file::reader::SerializedFileReader::new(std::fs::File::open(filename)?)?;
+                let headers = reader
+                    .metadata()
+                    .file_metadata()
+                    .schema()
+                    .get_fields()
+                    .iter()
+                    .map(|field| field.name())
+                    .collect::<csv::StringRecord>();
+                #indices
+                let rows = reader.into_iter();
+                let row = csv::StringRecord::default();
+                Ok(#iterator { rows, row, indices })
             }
-            writeln!(
-                buf,
-                "    pub {}: {},",
-                util::str_to_snake_case_identifier(&col.name),
-                col.as_str(self.args.string_handling)
-            )?;
         }
-        writeln!(buf, "}}")?;
-        writeln!(buf)?;
+    }
 
-        writeln!(buf, "impl {typename} {{")?;
+    /// `next_record`, which reads the next row of a Parquet file as a record of the text of each
+    /// value, so it's parsed the same way as a CSV record. This matches how the values were read
+    /// when inferring the types.
+    fn next_parquet_record(&self, typename: &str) -> TokenStream {
+        let iterator = format_ident!("{typename}Iterator");
+
+        // Rows are numbered from 1, like the lines of a CSV file without a header
+        quote! {
+            impl #iterator {
+                /// Reads the next row as text, in the same way as when the types were inferred
+                fn next_record(&mut self) -> Option<Result<csv::StringRecord, parquet::errors::ParquetError>> {
+                    let row = match self.rows.next()? {
+                        Ok(r) => r,
+                        Err(e) => return Some(Err(e)),
+                    };
+                    let mut record = csv::StringRecord::new();
+                    for (_, field) in row.get_column_iter() {
+                        record.push_field(&parquet_text(field));
+                    }
 
-        {
-            writeln!(
-                buf,
-                "    /// The `(name, type)` associated with each column."
-            )?;
-            writeln!(
-                buf,
-                "    pub const COLUMNS: [(&'static str, &'static str); {}] = [",
-                self.columns.len()
-            )?;
-            for col in &self.columns {
-                writeln!(
-                    buf,
-                    "        (\"{}\", \"{}\"),",
-                    col.name,
-                    col.as_str(self.args.string_handling),
-                )?;
-            }
-            writeln!(buf, "    ];")?;
-            writeln!(buf)?;
+                    let index = self.row.position().map_or(0, |p| p.record() + 1);
+                    let mut position = csv::Position::new();
+                    position.set_record(index).set_line(index + 1);
+                    record.set_position(Some(position));
+                    Some(Ok(record))
+                }
+            }
         }
+    }
 
-        writeln!(
-            buf,
-            "    pub fn load_csv<P>(filename: P) -> Result<{typename}Iterator, csv::Error>"
-        )?;
-        writeln!(buf, "    where")?;
-        writeln!(buf, "        P: AsRef<std::path::Path>,")?;
-        writeln!(buf, "    {{")?;
-        writeln!(buf, "        let reader = csv::ReaderBuilder::new()")?;
+    /// `RANGES` and `load_fixed_width`, which opens a fixed-width text file and checks its header
+    /// line.
+    fn load_fixed_width(&self, typename: &str) -> TokenStream {
+        let iterator = format_ident!("{typename}Iterator");
+        let num_ranges = Literal::usize_unsuffixed(self.ranges.len());
+        let ranges = self.ranges.iter().map(|range| {
+            let start = Literal::usize_unsuffixed(range.start);
+            match range.end {
+                Some(end) => {
+                    let end = Literal::usize_unsuffixed(end);
+                    quote! { (#start, #end) }
+                }
+                None => quote! { (#start, usize::MAX) },
+            }
+        });
 
-        if self.args.no_header {
-            writeln!(buf, "            .has_headers(false)")?;
+        // The header line, if any, is read before the rows
+        let (mutability, indices) = if self.args.no_header {
+            let indices = self.ordered_indices();
+            (None, quote! { #indices let linenum = 0; })
         } else {
-            writeln!(buf, "            .has_headers(true)")?;
-        }
+            let indices = self.header_indices(quote! { &headers });
+            let header = quote! {
+                let header = lines.next().transpose()?.unwrap_or_default();
+                let headers = split_fixed_width(&header, &Self::RANGES);
+                #indices
+                let linenum = 1;
+            };
+            (Some(quote! { mut }), header)
+        };
 
-        if self.args.delimiter == '\t' {
-            writeln!(buf, "            .delimiter(b'\\t')")?;
-        } else {
-            writeln!(buf, "            .delimiter(b'{}')", self.args.delimiter)?;
-        };
-        writeln!(buf, "            .from_path(filename)?;")?;
-        writeln!(buf)?;
-        writeln!(buf, "        let records = reader.into_records();")?;
-        writeln!(buf, "        let row = csv::StringRecord::default();")?;
-        writeln!(buf, "        Ok({typename}Iterator {{ records, row }})")?;
-        writeln!(buf, "    }}")?;
-        writeln!(buf, "}}")?;
-        writeln!(buf)?;
-
-        writeln!(buf, "pub struct {typename}Iterator {{")?;
-        writeln!(
-            buf,
-            "    records: csv::StringRecordsIntoIter<std::fs::File>,"
-        )?;
-        writeln!(buf, "    row: csv::StringRecord,")?;
-        writeln!(buf, "}}")?;
-        writeln!(buf)?;
-
-        writeln!(buf, "impl Iterator for {typename}Iterator {{")?;
+        quote! {
+            /// The bytes of each column within a line. The last column may extend to the end of the line.
+            pub const RANGES: [(usize, usize); #num_ranges] = [#(#ranges),*];
 
-        match self.args.error_handling {
-            ErrorHandling::Result => {
-                writeln!(buf, "    type Item = Result<{typename}, {typename}Error>;")?
+            pub fn load_fixed_width<P>(filename: P) -> Result<#iterator, std::io::Error>
+            where
+                P: AsRef<std::path::Path>,
+            {
+                use std::io::BufRead;
+
+                let #mutability lines = std::io::BufReader::new(std::fs::File::open(filename)?).split(b'\n');
+                #indices
+                let row = csv::StringRecord::default();
+                Ok(#iterator { lines, linenum, row, indices })
             }
-            _ => writeln!(buf, "    type Item = {typename};")?,
         }
-        writeln!(buf)?;
+    }
 
-        writeln!(buf, "    fn next(&mut self) -> Option<Self::Item> {{")?;
+    /// `next_record`, which reads the next line of a fixed-width file that isn't blank, split
+    /// into a record of the text of each column.
+    fn next_fixed_width_record(&self, typename: &str) -> TokenStream {
+        let name = format_ident!("{typename}");
+        let iterator = format_ident!("{typename}Iterator");
+
+        quote! {
+            impl #iterator {
+                /// Reads the next line that isn't blank, split into columns by `RANGES`
+                fn next_record(&mut self) -> Option<Result<csv::StringRecord, std::io::Error>> {
+                    loop {
+                        let line = match self.lines.next()? {
+                            Ok(l) => l,
+                            Err(e) => return Some(Err(e)),
+                        };
+                        self.linenum += 1;
+                        if line.iter().all(u8::is_ascii_whitespace) {
+                            continue;
+                        }
 
-        let indent = match self.args.error_handling {
-            ErrorHandling::IgnoreRow => {
-                writeln!(
-                    buf,
-                    "        // Because we're ignoring errors, loop until a row is valid"
-                )?;
-                writeln!(buf, "        loop {{")?;
-                writeln!(buf, "            self.row = match self.records.next()? {{")?;
-                writeln!(buf, "                Ok(r) => r,")?;
-                writeln!(buf, "                Err(_) => continue,")?;
-                writeln!(buf, "            }};")?;
-                "            " // IgnoreRow indents more than other error handling
-            }
-            ErrorHandling::Result => {
-                writeln!(buf, "        self.row = match self.records.next()? {{")?;
-                writeln!(buf, "            Ok(r) => r,")?;
-                writeln!(buf, "            Err(e) => return Some(Err(e.into())),")?;
-                writeln!(buf, "        }};")?;
-                "        "
-            }
-            ErrorHandling::Panic => {
-                writeln!(buf, "        self.row = match self.records.next()? {{")?;
-                writeln!(buf, "            Ok(r) => r,")?;
-                writeln!(buf, "            Err(_) => panic!(\"Failed to get row\"),")?;
-                writeln!(buf, "        }};")?;
-                "        "
+                        let mut record = split_fixed_width(&line, &#name::RANGES);
+                        let index = self.row.position().map_or(0, |p| p.record() + 1);
+                        let mut position = csv::Position::new();
+                        position.set_record(index).set_line(self.linenum);
+                        record.set_position(Some(position));
+                        return Some(Ok(record));
+                    }
+                }
             }
-        };
-        writeln!(buf)?;
+        }
+    }
 
-        match self.args.error_handling {
-            ErrorHandling::Result | ErrorHandling::Panic => {
-                writeln!(
-                    buf,
-                    "{indent}let linenum = self.row.position().unwrap().line();"
-                )?;
-                writeln!(buf)?;
-            }
-            ErrorHandling::IgnoreRow => {}
-        }
-
-        // Extract each column
-        for (i, col) in self.columns.iter().enumerate() {
-            let CsvColumnInfo {
-                column_docs: _,
-                name,
-                r#type,
-                seen_values,
-                error_handling,
-            } = col;
-
-            let optional = r#type.is_optional();
-
-            if r#type == &ColumnType::Unit {
-                writeln!(buf, "{indent}let {name} = ();")?;
-                writeln!(buf)?;
-                continue;
+    /// The `split_fixed_width` function used to split the lines of a fixed-width file.
+    fn split_fixed_width(&self) -> TokenStream {
+        let vis = self.common_module.then(|| quote! { pub });
+
+        quote! {
+            /// Splits a line into the trimmed text of each column. Columns past the end of the line are empty.
+            #vis fn split_fixed_width<const N: usize>(
+                line: &[u8],
+                ranges: &[(usize, usize); N],
+            ) -> csv::StringRecord {
+                let line = line.strip_suffix(b"\r").unwrap_or(line);
+                let mut record = csv::StringRecord::new();
+                for &(start, end) in ranges {
+                    let field = line.get(start..end.min(line.len())).unwrap_or_default();
+                    record.push_field(String::from_utf8_lossy(field).trim());
+                }
+                record
             }
+        }
+    }
 
-            let snake_name = util::str_to_snake_case_identifier(name);
-            if self.args.trim_input {
-                writeln!(buf, "{indent}let {snake_name} = match self.row.get({i}).map(|s| s.trim()) {{")?;
-            } else {
-                writeln!(buf, "{indent}let {snake_name} = match self.row.get({i}) {{")?;
+    /// `SHEET` and `load_sheet`, which reads the worksheet of a spreadsheet as text and checks
+    /// its header row.
+    fn load_sheet(&self, typename: &str) -> TokenStream {
+        let iterator = format_ident!("{typename}Iterator");
+        let sheet = self.sheet.as_deref().unwrap_or_default();
+        let cell = |(row, col): (u32, u32)| {
+            let (row, col) = (Literal::u32_unsuffixed(row), Literal::u32_unsuffixed(col));
+            quote! { (#row, #col) }
+        };
+        let range = self.args.range.map(|range| {
+            let start = cell(range.start);
+            match range.end {
+                Some(end) => {
+                    let end = cell(end);
+                    quote! { let cells = cells.range(#start, #end); }
+                }
+                None => quote! {
+                    let end = cells.end().unwrap_or(#start);
+                    let cells = cells.range(#start, end);
+                },
             }
+        });
 
-            write!(buf, "{indent}    None => ")?;
+        // Empty rows are skipped, and the header row, if any, is taken before the rows
+        let (mutability, indices) = if self.args.no_header {
+            (None, self.ordered_indices())
+        } else {
+            let indices = self.header_indices(quote! { &headers });
+            let header = quote! {
+                let headers = rows.next().map(|(_, record)| record).unwrap_or_default();
+                #indices
+            };
+            (Some(quote! { mut }), header)
+        };
+
+        quote! {
+            /// The worksheet that rows are read from
+            pub const SHEET: &'static str = #sheet;
 
-            // Can't get a value from the CSV reader
+            pub fn load_sheet<P>(filename: P) -> Result<#iterator, calamine::Error>
+            where
+                P: AsRef<std::path::Path>,
             {
-                match self.args.error_handling {
-                    ErrorHandling::IgnoreRow => {
-                        writeln!(buf, "continue,")?;
-                    }
-                    ErrorHandling::Result => {
-                        writeln!(buf, "return Some(Err((linenum, \"{name}\").into())),")?;
-                    }
-                    ErrorHandling::Panic => {
-                        writeln!(buf, "panic!(\"Failed to get '{snake_name}' at line={{linenum}} column={i}\"),")?;
-                    }
-                }
+                use calamine::Reader;
+
+                let mut workbook = calamine::open_workbook_auto(filename)?;
+                let cells = workbook.worksheet_range(Self::SHEET)?;
+                #range
+                let first_row = cells.start().map_or(0, |(row, _)| row as u64);
+
+                let #mutability rows = cells
+                    .rows()
+                    .enumerate()
+                    .map(|(i, cells)| {
+                        let record = cells.iter().map(cell_text).collect::<csv::StringRecord>();
+                        (first_row + i as u64 + 1, record)
+                    })
+                    .filter(|(_, record)| record.iter().any(|value| !value.is_empty()))
+                    .collect::<Vec<_>>()
+                    .into_iter();
+                #indices
+                let row = csv::StringRecord::default();
+                Ok(#iterator { rows, row, indices })
             }
+        }
+    }
 
-            // Handle the Some start for all non-strings
-            //if !matches!(r#type, ColumnType::String(_)) {
-            if optional {
-                writeln!(buf, "{indent}    Some(\"\") => None,")?;
-                //} else if matches!(r#type, ColumnType::Unit) {
-                //    writeln!(buf, "{indent}    Some(\"\") => (),")?;
+    /// `next_record`, which takes the next row of a worksheet, positioned at its row number.
+    fn next_sheet_record(&self, typename: &str) -> TokenStream {
+        let iterator = format_ident!("{typename}Iterator");
+
+        quote! {
+            impl #iterator {
+                /// Takes the next row that isn't empty, positioned at its row of the worksheet
+                fn next_record(&mut self) -> Option<Result<csv::StringRecord, calamine::Error>> {
+                    let (row, mut record) = self.rows.next()?;
+                    let index = self.row.position().map_or(0, |p| p.record() + 1);
+                    let mut position = csv::Position::new();
+                    position.set_record(index).set_line(row);
+                    record.set_position(Some(position));
+                    Some(Ok(record))
+                }
             }
-            //}
+        }
+    }
 
-            let error_string = match error_handling {
-                ErrorHandling::IgnoreRow => "continue".to_string(),
-                ErrorHandling::Result => format!("return Some(Err((linenum, \"{snake_name}\", val).into()))"),
-                ErrorHandling::Panic =>  format!("panic!(\"Unexpected '{snake_name}' value '{{val}}' at line={{linenum}} column={i}\")"),
-            };
+    /// The `cell_text` function, which converts a worksheet cell to text in the same way as when
+    /// the types were inferred.
+    fn cell_text(&self) -> TokenStream {
+        let vis = self.common_module.then(|| quote! { pub });
+
+        quote! {
+            /// The text of a cell. Dates are ISO 8601 text, durations are a number of days, and errors are empty.
+            #vis fn cell_text(cell: &calamine::Data) -> String {
+                use calamine::{Data, DataType};
+
+                match cell {
+                    Data::Empty | Data::Error(_) => String::new(),
+                    Data::DateTime(d) if d.is_duration() => d.as_f64().to_string(),
+                    Data::DateTime(d) => match cell.as_datetime() {
+                        Some(dt) if d.as_f64().fract() == 0.0 => dt.date().to_string(),
+                        Some(dt) => format!("{}T{}", dt.date(), dt.time()),
+                        None => d.as_f64().to_string(),
+                    },
+                    cell => cell.to_string(),
+                }
+            }
+        }
+    }
 
-            match r#type {
-                ColumnType::String(_) => match self.args.string_handling {
-                    //StringHandling::Owned if self.args.trim_input => {
-                    //    writeln!(buf, "{indent}    Some(val) => Some(val.to_owned())")?
-                    //}
-                    StringHandling::Owned if optional => {
-                        writeln!(buf, "{indent}    Some(val) => Some(val.to_owned())")?
-                    }
-                    StringHandling::Owned => {
-                        writeln!(buf, "{indent}    Some(val) => val.to_owned()")?
-                    }
-                    StringHandling::Static => {
-                        //writeln!(buf, "Some(val) => match val {{")?;
-                        for seen in seen_values {
-                            writeln!(buf, "{indent}    Some(\"{seen}\") => \"{seen}\",")?;
-                        }
-                        writeln!(buf, "{indent}    Some(val) => {error_string},")?;
-                        //writeln!(buf, "{indent}    }}")?;
-                    }
-                    StringHandling::Enum(_) => {
-                        writeln!(buf, "{indent}    Some(val) => match val.parse() {{")?;
-                        writeln!(buf, "{indent}        Ok(v) => v,")?;
-                        writeln!(buf, "{indent}        Err(_) => {error_string},")?;
-                        writeln!(buf, "{indent}    }}")?;
-                    }
-                },
-                ColumnType::Bool(_) => {
-                    // Bools will do case-insensitive comparisons for 'true'/'false'
-                    write!(
-                        buf,
-                        "{indent}    Some(val) if val.eq_ignore_ascii_case(\"true\") => "
-                    )?;
+    /// `TABLE`, `open_sqlite` and `load_sqlite`, which read the rows of the table as text and
+    /// check its column names, and `load_csv`, which reads a CSV export of the table into the
+    /// same rows.
+    fn load_sqlite(&self, typename: &str) -> TokenStream {
+        let iterator = format_ident!("{typename}Iterator");
+        let table = self.args.table.as_deref().unwrap_or_default();
+        let select = format!("SELECT * FROM {}", util::quote_identifier(table));
+        let (_, convert) = self.sqlite_row_error(typename);
+
+        quote! {
+            /// The table rows are read from
+            pub const TABLE: &'static str = #table;
+
+            /// Opens the SQLite database `filename` read-only, and reads the rows of `TABLE` as `load_sqlite` does.
+            pub fn open_sqlite<P>(filename: P) -> Result<#iterator, rusqlite::Error>
+            where
+                P: AsRef<std::path::Path>,
+            {
+                let conn = rusqlite::Connection::open_with_flags(filename, rusqlite::OpenFlags::SQLITE_OPEN_READ_ONLY)?;
+                Self::load_sqlite(conn)
+            }
 
-                    if optional {
-                        writeln!(buf, "Some(true),")?;
-                    } else {
-                        writeln!(buf, "true,")?;
+            /// Reads the rows of `TABLE` and checks its column names. Each value is read as text, in the same way as when the types were inferred.
+            ///
+            /// The query borrows the connection, so both are moved to a thread that reads rows as the iterator takes them.
+            pub fn load_sqlite(conn: rusqlite::Connection) -> Result<#iterator, rusqlite::Error> {
+                // The column names are sent first, and then at most 1024 rows ahead of the iterator
+                let (sender, receiver) = std::sync::mpsc::sync_channel(1024);
+                std::thread::spawn(move || {
+                    if let Err(e) = Self::send_sqlite_rows(&conn, &sender) {
+                        let _ = sender.send(Err(e));
                     }
+                });
 
-                    write!(
-                        buf,
-                        "{indent}    Some(val) if val.eq_ignore_ascii_case(\"false\") => "
-                    )?;
-                    if optional {
-                        writeln!(buf, "Some(false),")?;
-                    } else {
-                        writeln!(buf, "false,")?;
-                    }
+                let headers = receiver.recv().expect("the SQLite reader stopped")?;
+                let indices = check_headers(&headers, &Self::HEADERS)?;
+                let rows = Box::new(receiver.into_iter().map(|r| r.map_err(#convert)));
+                let row = csv::StringRecord::default();
+                Ok(#iterator { rows, row, indices })
+            }
 
-                    writeln!(buf, "{indent}    Some(val) => {error_string}")?;
+            /// Sends the column names of `TABLE`, and then each of its rows positioned at its row number, until the receiver is dropped.
+            fn send_sqlite_rows(
+                conn: &rusqlite::Connection,
+                sender: &std::sync::mpsc::SyncSender<Result<csv::StringRecord, rusqlite::Error>>,
+            ) -> Result<(), rusqlite::Error> {
+                let mut statement = conn.prepare(#select)?;
+                let headers = statement.column_names().into_iter().collect::<csv::StringRecord>();
+                let width = headers.len();
+                if sender.send(Ok(headers)).is_err() {
+                    return Ok(());
                 }
-                _ => {
-                    writeln!(buf, "{indent}    Some(val) => match val.parse() {{")?;
-                    if optional {
-                        writeln!(buf, "{indent}        Ok(v) => Some(v),")?;
-                    } else {
-                        writeln!(buf, "{indent}        Ok(v) => v,")?;
+
+                let mut query = statement.query([])?;
+                let mut index = 0;
+                while let Some(row) = query.next()? {
+                    let mut record = (0..width)
+                        .map(|i| row.get_ref(i).map(sqlite_text))
+                        .collect::<Result<csv::StringRecord, _>>()?;
+                    let mut position = csv::Position::new();
+                    position.set_record(index).set_line(index + 1);
+                    record.set_position(Some(position));
+                    if sender.send(Ok(record)).is_err() {
+                        break;
                     }
-                    writeln!(buf, "{indent}        Err(_) => {error_string},")?;
-                    writeln!(buf, "{indent}    }}")?;
+                    index += 1;
                 }
+                Ok(())
             }
 
-            writeln!(buf, "{indent}}};")?; // end this particular column
-            writeln!(buf)?;
+            /// Reads a CSV export of `TABLE` with a header row, such as from `sqlite3 -header -csv`, as the same rows as `load_sqlite`.
+            pub fn load_csv<P>(filename: P) -> Result<#iterator, csv::Error>
+            where
+                P: AsRef<std::path::Path>,
+            {
+                let mut reader = csv::Reader::from_path(filename)?;
+                let indices = check_headers(reader.headers()?, &Self::HEADERS).map_err(|e| {
+                    csv::Error::from(std::io::Error::new(std::io::ErrorKind::InvalidData, e.to_string()))
+                })?;
+                let rows = Box::new(reader.into_records().map(|r| r.map_err(#convert)));
+                let row = csv::StringRecord::default();
+                Ok(#iterator { rows, row, indices })
+            }
         }
+    }
 
-        // Create the struct that will be returned
-        {
-            writeln!(buf, "{indent}let res = {typename} {{")?;
-            for col in &self.columns {
-                writeln!(
-                    buf,
-                    "{indent}    {},",
-                    util::str_to_snake_case_identifier(&col.name)
-                )?;
-            }
-            writeln!(buf, "{indent}}};")?;
-            writeln!(buf)?;
+    /// `next_record`, which takes the next row that was read.
+    fn next_sqlite_record(&self, typename: &str) -> TokenStream {
+        let iterator = format_ident!("{typename}Iterator");
+        let (error, _) = self.sqlite_row_error(typename);
+
+        quote! {
+            impl #iterator {
+                /// Takes the next row that was read
+                fn next_record(&mut self) -> Option<Result<csv::StringRecord, #error>> {
+                    self.rows.next()
+                }
+            }
         }
+    }
 
+    /// The error of each row read from a SQLite table or a CSV export of it, and the conversion
+    /// from either source's error. Errors are only kept when they're returned.
+    fn sqlite_row_error(&self, typename: &str) -> (TokenStream, TokenStream) {
         match self.args.error_handling {
-            ErrorHandling::IgnoreRow => {
-                writeln!(buf, "            return Some(res);")?;
-                writeln!(buf, "        }} // end loop")?;
+            ErrorHandling::Result => {
+                let error = format_ident!("{typename}Error");
+                (quote! { #error }, quote! { #error::from })
             }
-            ErrorHandling::Result => writeln!(buf, "{indent}Some(Ok(res))")?,
-            ErrorHandling::Panic => writeln!(buf, "{indent}Some(res)")?,
+            _ => (quote! { () }, quote! { |_| () }),
         }
+    }
 
-        writeln!(buf, "    }}")?;
-        writeln!(buf, "}}")?;
-        writeln!(buf)?;
+    /// The `parquet_text` function, which converts a Parquet value to text in the same way as when
+    /// the types were inferred.
+    fn parquet_text(&self) -> TokenStream {
+        let vis = self.common_module.then(|| quote! { pub });
+
+        quote! {
+            /// The text of a Parquet value. Null is empty, a date or timestamp is ISO 8601 text in UTC, and a decimal is the integer of its digits.
+            #vis fn parquet_text(field: &parquet::record::Field) -> String {
+                use parquet::record::Field;
+
+                // A timestamp's date is formatted as a Parquet date, and its time of day from the remainder
+                let timestamp = |t: i64, per_second: i64, digits: usize| {
+                    let per_day = per_second * 86_400;
+                    let date = Field::Date(t.div_euclid(per_day) as i32);
+                    let time = t.rem_euclid(per_day);
+                    let seconds = time / per_second;
+                    format!(
+                        "{date}T{:02}:{:02}:{:02}.{:0digits$}Z",
+                        seconds / 3600,
+                        seconds / 60 % 60,
+                        seconds % 60,
+                        time % per_second
+                    )
+                };
+
+                match field {
+                    Field::Null => String::new(),
+                    Field::Str(s) => s.clone(),
+                    Field::Bytes(b) => String::from_utf8_lossy(b.data()).into_owned(),
+                    Field::TimestampMillis(t) => timestamp(*t, 1_000, 3),
+                    Field::TimestampMicros(t) => timestamp(*t, 1_000_000, 6),
+                    Field::Decimal(d) => {
+                        // The digits are big-endian two's complement, sign-extended to an i128
+                        let data = &d.data()[d.data().len().saturating_sub(16)..];
+                        let fill = if data.first().is_some_and(|b| b & 0x80 != 0) { 0xff } else { 0 };
+                        let mut bytes = [fill; 16];
+                        bytes[16 - data.len()..].copy_from_slice(data);
+                        i128::from_be_bytes(bytes).to_string()
+                    }
+                    field => field.to_string(),
+                }
+            }
+        }
+    }
 
-        writeln!(buf, "#[test]")?;
-        writeln!(buf, "fn test_load() {{")?;
-        match self.args.error_handling {
-            ErrorHandling::Result => {
-                // for the result type, our sample will flatten Result<T,E> out to T
-                writeln!(
-                    buf,
-                    "    for row in {typename}::load_csv({:?})",
-                    self.args.input_file
-                )?;
-                writeln!(buf, "        .expect(\"Couldn't load file\")")?;
-                writeln!(buf, "        .map_while(Result::ok)")?;
-                writeln!(buf, "    {{")?;
-            }
-            _ => {
-                // other kinds will always get T
-                writeln!(
-                    buf,
-                    "    for row in {typename}::load_csv({:?})",
-                    self.args.input_file
-                )?;
-                writeln!(buf, "        .expect(\"Couldn't load file\") {{")?;
-            }
-        }
-        writeln!(buf, "        println!(\"Got row: {{row:?}}\");")?;
-        writeln!(buf, "    }}")?;
-        writeln!(buf, "}}")?;
+    /// The `sqlite_text` function, which converts a SQLite value to text in the same way as when
+    /// the types were inferred.
+    fn sqlite_text(&self) -> TokenStream {
+        let vis = self.common_module.then(|| quote! { pub });
+
+        quote! {
+            /// The text of a SQLite value. `NULL` is empty, a real number always has a decimal point or exponent, and a BLOB is read as UTF-8.
+            #vis fn sqlite_text(value: rusqlite::types::ValueRef) -> String {
+                use rusqlite::types::ValueRef;
+
+                match value {
+                    ValueRef::Null => String::new(),
+                    ValueRef::Integer(i) => i.to_string(),
+                    ValueRef::Real(f) => format!("{f:?}"),
+                    ValueRef::Text(s) | ValueRef::Blob(s) => String::from_utf8_lossy(s).into_owned(),
+                }
+            }
+        }
+    }
 
-        Ok(())
+    /// The function that generated types load their input with, and the error it returns.
+    fn loader(&self) -> (Ident, TokenStream) {
+        let (load, error) = match self.args.input_format() {
+            InputFormat::Csv => ("load_csv", quote! { csv::Error }),
+            InputFormat::Parquet => ("load_parquet", quote! { parquet::errors::ParquetError }),
+            InputFormat::FixedWidth => ("load_fixed_width", quote! { std::io::Error }),
+            InputFormat::Spreadsheet => ("load_sheet", quote! { calamine::Error }),
+            InputFormat::Sqlite => ("open_sqlite", quote! { rusqlite::Error }),
+        };
+        (syntax::ident(load), error)
     }
-}
 
-impl CsvColumnInfo {
-    pub fn write_enum(&self, buf: &mut BufWriter<File>) -> Result<(), std::io::Error> {
-        let enum_name = util::str_to_camel_case_identifier(&self.name);
+    /// Whether string values can be copied, which they can't when they're owned or an enum has a
+    /// catch-all variant holding unseen values
+    fn strings_are_copy(&self) -> bool {
+        match self.args.string_handling {
+            StringHandling::Owned => false,
+            StringHandling::Static => true,
+            StringHandling::Enum(_) | StringHandling::EnumOpen => self.args.catch_all().is_none(),
+        }
+    }
 
-        // definition
-        {
-            writeln!(buf, "#[derive(Copy, Clone, Debug, PartialEq, Eq)]")?;
-            writeln!(buf, "pub enum {enum_name} {{")?;
+    /// Whether any column is absent from some of the input files
+    fn may_be_missing(&self) -> bool {
+        self.columns.iter().any(|c| c.may_be_missing)
+    }
+
+    /// The `check_headers` function used by `load_csv` or `load_parquet` to validate the header row
+    /// and find the position of each column, according to the `--header-check` argument.
+    ///
+    /// When columns may be missing from some files, their position can't be fixed, so an
+    /// exact check only requires the same columns, and missing columns are given a position
+    /// past the end of every row.
+    fn check_headers(&self) -> TokenStream {
+        let vis = self.common_module.then(|| quote! { pub });
+        let header = if self.args.trim_input {
+            quote! { h.trim() }
+        } else {
+            quote! { h }
+        };
+        let may_be_missing = self.may_be_missing();
+        let header_check = match self.args.header_check {
+            HeaderCheck::Exact if may_be_missing => HeaderCheck::SameSet,
+            hc => hc,
+        };
+
+        let docs = match header_check {
+            HeaderCheck::Exact => quote! {
+                /// Checks that the header row exactly matches `expected`, including order.
+            },
+            HeaderCheck::SameSet if may_be_missing => quote! {
+                /// Checks that the header row contains only columns in `expected`, in any order, and every
+                /// column that isn't `may_be_missing`.
+            },
+            HeaderCheck::SameSet => quote! {
+                /// Checks that the header row contains the same columns as `expected`, in any order.
+            },
+            HeaderCheck::Superset if may_be_missing => quote! {
+                /// Checks that the header row contains every column in `expected` that isn't `may_be_missing`,
+                /// in any order.
+            },
+            HeaderCheck::Superset => quote! {
+                /// Checks that the header row contains every column in `expected`, in any order.
+            },
+        };
+        let may_be_missing_param = may_be_missing.then(|| quote! { may_be_missing: &[bool; N], });
+        let (_, load_error) = self.loader();
+        let mismatch = match self.args.input_format() {
+            InputFormat::Csv => quote! {
+                csv::Error::from(std::io::Error::new(std::io::ErrorKind::InvalidData, message))
+            },
+            InputFormat::Parquet => quote! { parquet::errors::ParquetError::General(message) },
+            InputFormat::FixedWidth => quote! {
+                std::io::Error::new(std::io::ErrorKind::InvalidData, message)
+            },
+            InputFormat::Spreadsheet => quote! {
+                calamine::Error::from(std::io::Error::new(std::io::ErrorKind::InvalidData, message))
+            },
+            InputFormat::Sqlite => quote! { rusqlite::Error::InvalidColumnName(message) },
+        };
 
-            for seen_value in &self.seen_values {
-                let seen_value_name = util::str_to_camel_case_identifier(seen_value);
+        let unexpected = if header_check == HeaderCheck::SameSet && may_be_missing {
+            Some(quote! {
+                if let Some(h) = headers.iter().find(|h| !expected.contains(&#header)) {
+                    return Err(mismatch(format!(
+                        "Unexpected column '{h}' in header {headers:?}"
+                    )));
+                }
+            })
+        } else if header_check != HeaderCheck::Superset {
+            Some(quote! {
+                if headers.len() != N {
+                    return Err(mismatch(format!(
+                        "Expected {N} columns but found {} in header {headers:?}",
+                        headers.len()
+                    )));
+                }
+            })
+        } else {
+            None
+        };
 
-                if seen_value_name != *seen_value {
-                    writeln!(buf, "    /// From the input string '{seen_value}'")?;
+        let find = if header_check == HeaderCheck::Exact {
+            let found = if self.args.trim_input {
+                quote! { headers.get(i).map(|h| h.trim()) }
+            } else {
+                quote! { headers.get(i) }
+            };
+            quote! {
+                if #found != Some(*name) {
+                    return Err(mismatch(format!(
+                        "Expected column '{name}' at position {i} in header {headers:?}"
+                    )));
                 }
-                writeln!(buf, "    {seen_value_name},")?;
+                indices[i] = i;
             }
+        } else {
+            // No row has a value at the position of a missing column
+            let missing =
+                may_be_missing.then(|| quote! { None if may_be_missing[i] => usize::MAX, });
+            // A repeated header is matched to the next column with that header
+            quote! {
+                let unused = |&(pos, h): &(usize, &str)| {
+                    #header == *name && !indices[..i].contains(&pos)
+                };
+                indices[i] = match headers.iter().enumerate().find(unused) {
+                    Some((pos, _)) => pos,
+                    #missing
+                    None => {
+                        return Err(mismatch(format!(
+                            "Missing column '{name}' in header {headers:?}"
+                        )))
+                    }
+                };
+            }
+        };
 
-            writeln!(buf, "}}")?;
-            writeln!(buf)?;
+        quote! {
+            #docs
+            /// Returns the position of each expected column within the header row.
+            #vis fn check_headers<const N: usize>(
+                headers: &csv::StringRecord,
+                expected: &[&str; N],
+                #may_be_missing_param
+            ) -> Result<[usize; N], #load_error> {
+                let mismatch = |message: String| {
+                    #mismatch
+                };
+
+                #unexpected
+
+                let mut indices = [0; N];
+                for (i, name) in expected.iter().enumerate() {
+                    #find
+                }
+
+                Ok(indices)
+            }
         }
+    }
+}
+
+impl CsvColumnInfo {
+    /// The name of the column's field, which is the same in every emitted language
+    pub(crate) fn field_name(&self) -> String {
+        naming::field_name(&self.name)
+    }
+
+    /// The name of the enum of a string column's values, with `--strings enum`
+    pub(crate) fn enum_name(&self) -> String {
+        self.enum_name.clone()
+    }
 
-        // implementation
+    /// The values a string column can only have with 'static' or 'enum' string handling, which
+    /// are those seen, in order. Other columns have none.
+    fn known_values(&self, string_handling: StringHandling) -> Vec<&str> {
+        let mut values = Vec::new();
+        if string_handling != StringHandling::Owned && matches!(self.r#type, ColumnType::String(_))
         {
-            writeln!(buf, "impl std::str::FromStr for {enum_name} {{")?;
-            writeln!(buf, "    type Err = String;")?;
-            writeln!(buf)?;
-            writeln!(
-                buf,
-                "    fn from_str(s: &str) -> Result<Self, Self::Err> {{"
-            )?;
+            values.extend(
+                self.seen_values
+                    .iter()
+                    .filter(|v| !v.is_empty())
+                    .map(String::as_str),
+            );
+            values.sort();
+        }
+        values
+    }
 
-            writeln!(buf, "        match s {{")?;
-            for seen_value in &self.seen_values {
-                writeln!(
-                    buf,
-                    "            \"{seen_value}\" => Ok(Self::{}),",
-                    util::str_to_camel_case_identifier(seen_value)
-                )?;
-            }
-            writeln!(buf, "            _ => Err(s.to_string()),")?;
-            writeln!(buf, "        }}")?;
-            writeln!(buf, "    }}")?;
-            writeln!(buf, "}}")?;
-            writeln!(buf)?;
+    /// The names of the enum variants of the column's `known_values`, with 'enum' string handling
+    fn variants(&self, args: &Commands) -> Vec<String> {
+        naming::variant_names(
+            &self.known_values(args.string_handling),
+            &args.variant_fallback,
+            args.catch_all(),
+        )
+    }
+
+    /// Explains why the column was given its type.
+    fn reason(&self) -> String {
+        match &self.source_type {
+            Some(source_type) => format!("it's {source_type}"),
+            None => self.inference.reason(),
         }
+    }
 
-        Ok(())
+    /// The Arrow `DataType` of the column's values. Strings are UTF-8, or dictionary encoded when
+    /// they're an enum.
+    pub(crate) fn arrow_type(&self, string_handling: StringHandling) -> String {
+        let ty = match self.r#type {
+            ColumnType::Unit => "Null",
+            ColumnType::Bool(_) => "Boolean",
+            ColumnType::I8(_) => "Int8",
+            ColumnType::I16(_) => "Int16",
+            ColumnType::I32(_) => "Int32",
+            ColumnType::I64(_) => "Int64",
+            ColumnType::U8(_) => "UInt8",
+            ColumnType::U16(_) => "UInt16",
+            ColumnType::U32(_) => "UInt32",
+            ColumnType::U64(_) => "UInt64",
+            ColumnType::F64(_) => "Float64",
+            ColumnType::String(_) => match string_handling {
+                StringHandling::Enum(_) => {
+                    let key = match self.dictionary_key() {
+                        "u8" => "UInt8",
+                        _ => "UInt16",
+                    };
+                    return format!(
+                        "DataType::Dictionary(Box::new(DataType::{key}), Box::new(DataType::Utf8))"
+                    );
+                }
+                _ => "Utf8",
+            },
+        };
+        format!("DataType::{ty}")
+    }
+
+    /// The Arrow builder for the column's values, if it needs one. Unit columns are all null,
+    /// and enum columns are built as the keys of a dictionary.
+    pub(crate) fn arrow_builder(&self, string_handling: StringHandling) -> Option<&'static str> {
+        let builder = match self.r#type {
+            ColumnType::Unit => return None,
+            ColumnType::Bool(_) => "BooleanBuilder",
+            ColumnType::I8(_) => "Int8Builder",
+            ColumnType::I16(_) => "Int16Builder",
+            ColumnType::I32(_) => "Int32Builder",
+            ColumnType::I64(_) => "Int64Builder",
+            ColumnType::U8(_) => "UInt8Builder",
+            ColumnType::U16(_) => "UInt16Builder",
+            ColumnType::U32(_) => "UInt32Builder",
+            ColumnType::U64(_) => "UInt64Builder",
+            ColumnType::F64(_) => "Float64Builder",
+            ColumnType::String(_) => match string_handling {
+                StringHandling::Enum(_) if self.dictionary_key() == "u8" => "UInt8Builder",
+                StringHandling::Enum(_) => "UInt16Builder",
+                _ => "StringBuilder",
+            },
+        };
+        Some(builder)
+    }
+
+    /// The integer type of the keys of an enum column's Arrow dictionary, which is the index of
+    /// each variant
+    fn dictionary_key(&self) -> &'static str {
+        if self.seen_values.len() <= 256 {
+            "u8"
+        } else {
+            "u16"
+        }
+    }
+
+    /// The Rust type of the column's values, without the `Option` of an optional column
+    pub(crate) fn value_type(&self, string_handling: StringHandling) -> String {
+        let ty = self.as_str(string_handling);
+        match ty.strip_prefix("Option<") {
+            Some(inner) => inner[..inner.len() - 1].to_string(),
+            None => ty.into_owned(),
+        }
     }
 
     pub(crate) fn as_str(&self, string_handling: StringHandling) -> Cow<'static, str> {
@@ -641,13 +3521,373 @@ impl CsvColumnInfo {
                 (true, StringHandling::Owned) => "Option<String>".into(),
                 (false, StringHandling::Static) => "&'static str".into(),
                 (true, StringHandling::Static) => "Option<&'static str>".into(),
-                (false, StringHandling::Enum(_)) => {
-                    util::str_to_camel_case_identifier(&self.name).into()
+                (false, StringHandling::Enum(_) | StringHandling::EnumOpen) => {
+                    self.enum_name().into()
                 }
-                (true, StringHandling::Enum(_)) => {
-                    format!("Option<{}>", util::str_to_camel_case_identifier(&self.name)).into()
+                (true, StringHandling::Enum(_) | StringHandling::EnumOpen) => {
+                    format!("Option<{}>", self.enum_name()).into()
                 }
             },
         }
     }
 }
+
+/// Generates the Rust code for a CSV file with the contents `csv`, run with `args`.
+#[cfg(test)]
+fn generate_for(name: &str, csv: &str, args: &[&str]) -> String {
+    try_generate(name, csv, None, args).unwrap()
+}
+
+/// Generates the Rust code for a CSV file with the contents `csv` and, if given, a '.def' file
+/// with the contents `def`, run with `args`.
+#[cfg(test)]
+fn try_generate(
+    name: &str,
+    csv: &str,
+    def: Option<&str>,
+    args: &[&str],
+) -> Result<String, TypeGenErrors> {
+    let mut buf = Vec::new();
+    analyze_for(name, csv, def, args)?.generate(&mut buf)?;
+    Ok(String::from_utf8(buf).unwrap())
+}
+
+/// Analyzes a CSV file with the contents `csv` and, if given, a '.def' file with the contents
+/// `def`, run with `args`.
+#[cfg(test)]
+fn analyze_for(
+    name: &str,
+    csv: &str,
+    def: Option<&str>,
+    args: &[&str],
+) -> Result<CsvFileInfo, TypeGenErrors> {
+    use clap::Parser;
+
+    let path =
+        std::env::temp_dir().join(format!("generate-type-{}-{name}.csv", std::process::id()));
+    std::fs::write(&path, csv)?;
+    if let Some(def) = def {
+        std::fs::write(path.with_extension("def"), def)?;
+    }
+    let path_arg = path.to_str().unwrap();
+    let args = Commands::try_parse_from(
+        ["cargo-generate-type", "generate-type", path_arg]
+            .into_iter()
+            .chain(args.iter().copied()),
+    )
+    .unwrap();
+    let info = CsvFileInfo::new(args)
+        .analyze_input()
+        .map(|i| i.load_data_def());
+    std::fs::remove_file(&path)?;
+    if def.is_some() {
+        std::fs::remove_file(path.with_extension("def"))?;
+    }
+    info
+}
+
+#[test]
+fn test_check_headers() {
+    let csv = " id , name \n1,a\n2,b\n";
+
+    // Headers are stored as they're compared, so trimmed input accepts its own file
+    let exact = generate_for("exact", csv, &["--trim-input"]);
+    assert!(exact.contains(r#"pub const HEADERS: [&'static str; 2] = ["id", "name"];"#));
+    assert!(exact.contains("if headers.len() != N {"));
+    assert!(exact.contains("if headers.get(i).map(|h| h.trim()) != Some(*name) {"));
+
+    let exact = generate_for("untrimmed", csv, &[]);
+    assert!(exact.contains(r#"pub const HEADERS: [&'static str; 2] = [" id ", " name "];"#));
+    assert!(exact.contains("if headers.get(i) != Some(*name) {"));
+
+    // A set of columns in any order must still have no others
+    let set = generate_for("set", csv, &["--trim-input", "--header-check", "same-set"]);
+    assert!(set.contains("if headers.len() != N {"));
+    assert!(set.contains("h.trim() == *name && !indices[..i].contains(&pos)"));
+    assert!(!set.contains("headers.get(i)"));
+
+    // A superset may have any other columns
+    let superset = generate_for("superset", csv, &["--header-check", "superset"]);
+    assert!(!superset.contains("headers.len() != N"));
+    assert!(superset.contains("h == *name && !indices[..i].contains(&pos)"));
+}
+
+#[test]
+fn test_load_csv_no_header() {
+    // Without a header row, the reader is never borrowed mutably
+    let code = generate_for("load-no-header", "1,a\n2,b\n", &["--no-header"]);
+    assert!(code.contains("let reader = csv::ReaderBuilder::new()"));
+    assert!(code.contains("let indices = [0, 1];"));
+
+    let code = generate_for("load-header", "id,name\n1,a\n2,b\n", &[]);
+    assert!(code.contains("let mut reader = csv::ReaderBuilder::new()"));
+}
+
+#[test]
+fn test_schema_hash() {
+    let schema_hash = |name, csv, args: &[&str]| {
+        let code = generate_for(name, csv, args);
+        let start = code.find("pub const SCHEMA_HASH: u64 = ").unwrap();
+        code[start..].lines().next().unwrap().to_string()
+    };
+
+    // The hash doesn't depend on the order in which values are seen
+    let a = schema_hash("hash-a", "id,name\n1,a\n2,b\n", &["--strings", "enum"]);
+    let b = schema_hash("hash-b", "id,name\n2,b\n1,a\n", &["--strings", "enum"]);
+    assert_eq!(a, b);
+    assert_eq!(
+        a,
+        schema_hash("hash-a", "id,name\n1,a\n2,b\n", &["--strings", "enum"])
+    );
+
+    // but does on the headers, types and values
+    assert_ne!(
+        a,
+        schema_hash("hash-c", "id,label\n1,a\n2,b\n", &["--strings", "enum"])
+    );
+    assert_ne!(
+        a,
+        schema_hash("hash-d", "id,name\n1.5,a\n2,b\n", &["--strings", "enum"])
+    );
+    assert_ne!(
+        a,
+        schema_hash("hash-e", "id,name\n1,a\n2,c\n", &["--strings", "enum"])
+    );
+
+    // Owned strings hash the same whether or not their values are collected
+    let owned = schema_hash("hash-f", "id,name\n1,a\n2,b\n", &[]);
+    assert_eq!(
+        owned,
+        schema_hash(
+            "hash-f",
+            "id,name\n1,a\n2,b\n",
+            &["--validate", "--validate-values"]
+        )
+    );
+    assert_eq!(
+        owned,
+        schema_hash(
+            "hash-f",
+            "id,name\n1,a\n2,c\n",
+            &["--validate", "--validate-values"]
+        )
+    );
+}
+
+#[test]
+fn test_validate_values() {
+    let csv = "id,name\n1,a\n2,b\n";
+
+    // Owned strings accept any value unless asked to check them
+    let code = generate_for("values-owned", csv, &["--validate"]);
+    assert!(!code.contains("matches!(v.as_str()"));
+
+    let code = generate_for("values-checked", csv, &["--validate", "--validate-values"]);
+    assert!(code.contains(r#"if !matches!(v.as_str(), "a" | "b") {"#));
+}
+
+#[test]
+fn test_enum_pattern() {
+    let def = "[code]\n@pattern ^[A-Z]+$\n";
+    let pattern = |name, csv, strings| {
+        try_generate(name, csv, Some(def), &["--validate", "--strings", strings])
+    };
+
+    assert!(pattern("pattern-owned", "id,code\n1,AB\n2,\n", "owned").is_ok());
+
+    // Enum values can't be matched against a pattern, even when the column is optional
+    for strings in ["enum", "enum-open"] {
+        let err = pattern("pattern-enum", "id,code\n1,AB\n2,CD\n", strings).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "@pattern can't be used with enum column 'code'"
+        );
+        let err = pattern("pattern-optional", "id,code\n1,AB\n2,\n", strings).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "@pattern can't be used with enum column 'code'"
+        );
+    }
+}
+
+#[test]
+fn test_columns_validity() {
+    let code = generate_for("validity", "id,score\n1,2.5\n2,\n", &["--columns"]);
+    assert!(code.contains(
+        "file stored by column. Each optional column has a validity bitmap, in which bit `i % 64` of word `i / 64` is set if row `i` has a value, and a placeholder value for each row that doesn't.\n#[derive"
+    ));
+
+    // Only optional columns have a validity bitmap, with a word for every 64 rows
+    assert!(code.contains(
+        "pub id: Vec<u8>,\n    pub score: Vec<f64>,\n    pub score_validity: Vec<u64>,\n"
+    ));
+    assert!(!code.contains("id_validity"));
+    assert!(code.contains("let (word, bit) = (self.len / 64, 1u64 << (self.len % 64));"));
+    assert!(code.contains("if bit == 1 {\n            self.score_validity.push(0);\n        }"));
+
+    // A value sets its row's bit, and a missing value leaves it clear with a default in its place
+    assert!(code.contains(
+        "Some(v) => {\n                self.score.push(v);\n                self.score_validity[word] |= bit;\n            }\n            None => self.score.push(Default::default()),"
+    ));
+    assert!(code.contains("(validity[i / 64] >> (i % 64)) & 1 == 1"));
+    assert!(
+        code.contains("score: Self::is_valid(&self.score_validity, i).then_some(self.score[i]),")
+    );
+}
+
+#[test]
+fn test_arrow_types() {
+    let csv = "e,b,i8,i16,i32,i64,u8,u16,u32,u64,f,s,o\n\
+               ,true,-1,-300,-70000,-3000000000,1,300,70000,5000000000,1.5,a,\n\
+               ,false,1,1,1,1,2,2,2,2,2,b,3\n";
+    let arrow_types = |string_handling| {
+        let args = ["--arrow", "--strings", string_handling];
+        let info = analyze_for("arrow", csv, None, &args).unwrap();
+        let string_handling = info.args.string_handling;
+        info.columns
+            .iter()
+            .map(|col| col.arrow_type(string_handling))
+            .collect::<Vec<_>>()
+    };
+
+    // Integers keep their narrowed widths, and an optional column has the type of its values
+    let types = arrow_types("owned");
+    assert_eq!(
+        types,
+        [
+            "DataType::Null",
+            "DataType::Boolean",
+            "DataType::Int8",
+            "DataType::Int16",
+            "DataType::Int32",
+            "DataType::Int64",
+            "DataType::UInt8",
+            "DataType::UInt16",
+            "DataType::UInt32",
+            "DataType::UInt64",
+            "DataType::Float64",
+            "DataType::Utf8",
+            "DataType::UInt8",
+        ]
+    );
+    assert_eq!(arrow_types("static")[11], "DataType::Utf8");
+
+    // Enums are dictionaries keyed by the index of each variant
+    assert_eq!(
+        arrow_types("enum")[11],
+        "DataType::Dictionary(Box::new(DataType::UInt8), Box::new(DataType::Utf8))"
+    );
+}
+
+#[test]
+fn test_arrow_catch_all() {
+    use clap::Parser;
+
+    // Dictionaries can only hold the values seen, so enums that hold others can't be Arrow arrays
+    let csv = "id,kind\n1,a\n";
+    let args = ["--arrow", "--strings", "enum-open"];
+    let err = analyze_for("arrow-open", csv, None, &args).unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "--arrow can't be used with enums that hold values that weren't seen"
+    );
+
+    let parse = |args: &[&str]| {
+        let command = ["cargo-generate-type", "generate-type", "events.csv"];
+        Commands::try_parse_from(command.iter().chain(args))
+    };
+    assert!(parse(&["--arrow", "--strings", "enum", "--catch-all"]).is_err());
+    assert!(parse(&["--arrow", "--strings", "enum"]).is_ok());
+}
+
+#[test]
+fn test_sql_column_names() {
+    let csv = "id,,Name,name\n1,a,b,c\n";
+    let args = ["--emit", "sql", "--sql-load", "insert"];
+    let info = analyze_for("sql", csv, None, &args).unwrap();
+    let mut buf = Vec::new();
+    info.write_sql(&mut buf).unwrap();
+    let sql = String::from_utf8(buf).unwrap();
+
+    // Columns are named by their headers, except unnamed and repeated ones, which are named as
+    // their fields are
+    assert!(sql.contains("\n    \"id\" SMALLINT NOT NULL,\n    \"column_1\" TEXT NOT NULL,\n    \"Name\" TEXT NOT NULL,\n    \"name_2\" TEXT NOT NULL\n);"));
+    assert!(sql.contains(r#"("id", "column_1", "Name", "name_2") VALUES ($1, $2, $3, $4);"#));
+
+    // A header that isn't an identifier is kept as it is
+    let csv = "Order ID,Unit Price ($)\n1,2.5\n";
+    let info = analyze_for("sql-headers", csv, None, &args).unwrap();
+    let mut buf = Vec::new();
+    info.write_sql(&mut buf).unwrap();
+    let sql = String::from_utf8(buf).unwrap();
+    assert!(sql.contains("\n    \"Order ID\" SMALLINT NOT NULL,\n    \"Unit Price ($)\" DOUBLE PRECISION NOT NULL\n);"));
+    assert!(sql.contains(r#"("Order ID", "Unit Price ($)") VALUES ($1, $2);"#));
+}
+
+#[test]
+fn test_enum_open() {
+    let csv = "id,class\n1,Iris-setosa\n2,Iris-virginica\n3,Iris-setosa\n";
+    let code = generate_for("enum-open", csv, &["--strings", "enum-open"]);
+
+    // Every value seen has a variant, in the order first seen, and only those are in ALL
+    assert!(code.contains("pub const ALL: [Self; 2] = [Self::IrisSetosa, Self::IrisVirginica];"));
+    assert!(code.contains(
+        "    /// A value that wasn't seen when this code was generated\n    Other(Box<str>),\n}"
+    ));
+
+    // Each variant parses from the string as_str gives back
+    assert!(code.contains("Self::IrisSetosa => \"Iris-setosa\","));
+    assert!(code.contains("Self::IrisVirginica => \"Iris-virginica\","));
+    assert!(code.contains("\"Iris-setosa\" => Ok(Self::IrisSetosa),"));
+    assert!(code.contains("\"Iris-virginica\" => Ok(Self::IrisVirginica),"));
+
+    // A value that wasn't seen is held as it is, and never fails to parse
+    assert!(code.contains("type Err = std::convert::Infallible;"));
+    assert!(code.contains("_ => Ok(Self::Other(s.into())),"));
+    assert!(code.contains("Self::Other(s) => s,"));
+
+    // A closed enum has no catch-all, so values that weren't seen fail to parse
+    let closed = generate_for("enum-closed", csv, &["--strings", "enum"]);
+    assert!(closed.contains("pub const ALL: [Self; 2] = [Self::IrisSetosa, Self::IrisVirginica];"));
+    assert!(!closed.contains("Other(Box<str>)"));
+    assert!(!closed.contains("std::convert::Infallible"));
+    assert!(closed.contains("_ => Err(s.to_string()),"));
+}
+
+#[test]
+fn test_generated_literals() {
+    // A value with a line break is escaped in the doc comment of its variant, so the code parses
+    let csv = "id,shape\n1,\"two\nlines\"\n2,round\n";
+    let code = generate_for("literal-enum", csv, &["--strings", "enum"]);
+    assert!(code.contains("/// From the input string 'two\\nlines'"));
+    syn::parse_file(&code).unwrap();
+
+    // Static strings are escaped, and wrapped in Some when the column is optional
+    let csv = "id,quote,note\n1,\"say \"\"hi\"\"\",a\n2,plain,\n";
+    let code = generate_for("literal-static", csv, &["--strings", "static"]);
+    assert!(code.contains(r#"Some("say \"hi\"") => "say \"hi\"","#));
+    assert!(code.contains(r#"Some("a") => Some("a"),"#));
+    syn::parse_file(&code).unwrap();
+}
+
+#[test]
+fn test_generated_names() {
+    // Names that can't be Rust identifiers are an error rather than invalid code
+    let result = try_generate("names-type", "id\n1\n", None, &["--typename", "2020"]);
+    assert!(result
+        .unwrap_err()
+        .to_string()
+        .contains("\"2020\" is not a Rust identifier"));
+    let result = try_generate("names-header", "id,a²\n1,2\n", None, &[]);
+    assert!(result
+        .unwrap_err()
+        .to_string()
+        .contains("is not a Rust identifier"));
+
+    // Columns are named by their fields everywhere, even when that isn't the header
+    let code = generate_for("names-fields", "type,Name\n1,a\n", &[]);
+    assert!(code.contains("pub type_: u8,"));
+    assert!(code.contains("(\"type_\", \"u8\"),\n        (\"name\", \"String\"),"));
+    assert!(code.contains(r#"None => return Some(Err((linenum, "type_").into())),"#));
+    assert!(code.contains(r#"return Some(Err((linenum, "type_", val).into()))"#));
+}
@@ -0,0 +1,732 @@
+use crate::{
+    column::{ColumnInference, IntermediateColumnType},
+    profile::ColumnProfile,
+    sample::{SampleOptions, Sampler},
+    util::Fnv1a,
+};
+use std::{
+    collections::HashSet,
+    fs::File,
+    io::{BufRead, BufReader, IsTerminal, Read, Seek, SeekFrom, Write},
+    path::Path,
+    sync::atomic::{AtomicU64, AtomicUsize, Ordering},
+    time::{Duration, Instant},
+};
+
+/// The nominal size of the chunks that a file is split into for parallel analysis. This is fixed
+/// rather than derived from the number of threads so that results don't depend on the thread count.
+const CHUNK_SIZE: u64 = 32 << 20;
+
+/// How many rows of each column are remembered to detect keys, when they're detected. Keys aren't
+/// detected when more rows than this are analyzed.
+const MAX_KEY_ROWS: usize = 1_000_000;
+
+/// How many columns are considered in pairs when no single column is a key
+const MAX_KEY_PAIR_COLUMNS: usize = 16;
+
+/// Controls what is collected while analyzing input.
+#[derive(Clone, Copy, Debug)]
+pub struct InferenceOptions {
+    /// The number of named columns
+    pub num_columns: usize,
+    /// How many well-formed records to analyze
+    pub num_rows: usize,
+    pub trim_input: bool,
+    /// Whether the distinct values of each column are needed
+    pub collect_values: bool,
+    /// How many distinct values are kept per column before giving up on them
+    pub max_values: usize,
+    pub profile: bool,
+    /// Whether a hash of each value is kept, to detect keys or check that a key is unique
+    pub detect_keys: bool,
+    pub delimiter: u8,
+}
+
+impl InferenceOptions {
+    /// Describes why `record` can't be analyzed, if it doesn't have the expected number of fields.
+    fn field_count_error(&self, record: &csv::StringRecord) -> Option<String> {
+        if record.len() != self.num_columns {
+            Some(format!(
+                "expected {} columns but found {}",
+                self.num_columns,
+                record.len()
+            ))
+        } else {
+            None
+        }
+    }
+}
+
+/// Everything learned about a single column while reading the input.
+#[derive(Clone, Debug)]
+pub struct ColumnStats {
+    pub inference: ColumnInference,
+    /// The distinct values seen, unless there were more than `InferenceOptions::max_values`
+    pub seen_values: HashSet<String>,
+    pub too_many_values: bool,
+    pub profile: Option<ColumnProfile>,
+    /// A hash of the value in each row, for detecting keys, unless there were more than `MAX_KEY_ROWS`
+    pub value_hashes: Option<Vec<u64>>,
+    /// The column's type in the input's own schema, when its values are converted to a type that
+    /// isn't inferred from text, such as a Parquet DATE to ISO 8601 text
+    pub source_type: Option<String>,
+    /// The number of decimal places of a decimal column, whose values are the integer of its digits
+    pub scale: Option<u32>,
+}
+
+impl ColumnStats {
+    fn new(options: &InferenceOptions) -> Self {
+        ColumnStats {
+            inference: ColumnInference::default(),
+            seen_values: HashSet::new(),
+            too_many_values: false,
+            profile: options.profile.then(ColumnProfile::default),
+            value_hashes: options.detect_keys.then(Vec::new),
+            source_type: None,
+            scale: None,
+        }
+    }
+
+    fn observe(&mut self, value: &str, line: u64, options: &InferenceOptions) {
+        self.inference.agg(value, line);
+
+        if let Some(profile) = &mut self.profile {
+            profile.observe(value);
+        }
+
+        if let Some(hashes) = &mut self.value_hashes {
+            if hashes.len() < MAX_KEY_ROWS {
+                let mut hasher = Fnv1a::new();
+                hasher.write(value.as_bytes());
+                hashes.push(hasher.finish());
+            } else {
+                self.value_hashes = None;
+            }
+        }
+
+        // Values are collected for every column, since any column may still turn out to be a string
+        if options.collect_values && !self.too_many_values && !self.seen_values.contains(value) {
+            if self.seen_values.len() < options.max_values {
+                self.seen_values.insert(value.to_string());
+            } else {
+                // Keep memory bounded for high-cardinality columns
+                self.too_many_values = true;
+                self.seen_values = HashSet::new();
+            }
+        }
+    }
+
+    /// Combines the statistics of a later part of the same column into these.
+    pub fn merge(&mut self, other: ColumnStats, options: &InferenceOptions) {
+        self.inference.merge(other.inference);
+
+        if let (Some(profile), Some(other)) = (&mut self.profile, other.profile) {
+            profile.merge(other);
+        }
+
+        self.value_hashes = match (self.value_hashes.take(), other.value_hashes) {
+            (Some(mut hashes), Some(other)) if hashes.len() + other.len() <= MAX_KEY_ROWS => {
+                hashes.extend(other);
+                Some(hashes)
+            }
+            _ => None,
+        };
+
+        if self.source_type.is_none() {
+            self.source_type = other.source_type;
+        }
+        if self.scale.is_none() {
+            self.scale = other.scale;
+        }
+
+        self.too_many_values |= other.too_many_values;
+        if !self.too_many_values {
+            self.seen_values.extend(other.seen_values);
+            if self.seen_values.len() > options.max_values {
+                self.too_many_values = true;
+            }
+        }
+        if self.too_many_values {
+            self.seen_values = HashSet::new();
+        }
+    }
+}
+
+/// The result of analyzing some or all of an input.
+#[derive(Clone, Debug)]
+pub struct InputStats {
+    pub columns: Vec<ColumnStats>,
+    /// The number of well-formed records analyzed
+    pub rows: u64,
+    /// The number of records skipped because they couldn't be parsed or had too few fields
+    pub malformed: u64,
+    /// The line and reason for the first malformed record
+    pub first_malformed: Option<(u64, String)>,
+}
+
+impl InputStats {
+    pub fn new(options: &InferenceOptions) -> Self {
+        InputStats {
+            columns: (0..options.num_columns)
+                .map(|_| ColumnStats::new(options))
+                .collect(),
+            rows: 0,
+            malformed: 0,
+            first_malformed: None,
+        }
+    }
+
+    fn skip(&mut self, line: u64, reason: String) {
+        self.malformed += 1;
+        if self.first_malformed.is_none() {
+            self.first_malformed = Some((line, reason));
+        }
+    }
+
+    /// Analyzes a single record, skipping it if it doesn't have the expected number of fields.
+    pub fn observe(&mut self, record: &csv::StringRecord, options: &InferenceOptions) {
+        let line = record.position().map(|p| p.line()).unwrap_or_default();
+
+        if let Some(reason) = options.field_count_error(record) {
+            self.skip(line, reason);
+            return;
+        }
+
+        self.rows += 1;
+        for (column, value) in self.columns.iter_mut().zip(record.iter()) {
+            let value = if options.trim_input {
+                value.trim()
+            } else {
+                value
+            };
+            column.observe(value, line, options);
+        }
+    }
+
+    /// Combines the statistics of a later part of the same input into these.
+    fn merge(&mut self, other: InputStats, options: &InferenceOptions) {
+        for (column, other) in self.columns.iter_mut().zip(other.columns) {
+            column.merge(other, options);
+        }
+
+        self.rows += other.rows;
+        self.malformed += other.malformed;
+        if self.first_malformed.is_none() {
+            self.first_malformed = other.first_malformed;
+        }
+    }
+
+    /// Adjusts recorded line numbers for a part of the input that didn't start on line 1.
+    fn offset_lines(&mut self, offset: u64) {
+        for column in &mut self.columns {
+            column.inference.offset_lines(offset);
+        }
+        if let Some((line, _)) = &mut self.first_malformed {
+            *line += offset;
+        }
+    }
+
+    /// Prints a warning about any records that were skipped.
+    pub fn report_malformed(&self, path: &Path) {
+        if let Some((line, reason)) = &self.first_malformed {
+            eprintln!(
+                "Skipped {} malformed records in {}; the first was on line {line}: {reason}",
+                self.malformed,
+                path.display()
+            );
+        }
+    }
+}
+
+/// Whether every row has a distinct combination of values in `columns`, as far as can be told
+/// from the hashes of their values.
+pub fn is_unique(columns: &[&ColumnStats]) -> Option<bool> {
+    let hashes = columns
+        .iter()
+        .map(|c| c.value_hashes.as_deref())
+        .collect::<Option<Vec<_>>>()?;
+    let rows = hashes.first()?.len();
+
+    let mut combined = (0..rows)
+        .map(|row| {
+            hashes.iter().fold(0u64, |acc, h| {
+                acc.rotate_left(23) ^ h[row].wrapping_mul(0x9e37_79b9_7f4a_7c15)
+            })
+        })
+        .collect::<Vec<_>>();
+    combined.sort_unstable();
+
+    Some(combined.windows(2).all(|w| w[0] != w[1]))
+}
+
+/// Finds the columns whose values are unique and never empty in the rows analyzed, or if there
+/// are none, the pairs of columns that are. Floating point columns are never keys.
+pub fn detect_keys(columns: &[&ColumnStats]) -> Vec<Vec<usize>> {
+    let eligible = columns
+        .iter()
+        .enumerate()
+        .filter(|(_, c)| {
+            !c.inference.state.is_optional()
+                && matches!(
+                    c.inference.state,
+                    IntermediateColumnType::Bool(_)
+                        | IntermediateColumnType::Integer(..)
+                        | IntermediateColumnType::String(_)
+                )
+        })
+        .map(|(i, _)| i)
+        .collect::<Vec<_>>();
+
+    let singles = eligible
+        .iter()
+        .filter(|&&i| is_unique(&[columns[i]]) == Some(true))
+        .map(|&i| vec![i])
+        .collect::<Vec<_>>();
+    if !singles.is_empty() {
+        return singles;
+    }
+
+    let eligible = &eligible[..eligible.len().min(MAX_KEY_PAIR_COLUMNS)];
+    let mut pairs = Vec::new();
+    for (n, &i) in eligible.iter().enumerate() {
+        for &j in &eligible[n + 1..] {
+            if is_unique(&[columns[i], columns[j]]) == Some(true) {
+                pairs.push(vec![i, j]);
+            }
+        }
+    }
+    pairs
+}
+
+/// Analyzes records from `reader` in a single pass until `InferenceOptions::num_rows`
+/// well-formed records have been seen or the input is exhausted.
+///
+/// Records that can't be parsed are skipped and counted rather than ending the analysis.
+pub fn analyze_reader<R: Read>(
+    reader: &mut csv::Reader<R>,
+    options: &InferenceOptions,
+    mut progress: Option<&mut Progress>,
+) -> Result<InputStats, csv::Error> {
+    let mut stats = InputStats::new(options);
+    let mut record = csv::StringRecord::new();
+
+    while (stats.rows as usize) < options.num_rows {
+        match reader.read_record(&mut record) {
+            Ok(true) => stats.observe(&record, options),
+            Ok(false) => break,
+            // I/O errors can't be skipped past
+            Err(e) if e.is_io_error() => return Err(e),
+            Err(e) => {
+                let line = e.position().map(|p| p.line()).unwrap_or_default();
+                stats.skip(line, e.to_string());
+            }
+        }
+
+        if let Some(progress) = progress.as_mut() {
+            progress.update(reader.position().byte(), stats.rows);
+        }
+    }
+
+    if let Some(progress) = progress {
+        progress.finish();
+    }
+
+    Ok(stats)
+}
+
+/// The result of analyzing one chunk of a file
+struct ChunkResult {
+    stats: InputStats,
+    /// The records sampled from this chunk, when sampling
+    sampler: Option<Sampler>,
+    /// The number of lines in this chunk
+    lines: u64,
+}
+
+/// Analyzes the file at `path` from `start` onward, split into chunks that are analyzed on
+/// `threads` threads and then merged. `start` is the position of the first record after any header.
+///
+/// If `sampling` is given, only a sample of the well-formed rows is analyzed.
+///
+/// Chunks are split between records, so quoted fields may contain newlines. The file is scanned
+/// once for the boundaries before the chunks are parsed.
+pub fn analyze_file(
+    path: &Path,
+    start: &csv::Position,
+    options: &InferenceOptions,
+    threads: usize,
+    sampling: Option<&SampleOptions>,
+) -> Result<InputStats, csv::Error> {
+    let len = std::fs::metadata(path)?.len();
+    let boundaries = chunk_boundaries(path, start.byte(), len, CHUNK_SIZE, options.delimiter)?;
+    let num_chunks = boundaries.len() - 1;
+
+    let next_chunk = AtomicUsize::new(0);
+    let bytes_read = AtomicU64::new(start.byte());
+    let rows_read = AtomicU64::new(0);
+    let mut progress = Progress::new(len);
+
+    let mut results = std::thread::scope(|scope| {
+        let workers = (0..threads.clamp(1, num_chunks.max(1)))
+            .map(|_| {
+                scope.spawn(|| {
+                    let mut results = Vec::new();
+                    loop {
+                        let i = next_chunk.fetch_add(1, Ordering::Relaxed);
+                        if i >= num_chunks {
+                            break results;
+                        }
+
+                        let chunk = analyze_chunk(
+                            path,
+                            boundaries[i],
+                            boundaries[i + 1],
+                            options,
+                            sampling.map(|s| s.sampler(i)),
+                            (&bytes_read, &rows_read),
+                        );
+                        let failed = chunk.is_err();
+                        results.push((i, chunk));
+                        if failed {
+                            break results;
+                        }
+                    }
+                })
+            })
+            .collect::<Vec<_>>();
+
+        while !workers.iter().all(|w| w.is_finished()) {
+            if let Some(progress) = progress.as_mut() {
+                progress.update(
+                    bytes_read.load(Ordering::Relaxed),
+                    rows_read.load(Ordering::Relaxed),
+                );
+            }
+            std::thread::sleep(Duration::from_millis(50));
+        }
+
+        workers
+            .into_iter()
+            .flat_map(|w| w.join().expect("analysis thread panicked"))
+            .collect::<Vec<_>>()
+    });
+
+    if let Some(progress) = progress {
+        progress.finish();
+    }
+
+    // Merge in file order, so widenings and the first malformed record are reported as if the
+    // file had been read from start to end
+    results.sort_by_key(|(i, _)| *i);
+    let mut stats = InputStats::new(options);
+    let mut samplers = Vec::new();
+    let mut line_offset = start.line() - 1;
+
+    for (_, chunk) in results {
+        let mut chunk = chunk?;
+        chunk.stats.offset_lines(line_offset);
+        if let Some(mut sampler) = chunk.sampler {
+            sampler.offset_lines(line_offset);
+            samplers.push(sampler);
+        }
+        line_offset += chunk.lines;
+        stats.merge(chunk.stats, options);
+    }
+
+    if let Some(sampling) = sampling {
+        for record in sampling.merge(samplers) {
+            stats.observe(&record, options);
+        }
+    }
+
+    Ok(stats)
+}
+
+/// Finds the byte offsets at which to split `start..len` of a file into chunks of about
+/// `chunk_size` bytes, each of which starts at the beginning of a record. The first and
+/// last offsets are `start` and `len`.
+///
+/// A newline only ends a record outside of a quoted field, and whether a byte is inside one
+/// depends on every quote before it, so the file is scanned from `start` to find the boundaries.
+/// As in the CSV grammar, a quote only opens a quoted field at the start of a field, and two
+/// quotes inside one are an escaped quote.
+fn chunk_boundaries(
+    path: &Path,
+    start: u64,
+    len: u64,
+    chunk_size: u64,
+    delimiter: u8,
+) -> std::io::Result<Vec<u64>> {
+    /// Where the scan is within a record
+    #[derive(Clone, Copy, PartialEq)]
+    enum State {
+        FieldStart,
+        Unquoted,
+        Quoted,
+        /// Just after a quote inside a quoted field, which either escapes another or closes it
+        QuoteInQuoted,
+    }
+
+    let mut reader = BufReader::with_capacity(1 << 20, File::open(path)?);
+    reader.seek(SeekFrom::Start(start))?;
+    let mut boundaries = vec![start];
+    let (mut pos, mut state) = (start, State::FieldStart);
+
+    loop {
+        let buf = reader.fill_buf()?;
+        if buf.is_empty() {
+            break;
+        }
+
+        for (i, &byte) in buf.iter().enumerate() {
+            state = match (state, byte) {
+                (State::FieldStart, b'"') | (State::QuoteInQuoted, b'"') => State::Quoted,
+                (State::Quoted, b'"') => State::QuoteInQuoted,
+                (State::Quoted, _) => State::Quoted,
+                (_, b'\n') => {
+                    // The chunk ends just after the first newline at or following its nominal end
+                    let offset = pos + i as u64;
+                    if offset >= boundaries.last().unwrap() + chunk_size && offset + 1 < len {
+                        boundaries.push(offset + 1);
+                    }
+                    State::FieldStart
+                }
+                (_, b) if b == delimiter => State::FieldStart,
+                _ => State::Unquoted,
+            };
+        }
+
+        let read = buf.len();
+        reader.consume(read);
+        pos += read as u64;
+    }
+
+    boundaries.push(len);
+    Ok(boundaries)
+}
+
+/// Analyzes the records in bytes `start..end` of a file, either directly or by sampling
+/// them with `sampler`. Record line numbers are relative to the start of the chunk.
+fn analyze_chunk(
+    path: &Path,
+    start: u64,
+    end: u64,
+    options: &InferenceOptions,
+    mut sampler: Option<Sampler>,
+    (bytes_read, rows_read): (&AtomicU64, &AtomicU64),
+) -> Result<ChunkResult, csv::Error> {
+    /// How many records are read between updates of the shared progress counters
+    const REPORT_EVERY: u64 = 4096;
+
+    let mut file = File::open(path)?;
+    file.seek(SeekFrom::Start(start))?;
+    let mut reader = csv::ReaderBuilder::new()
+        .has_headers(false)
+        .delimiter(options.delimiter)
+        .flexible(true)
+        .from_reader(file.take(end - start));
+
+    let mut stats = InputStats::new(options);
+    let mut record = csv::StringRecord::new();
+    let (mut records, mut reported_bytes) = (0, 0);
+
+    loop {
+        match reader.read_record(&mut record) {
+            Ok(true) => match &mut sampler {
+                Some(sampler) => {
+                    let line = record.position().map(|p| p.line()).unwrap_or_default();
+                    match options.field_count_error(&record) {
+                        Some(reason) => stats.skip(line, reason),
+                        None => sampler.push(&record),
+                    }
+                }
+                None => stats.observe(&record, options),
+            },
+            Ok(false) => break,
+            Err(e) if e.is_io_error() => return Err(e),
+            Err(e) => {
+                let line = e.position().map(|p| p.line()).unwrap_or_default();
+                stats.skip(line, e.to_string());
+            }
+        }
+
+        records += 1;
+        if records % REPORT_EVERY == 0 {
+            let bytes = reader.position().byte();
+            bytes_read.fetch_add(bytes - reported_bytes, Ordering::Relaxed);
+            rows_read.fetch_add(REPORT_EVERY, Ordering::Relaxed);
+            reported_bytes = bytes;
+        }
+    }
+    bytes_read.fetch_add(reader.position().byte() - reported_bytes, Ordering::Relaxed);
+    rows_read.fetch_add(records % REPORT_EVERY, Ordering::Relaxed);
+
+    Ok(ChunkResult {
+        stats,
+        sampler,
+        // The reader's line count is one past the last newline in the chunk
+        lines: reader.position().line() - 1,
+    })
+}
+
+/// Shows how much of an input has been analyzed, when stderr is a terminal.
+pub struct Progress {
+    total_bytes: u64,
+    last_update: Instant,
+}
+
+impl Progress {
+    const INTERVAL: Duration = Duration::from_millis(250);
+
+    /// Creates a progress indicator for an input of `total_bytes`, or `None` if it wouldn't be seen.
+    pub fn new(total_bytes: u64) -> Option<Self> {
+        std::io::stderr().is_terminal().then(|| Progress {
+            total_bytes,
+            last_update: Instant::now(),
+        })
+    }
+
+    pub fn update(&mut self, bytes: u64, rows: u64) {
+        if self.last_update.elapsed() < Self::INTERVAL {
+            return;
+        }
+        self.last_update = Instant::now();
+
+        let percent = if self.total_bytes == 0 {
+            100.0
+        } else {
+            100.0 * bytes as f64 / self.total_bytes as f64
+        };
+        eprint!("\rAnalyzed {rows} rows ({percent:.1}%)");
+        let _ = std::io::stderr().flush();
+    }
+
+    pub fn finish(&self) {
+        // Clear the progress line
+        eprint!("\r\x1b[2K");
+        let _ = std::io::stderr().flush();
+    }
+}
+
+#[test]
+fn test_detect_keys() {
+    let options = InferenceOptions {
+        num_columns: 3,
+        num_rows: usize::MAX,
+        trim_input: false,
+        collect_values: false,
+        max_values: 0,
+        profile: false,
+        detect_keys: true,
+        delimiter: b',',
+    };
+
+    let detect = |rows: &[[&str; 3]]| {
+        let mut stats = InputStats::new(&options);
+        for row in rows {
+            stats.observe(&csv::StringRecord::from(row.to_vec()), &options);
+        }
+        detect_keys(&stats.columns.iter().collect::<Vec<_>>())
+    };
+
+    // Student and term together are unique, and grades are floats
+    let grades = [
+        ["ann", "fall", "90.5"],
+        ["ann", "spring", "85"],
+        ["bob", "fall", "70"],
+    ];
+    assert_eq!(detect(&grades), vec![vec![0, 1]]);
+
+    // A unique column is preferred over pairs, but empty values rule it out
+    let ids = [["1", "a", ""], ["2", "a", "x"], ["3", "b", "y"]];
+    assert_eq!(detect(&ids), vec![vec![0]]);
+}
+
+#[test]
+fn test_skip_malformed() {
+    let options = InferenceOptions {
+        num_columns: 2,
+        num_rows: usize::MAX,
+        trim_input: false,
+        collect_values: true,
+        max_values: 10,
+        profile: false,
+        detect_keys: false,
+        delimiter: b',',
+    };
+
+    // A record with too few fields and one that isn't UTF-8 are skipped, and reading carries on
+    let input = b"id,name\n1,a\n2\n3,\xff\n4,d\n";
+    let mut reader = csv::ReaderBuilder::new()
+        .flexible(true)
+        .from_reader(&input[..]);
+    let stats = analyze_reader(&mut reader, &options, None).unwrap();
+    assert_eq!(stats.rows, 2);
+    assert_eq!(stats.malformed, 2);
+    let (line, reason) = stats.first_malformed.unwrap();
+    assert_eq!(line, 3);
+    assert_eq!(reason, "expected 2 columns but found 1");
+    assert_eq!(stats.columns[0].inference.state.to_string(), "integer");
+}
+
+#[test]
+fn test_bounded_values() {
+    let options = InferenceOptions {
+        num_columns: 2,
+        num_rows: usize::MAX,
+        trim_input: false,
+        collect_values: true,
+        max_values: 20,
+        profile: false,
+        detect_keys: false,
+        delimiter: b',',
+    };
+
+    // Reading a whole file keeps no more than `max_values` distinct values of any column
+    let path = std::env::temp_dir().join(format!("inference-{}.csv", std::process::id()));
+    let mut csv = String::from("id,kind\n");
+    for i in 0..10_000 {
+        csv += &format!("{i},{}\n", ["a", "b", "c"][i % 3]);
+    }
+    std::fs::write(&path, csv).unwrap();
+    let start = csv::Reader::from_path(&path).and_then(|mut r| {
+        r.headers()?;
+        Ok(r.position().clone())
+    });
+    let stats = analyze_file(&path, &start.unwrap(), &options, 2, None);
+    std::fs::remove_file(&path).unwrap();
+
+    let stats = stats.unwrap();
+    assert_eq!(stats.rows, 10_000);
+    assert!(stats.columns[0].too_many_values);
+    assert!(stats.columns[0].seen_values.is_empty());
+    assert!(!stats.columns[1].too_many_values);
+    assert_eq!(stats.columns[1].seen_values.len(), 3);
+}
+
+#[test]
+fn test_chunk_boundaries() {
+    let path = std::env::temp_dir().join(format!("chunks-{}.csv", std::process::id()));
+    let csv = "id,text\n1,\"a\nb\"\n2,\"say \"\"hi\"\"\nthere\"\n3,c\n4,\"\nd\n\"\n5,e\n";
+    std::fs::write(&path, csv).unwrap();
+    let boundaries = chunk_boundaries(&path, 8, csv.len() as u64, 1, b',');
+    let starts = |boundaries: std::io::Result<Vec<u64>>, csv: &str| {
+        boundaries
+            .unwrap()
+            .iter()
+            .map(|&b| csv[b as usize..].split([',', ';']).next().unwrap().to_string())
+            .collect::<Vec<_>>()
+    };
+
+    // Every record starts a chunk of its own, and none is split by its quoted newlines
+    assert_eq!(starts(boundaries, csv), ["1", "2", "3", "4", "5", ""]);
+
+    // A quote inside an unquoted field is part of its text, so the newline after it still ends
+    // the record, as does one after a quoted field that a stray quote follows
+    let csv = "id;text\n1;5\" tall\n2;b\n3;\"x\"y\n4;c\n";
+    std::fs::write(&path, csv).unwrap();
+    let boundaries = chunk_boundaries(&path, 8, csv.len() as u64, 1, b';');
+    assert_eq!(starts(boundaries, csv), ["1", "2", "3", "4", ""]);
+
+    std::fs::remove_file(&path).unwrap();
+}
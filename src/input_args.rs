@@ -1,7 +1,10 @@
 use clap::Parser;
-use std::{path::PathBuf, str::FromStr};
+use std::{
+    path::{Path, PathBuf},
+    str::FromStr,
+};
 
-use crate::util;
+use crate::{naming, util};
 
 /// Specifies how errors are handled with input data, either from the underlying `csv` parser or
 /// due to unexpected (ie, unparseable) input values.
@@ -35,6 +38,15 @@ pub enum StringHandling {
     Owned,
     Static,
     Enum(u8),
+    /// An enum with a variant for values that weren't seen when the code was generated
+    EnumOpen,
+}
+
+impl StringHandling {
+    /// Whether a string column's values are an enum
+    pub fn is_enum(self) -> bool {
+        matches!(self, Self::Enum(_) | Self::EnumOpen)
+    }
 }
 
 impl FromStr for StringHandling {
@@ -45,6 +57,7 @@ impl FromStr for StringHandling {
             "owned" => Self::Owned,
             "static" => Self::Static,
             "enum" => Self::Enum(10),
+            "enum-open" => Self::EnumOpen,
             _ => Err(format!("Unknown strings handler: {s}"))?,
         };
 
@@ -52,15 +65,316 @@ impl FromStr for StringHandling {
     }
 }
 
-#[allow(dead_code)]
-#[derive(Debug, Parser)]
+/// Specifies how strictly generated code checks the header row of an input file against the
+/// headers seen when the code was generated.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum HeaderCheck {
+    /// The header row must match exactly, including column order.
+    Exact,
+    /// The same columns must be present, but they may be in any order.
+    SameSet,
+    /// All known columns must be present in any order; additional columns are ignored.
+    Superset,
+}
+
+impl FromStr for HeaderCheck {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let hc = match &s.to_lowercase()[..] {
+            "exact" => Self::Exact,
+            "set" | "same-set" => Self::SameSet,
+            "superset" => Self::Superset,
+            _ => Err(format!("Unknown header check: {s}"))?,
+        };
+
+        Ok(hc)
+    }
+}
+
+/// The output format of a column profile report
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ProfileFormat {
+    Text,
+    Markdown,
+    Json,
+}
+
+impl FromStr for ProfileFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let pf = match &s.to_lowercase()[..] {
+            "text" => Self::Text,
+            "markdown" | "md" => Self::Markdown,
+            "json" => Self::Json,
+            _ => Err(format!("Unknown profile format: {s}"))?,
+        };
+
+        Ok(pf)
+    }
+}
+
+/// What is generated from the inferred columns
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Emit {
+    /// A Rust type and the code to load it
+    Rust,
+    /// A SQL `CREATE TABLE` statement
+    Sql,
+    /// A JSON Schema of the rows
+    JsonSchema,
+    /// A TypeScript interface
+    TypeScript,
+    /// A Python dataclass
+    Python,
+    /// A Python pydantic model
+    Pydantic,
+}
+
+impl Emit {
+    /// The extension of the output file
+    pub fn extension(self) -> &'static str {
+        match self {
+            Emit::Rust => "rs",
+            Emit::Sql => "sql",
+            Emit::JsonSchema => "schema.json",
+            Emit::TypeScript => "ts",
+            Emit::Python | Emit::Pydantic => "py",
+        }
+    }
+}
+
+impl FromStr for Emit {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let emit = match &s.to_lowercase()[..] {
+            "rust" | "rs" => Self::Rust,
+            "sql" => Self::Sql,
+            "json-schema" | "jsonschema" => Self::JsonSchema,
+            "typescript" | "ts" => Self::TypeScript,
+            "python" | "py" | "dataclass" => Self::Python,
+            "pydantic" => Self::Pydantic,
+            _ => Err(format!("Unknown output: {s}"))?,
+        };
+
+        Ok(emit)
+    }
+}
+
+/// The database whose SQL is written by `--emit sql`
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SqlDialect {
+    Postgres,
+    Sqlite,
+    Mysql,
+}
+
+impl FromStr for SqlDialect {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let dialect = match &s.to_lowercase()[..] {
+            "postgres" | "postgresql" | "pg" => Self::Postgres,
+            "sqlite" => Self::Sqlite,
+            "mysql" | "mariadb" => Self::Mysql,
+            _ => Err(format!("Unknown SQL dialect: {s}"))?,
+        };
+
+        Ok(dialect)
+    }
+}
+
+/// How `--emit sql` loads the input into the table it creates
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SqlLoad {
+    /// The dialect's bulk-load command for each CSV input file
+    Copy,
+    /// An `INSERT` statement with a parameter for each column
+    Insert,
+}
+
+impl FromStr for SqlLoad {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let load = match &s.to_lowercase()[..] {
+            "copy" => Self::Copy,
+            "insert" => Self::Insert,
+            _ => Err(format!("Unknown SQL load statement: {s}"))?,
+        };
+
+        Ok(load)
+    }
+}
+
+/// The kind of map generated by `load_map`
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum KeyMap {
+    Hash,
+    BTree,
+}
+
+impl FromStr for KeyMap {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let km = match &s.to_lowercase()[..] {
+            "hash" | "hashmap" => Self::Hash,
+            "btree" | "btreemap" => Self::BTree,
+            _ => Err(format!("Unknown key map: {s}"))?,
+        };
+
+        Ok(km)
+    }
+}
+
+/// Which rows of the input are used to infer column types
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SampleStrategy {
+    /// The first rows
+    Head,
+    /// A uniform random sample of the whole input
+    Reservoir,
+    /// Evenly spaced rows across the whole input
+    Stride,
+    /// The last rows
+    Tail,
+}
+
+impl FromStr for SampleStrategy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let ss = match &s.to_lowercase()[..] {
+            "head" => Self::Head,
+            "reservoir" | "random" => Self::Reservoir,
+            "stride" => Self::Stride,
+            "tail" => Self::Tail,
+            _ => Err(format!("Unknown sample strategy: {s}"))?,
+        };
+
+        Ok(ss)
+    }
+}
+
+/// The format of an input file
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum InputFormat {
+    Csv,
+    Parquet,
+    /// Text with each column at the same position in every line
+    FixedWidth,
+    /// A worksheet of an Excel or OpenDocument workbook
+    Spreadsheet,
+    /// A table of a SQLite database, from `--sqlite` and `--table`
+    Sqlite,
+}
+
+impl InputFormat {
+    /// The format of a file, from its extension. Fixed-width files can't be told apart from CSV
+    /// files this way.
+    pub fn from_path(path: &Path) -> Self {
+        let extension = path
+            .extension()
+            .and_then(|e| e.to_str())
+            .map(|e| e.to_lowercase());
+
+        match extension.as_deref() {
+            Some("parquet" | "parq" | "pq") => Self::Parquet,
+            Some("xlsx" | "xlsm" | "xlsb" | "xls" | "ods") => Self::Spreadsheet,
+            _ => Self::Csv,
+        }
+    }
+}
+
+impl FromStr for InputFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let format = match &s.to_lowercase()[..] {
+            "csv" => Self::Csv,
+            "parquet" => Self::Parquet,
+            "fixed" | "fixed-width" => Self::FixedWidth,
+            "spreadsheet" | "xlsx" | "xls" | "ods" => Self::Spreadsheet,
+            _ => Err(format!("Unknown input format: {s}"))?,
+        };
+
+        Ok(format)
+    }
+}
+
+/// A rectangle of worksheet cells, as zero-based `(row, column)` positions
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct CellRange {
+    pub start: (u32, u32),
+    /// The last cell, or the end of the worksheet if `None`
+    pub end: Option<(u32, u32)>,
+}
+
+impl CellRange {
+    /// Parses a cell reference such as 'B3' into a zero-based `(row, column)`
+    fn parse_cell(s: &str) -> Option<(u32, u32)> {
+        let digits = s.find(|c: char| c.is_ascii_digit())?;
+        let (letters, digits) = s.split_at(digits);
+        if letters.is_empty() || !letters.chars().all(|c| c.is_ascii_alphabetic()) {
+            return None;
+        }
+
+        let column = letters.chars().try_fold(0u32, |col, c| {
+            col.checked_mul(26)?
+                .checked_add(c.to_ascii_uppercase() as u32 - 'A' as u32 + 1)
+        })?;
+        let row = digits.parse::<u32>().ok()?.checked_sub(1)?;
+        Some((row, column - 1))
+    }
+}
+
+impl FromStr for CellRange {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || format!("Invalid cell range: {s}");
+        let (start, end) = match s.split_once(':') {
+            Some((start, end)) => (start, Some(end)),
+            None => (s, None),
+        };
+
+        let start = Self::parse_cell(start.trim()).ok_or_else(invalid)?;
+        let end = match end {
+            Some(end) => Some(Self::parse_cell(end.trim()).ok_or_else(invalid)?),
+            None => None,
+        };
+        if end.is_some_and(|(row, col)| row < start.0 || col < start.1) {
+            return Err(invalid());
+        }
+
+        Ok(CellRange { start, end })
+    }
+}
+
+#[derive(Clone, Debug, Parser)]
 #[command(author, version, bin_name("cargo-generate-type"), about)]
 pub struct Commands {
     #[clap(hide = true)]
     _subcommand: String,
 
-    /// The input file we'll use to understand the type
-    pub input_file: PathBuf,
+    /// The input files we'll use to understand the type. File names may contain '*' and '?' wildcards.
+    #[arg(required_unless_present_any = ["dir", "sqlite", "schema"])]
+    pub input_files: Vec<PathBuf>,
+
+    /// Generates a type for every file in this directory matching `--pattern`, written as a module to `--out`.
+    #[arg(long, requires = "out", conflicts_with_all = ["input_files", "typename", "output_file", "profile"])]
+    pub dir: Option<PathBuf>,
+
+    /// The directory into which batch mode writes one source file per type, along with `mod.rs` and `common.rs`.
+    #[arg(long, requires = "dir")]
+    pub out: Option<PathBuf>,
+
+    /// Which files in `--dir` to generate types for
+    #[arg(long, default_value = "*.csv", requires = "dir")]
+    pub pattern: String,
 
     /// The name of the generated Rust struct for this type. If omitted, it will be generated from the input filename.
     #[arg(short, long)]
@@ -70,10 +384,53 @@ pub struct Commands {
     #[arg(short, long)]
     pub output_file: Option<PathBuf>,
 
-    /// How many rows of input should be used to infer column types for the input file.
+    /// How many rows of input should be used to infer column types for the input file. Use 0 to read all rows.
     #[arg(short, long, aliases=["rows"], default_value="1000")]
     pub num_rows: Option<usize>,
 
+    /// Which rows are used to infer column types. Options are 'head', 'reservoir', 'stride', and 'tail'.
+    /// Defaults to 'reservoir' if a seed or stratify column is given, and 'head' otherwise.
+    #[arg(long)]
+    pub sample: Option<SampleStrategy>,
+
+    /// The random seed for 'reservoir' sampling. The same seed always samples the same rows.
+    #[arg(long)]
+    pub seed: Option<u64>,
+
+    /// Samples each distinct value of this column separately, so that rare values are represented.
+    /// Implies 'reservoir' sampling, so it can't be used with --sample.
+    #[arg(long, conflicts_with = "sample")]
+    pub stratify: Option<String>,
+
+    /// How many threads are used to analyze the whole input file, or a sample of it. Use 0 for one per CPU.
+    #[arg(long, default_value = "0")]
+    pub threads: usize,
+
+    /// The format of the input files. Options are 'csv', 'parquet', 'fixed' (fixed-width text), and 'spreadsheet'. If omitted, it's determined by the extension of the first input file
+    #[arg(long)]
+    pub format: Option<InputFormat>,
+
+    /// The worksheet of a spreadsheet to read. If omitted, the first worksheet is read
+    #[arg(long)]
+    pub sheet: Option<String>,
+
+    /// The cells of the worksheet to read, such as 'B3:F100', or 'B3' for the cells from B3 onward. If omitted, every cell with a value is read
+    #[arg(long)]
+    pub range: Option<CellRange>,
+
+    /// A SQLite database to read `--table` from, instead of input files
+    #[arg(long, requires = "table", conflicts_with_all = ["input_files", "dir", "format"])]
+    pub sqlite: Option<PathBuf>,
+
+    /// The table or view of the `--sqlite` database to generate a type for
+    #[arg(long, requires = "sqlite")]
+    pub table: Option<String>,
+
+    /// A JSON Schema whose properties are the columns, instead of inferring them from the input files. Input
+    /// files are optional, and choose the format the generated code reads
+    #[arg(long, conflicts_with_all = ["sqlite", "dir", "profile"])]
+    pub schema: Option<PathBuf>,
+
     /// The column delimiter
     #[arg(short, long, default_value = ",")]
     pub delimiter: char,
@@ -90,55 +447,196 @@ pub struct Commands {
     #[arg(long, default_value = "false")]
     pub no_header: bool,
 
-    /// How strings will be stored. Options are 'owned', 'static', and 'enum'.
+    /// How strings will be stored. Options are 'owned', 'static', 'enum', and 'enum-open' for an enum with an
+    /// `Other` variant holding values that weren't seen.
     #[arg(short, long, aliases=["strings"], default_value="owned")]
     pub string_handling: StringHandling,
 
-    /// How many individual values are recognized for 'static' or 'enum' string_handling; other values are handled as errors.
+    /// How many individual values are recognized for 'static' or 'enum' string_handling; other values are handled as
+    /// errors, except with 'enum-open'.
     #[arg(short, long, default_value = "20")]
     pub max_strings: Option<usize>,
 
+    /// How enum variants are named for values that don't start with a letter, with 'enum' string_handling. A
+    /// value starting with a digit, such as '1st', is prefixed to become 'Value1st', and one with no letters or
+    /// digits at all, such as '' or '?', is numbered 'Value1', 'Value2', and so on.
+    #[arg(long, default_value = "Value", value_parser = naming::parse_type_name)]
+    pub variant_fallback: String,
+
+    /// Adds a variant to each enum, named 'Unknown' unless another name is given, that holds any value not seen
+    /// when the code was generated. Values not seen are then parsed into it rather than being errors, but the
+    /// enums and the type can no longer be `Copy`. Only used with 'enum' string_handling, and names the
+    /// `Other` variant of 'enum-open'.
+    #[arg(long, num_args = 0..=1, default_missing_value = "Unknown", value_parser = naming::parse_type_name, conflicts_with = "arrow")]
+    pub catch_all: Option<String>,
+
     /// Should values be trimmed
     #[arg(long, default_value = "false")]
     pub trim_input: bool,
+
+    /// How generated code validates the header row when opening a file. Options are 'exact', 'set', and 'superset'.
+    #[arg(long, default_value = "exact")]
+    pub header_check: HeaderCheck,
+
+    /// Generates a `validate` method that checks values against the ranges and strings seen in the input.
+    #[arg(long, default_value = "false")]
+    pub validate: bool,
+
+    /// How far beyond the observed integer range values may fall when validating, as a fraction of the observed range.
+    #[arg(long, default_value = "0")]
+    pub validate_slack: f64,
+
+    /// Also checks that values of owned string columns are among those seen, when there were no more than --max-strings of them.
+    #[arg(long, requires = "validate")]
+    pub validate_values: bool,
+
+    /// Explains why each column's type was inferred, showing the values that caused its type to widen,
+    /// and includes the explanation as comments in the generated struct.
+    #[arg(long, default_value = "false")]
+    pub explain: bool,
+
+    /// Generates a `load_map` function that loads rows into a map keyed by this column, or by several
+    /// comma-separated columns.
+    #[arg(long, conflicts_with = "dir")]
+    pub key: Option<String>,
+
+    /// Suggests the columns, or pairs of columns, whose values are unique and never empty in the rows
+    /// analyzed as keys. This keeps a hash of each value of up to a million rows.
+    #[arg(long, default_value = "false")]
+    pub detect_keys: bool,
+
+    /// The kind of map `load_map` returns. Options are 'hash' and 'btree'.
+    #[arg(long, default_value = "hash")]
+    pub key_map: KeyMap,
+
+    /// Also generates a `{Type}Columns` struct that stores rows by column, with a `load_columns` function.
+    #[arg(long, default_value = "false")]
+    pub columns: bool,
+
+    /// Also generates an Arrow schema and a `load_record_batches` function that reads rows into Arrow record
+    /// batches. The generated code depends on the `arrow` crate.
+    #[arg(long, default_value = "false")]
+    pub arrow: bool,
+
+    /// What to generate. Options are 'rust', 'sql' for a `CREATE TABLE` statement in `--dialect`,
+    /// 'json-schema', 'typescript' for an interface, and 'python' or 'pydantic' for a dataclass or pydantic model.
+    #[arg(long, default_value = "rust", conflicts_with = "dir")]
+    pub emit: Emit,
+
+    /// The SQL dialect for `--emit sql`. Options are 'postgres', 'sqlite', and 'mysql'.
+    #[arg(long, default_value = "postgres")]
+    pub dialect: SqlDialect,
+
+    /// With `--emit sql`, also writes a statement that loads the input into the table. Options are 'copy', for
+    /// the dialect's bulk-load command for each CSV input file, and 'insert', for an `INSERT` statement with a
+    /// parameter for each column.
+    #[arg(long)]
+    pub sql_load: Option<SqlLoad>,
+
+    /// A directory of templates to generate the Rust code with, instead of the built-in template. Its
+    /// `type.rs.jinja` is rendered, and may extend the built-in template as `builtin.rs.jinja`.
+    #[arg(long)]
+    pub template: Option<PathBuf>,
+
+    /// Instead of generating code, reports a profile of each column. Options are 'text' (default), 'markdown', and 'json'.
+    #[arg(long, num_args = 0..=1, default_missing_value = "text")]
+    pub profile: Option<ProfileFormat>,
+
+    /// The file into which the profile report will be written. If omitted, it is written to stdout.
+    #[arg(long, requires = "profile")]
+    pub profile_output: Option<PathBuf>,
 }
 
 impl Commands {
     pub(crate) const DEFAULT_NUM_ROWS: usize = 100;
 
+    /// The name of the variant of each enum that holds values not seen when the code was generated, from
+    /// `--catch-all` or else 'enum-open' string handling
+    pub(crate) fn catch_all(&self) -> Option<&str> {
+        match (&self.catch_all, self.string_handling) {
+            (_, StringHandling::Owned | StringHandling::Static) => None,
+            (Some(name), _) => Some(name),
+            (None, StringHandling::EnumOpen) => Some("Other"),
+            (None, StringHandling::Enum(_)) => None,
+        }
+    }
+
+    /// The sampling strategy to use, taking into account the options that imply one
+    pub(crate) fn sample_strategy(&self) -> SampleStrategy {
+        match self.sample {
+            Some(s) => s,
+            None if self.seed.is_some() || self.stratify.is_some() => SampleStrategy::Reservoir,
+            None => SampleStrategy::Head,
+        }
+    }
+
+    /// The format of the input, from `--sqlite`, `--format`, or else the extension of the first
+    /// input file
+    pub(crate) fn input_format(&self) -> InputFormat {
+        if self.sqlite.is_some() {
+            return InputFormat::Sqlite;
+        }
+
+        self.format
+            .unwrap_or_else(|| InputFormat::from_path(self.input_file()))
+    }
+
+    /// The first input file, or the SQLite database, or else the JSON Schema
+    pub(crate) fn input_file(&self) -> &Path {
+        match (&self.sqlite, self.input_files.first(), &self.schema) {
+            (Some(db), _, _) => db,
+            (None, Some(file), _) => file,
+            (None, None, Some(schema)) => schema,
+            (None, None, None) => panic!("No input file"),
+        }
+    }
+
+    /// What names are generated from: the SQLite table, or else the name of the first input file or the schema
+    fn name_source(&self) -> &str {
+        match &self.table {
+            Some(table) => table,
+            None => {
+                let stem = self
+                    .input_file()
+                    .file_stem()
+                    .expect("File stem")
+                    .to_str()
+                    .expect("File stem from OsStr");
+
+                // As written by `--emit json-schema`, such as 'people.schema.json'
+                if self.input_files.is_empty() {
+                    stem.strip_suffix(".schema").unwrap_or(stem)
+                } else {
+                    stem
+                }
+            }
+        }
+    }
+
     /// Determines the name of the output source file to use
     pub(crate) fn get_output_filename(&self) -> PathBuf {
         if let Some(of) = &self.output_file {
             return of.to_owned();
         }
 
-        match &self.typename {
-            Some(tn) => util::header_to_identifier(tn).to_lowercase() + ".rs",
-            None => {
-                util::header_to_identifier(
-                    self.input_file
-                        .file_stem()
-                        .expect("File stem")
-                        .to_str()
-                        .expect("File stem from OsStr"),
-                )
-                .to_lowercase()
-                    + ".rs"
-            }
-        }
-        .into()
+        format!("{}.{}", self.output_name(), self.emit.extension()).into()
+    }
+
+    /// The name of the output file without its extension, which also names a SQL table
+    pub(crate) fn output_name(&self) -> String {
+        let name = self
+            .typename
+            .as_deref()
+            .unwrap_or_else(|| self.name_source());
+        util::header_to_identifier(name).to_lowercase()
     }
 
     pub(crate) fn get_typename(&self) -> String {
         if let Some(t) = &self.typename {
             t.to_owned()
         } else {
-            // No typename was given, so we'll generate one from the input filename
-            // First, get the base filename
-            let filename = self.input_file.file_stem().expect("File stem");
-            let filename = filename.to_str().expect("File stem from OsStr");
-
-            util::str_to_camel_case_identifier(filename)
+            // No typename was given, so we'll generate one from the input filename or table
+            util::str_to_camel_case_identifier(self.name_source())
             /*
                        let mut result = String::new();
                        let mut cap = true;
@@ -159,3 +657,31 @@ impl Commands {
         }
     }
 }
+
+#[test]
+fn test_cell_range() {
+    let range = "B3:AA100".parse::<CellRange>().unwrap();
+    assert_eq!(range.start, (2, 1));
+    assert_eq!(range.end, Some((99, 26)));
+
+    let range = "c2".parse::<CellRange>().unwrap();
+    assert_eq!(range.start, (1, 2));
+    assert_eq!(range.end, None);
+
+    assert!("B3:A1".parse::<CellRange>().is_err());
+    assert!("A0".parse::<CellRange>().is_err());
+    assert!("3B".parse::<CellRange>().is_err());
+}
+
+#[test]
+fn test_sample_conflicts() {
+    let parse = |args: &[&str]| {
+        let command = ["cargo-generate-type", "generate-type", "events.csv"];
+        Commands::try_parse_from(command.iter().chain(args))
+    };
+
+    assert!(parse(&["--stratify", "region"]).is_ok());
+    // Stratified samples are always reservoir samples, rather than ignoring another strategy
+    assert!(parse(&["--sample", "head", "--stratify", "region"]).is_err());
+    assert!(parse(&["--sample", "stride", "--stratify", "region"]).is_err());
+}
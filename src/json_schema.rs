@@ -0,0 +1,308 @@
+//! Writes a JSON Schema of the row type, for `--emit json-schema`, and reads one back for
+//! `--schema`, so that a type can be generated from a published schema instead of inferred.
+
+use crate::{column::ColumnType, emit::TypeDef, util};
+use std::io::Write;
+#[cfg(feature = "json-schema")]
+use {
+    crate::{column::IntermediateColumnType, err::TypeGenErrors},
+    serde_json::Value,
+    std::path::Path,
+};
+
+/// The dialect of JSON Schema that is written
+const DRAFT: &str = "https://json-schema.org/draft/2020-12/schema";
+
+/// The JSON type of the values of a column
+fn json_type(r#type: &ColumnType) -> &'static str {
+    match r#type {
+        ColumnType::Unit => "null",
+        ColumnType::Bool(_) => "boolean",
+        ColumnType::F64(_) => "number",
+        ColumnType::String(_) => "string",
+        _ => "integer",
+    }
+}
+
+/// Writes a Draft 2020-12 schema of an object with a property for each field, named by its
+/// column's header. Every property is required, and an optional field's property may also be
+/// `null`. Integer fields are bounded by the range of their Rust type.
+pub fn write_schema<W: Write>(buf: &mut W, def: &TypeDef) -> Result<(), std::io::Error> {
+    let columns = &def.fields;
+
+    writeln!(buf, "{{")?;
+    writeln!(buf, "  \"$schema\": {},", util::json_string(DRAFT))?;
+    writeln!(buf, "  \"title\": {},", util::json_string(&def.typename))?;
+    if !def.docs.is_empty() {
+        writeln!(
+            buf,
+            "  \"description\": {},",
+            util::json_string(&def.docs.join("\n"))
+        )?;
+    }
+    writeln!(buf, "  \"type\": \"object\",")?;
+    writeln!(buf, "  \"properties\": {{")?;
+
+    for (i, column) in columns.iter().enumerate() {
+        let json_type = json_type(column.r#type);
+        let optional = column.r#type.is_optional();
+
+        let mut keywords = Vec::new();
+        if optional {
+            keywords.push(format!("\"type\": [\"{json_type}\", \"null\"]"));
+        } else {
+            keywords.push(format!("\"type\": \"{json_type}\""));
+        }
+
+        if let Some((min, max)) = column.r#type.integer_bounds() {
+            keywords.push(format!("\"minimum\": {min}"));
+            keywords.push(format!("\"maximum\": {max}"));
+        }
+
+        // An enum that holds values that weren't seen doesn't limit the values to those it names
+        if !column.values.is_empty() && !column.is_open_enum(def) {
+            let mut values = column
+                .values
+                .iter()
+                .map(|v| util::json_string(v))
+                .collect::<Vec<_>>();
+            if optional {
+                values.push("null".to_string());
+            }
+            keywords.push(format!("\"enum\": [{}]", values.join(", ")));
+        }
+
+        match column.patterns {
+            [] => {}
+            [pattern] => keywords.push(format!("\"pattern\": {}", util::json_string(pattern))),
+            patterns => {
+                let patterns = patterns
+                    .iter()
+                    .map(|p| format!("{{ \"pattern\": {} }}", util::json_string(p)))
+                    .collect::<Vec<_>>();
+                keywords.push(format!("\"allOf\": [{}]", patterns.join(", ")));
+            }
+        }
+
+        if !column.docs.is_empty() {
+            keywords.push(format!(
+                "\"description\": {}",
+                util::json_string(&column.docs.join("\n"))
+            ));
+        }
+
+        writeln!(buf, "    {}: {{", util::json_string(column.header))?;
+        writeln!(buf, "      {}", keywords.join(",\n      "))?;
+        if i + 1 < columns.len() {
+            writeln!(buf, "    }},")?;
+        } else {
+            writeln!(buf, "    }}")?;
+        }
+    }
+
+    let required = columns
+        .iter()
+        .map(|c| util::json_string(c.header))
+        .collect::<Vec<_>>();
+    writeln!(buf, "  }},")?;
+    writeln!(buf, "  \"required\": [{}],", required.join(", "))?;
+    writeln!(buf, "  \"additionalProperties\": false")?;
+    writeln!(buf, "}}")?;
+
+    Ok(())
+}
+
+/// A property of a schema that is read back, which becomes a column
+#[cfg(feature = "json-schema")]
+#[derive(Debug)]
+pub struct Property {
+    pub header: String,
+    pub state: IntermediateColumnType,
+    /// The values of the property's `enum`, other than `null`
+    pub values: Vec<String>,
+    pub docs: Vec<String>,
+    pub patterns: Vec<String>,
+}
+
+/// Reads the description of a schema and the properties of the object it describes, in order.
+#[cfg(feature = "json-schema")]
+pub fn read_schema(path: &Path) -> Result<(Vec<String>, Vec<Property>), TypeGenErrors> {
+    let text = std::fs::read_to_string(path)?;
+    parse_schema(&text).map_err(|e| TypeGenErrors::Other(format!("{}: {e}", path.display()).into()))
+}
+
+#[cfg(feature = "json-schema")]
+fn parse_schema(text: &str) -> Result<(Vec<String>, Vec<Property>), String> {
+    let schema: Value = serde_json::from_str(text).map_err(|e| e.to_string())?;
+    let properties = schema
+        .get("properties")
+        .and_then(Value::as_object)
+        .ok_or("the schema has no \"properties\"")?;
+    let required = schema
+        .get("required")
+        .and_then(Value::as_array)
+        .map(|r| r.iter().filter_map(Value::as_str).collect::<Vec<_>>())
+        .unwrap_or_default();
+
+    let properties = properties
+        .iter()
+        .map(|(header, property)| {
+            // A property that may be absent is an empty value in a CSV file
+            let optional = !required.contains(&header.as_str());
+            parse_property(header, property, optional)
+        })
+        .collect::<Result<_, _>>()?;
+
+    Ok((description(&schema), properties))
+}
+
+/// Reads a property's type from its `type` and `enum` keywords, and its documentation and
+/// patterns. Integers are bounded by `minimum` and `maximum`, or else are 64 bits.
+#[cfg(feature = "json-schema")]
+fn parse_property(header: &str, property: &Value, optional: bool) -> Result<Property, String> {
+    let mut types = match property.get("type") {
+        Some(Value::String(t)) => vec![t.as_str()],
+        Some(Value::Array(types)) => types.iter().filter_map(Value::as_str).collect(),
+        _ => Vec::new(),
+    };
+    let nullable = types.contains(&"null");
+    types.retain(|t| *t != "null");
+
+    let mut values = Vec::new();
+    let mut null_value = false;
+    for value in property
+        .get("enum")
+        .and_then(Value::as_array)
+        .into_iter()
+        .flatten()
+    {
+        match value {
+            Value::Null => null_value = true,
+            Value::String(s) => values.push(s.clone()),
+            value => values.push(value.to_string()),
+        }
+    }
+    if types.is_empty() && !values.is_empty() {
+        types.push("string");
+    }
+
+    let optional = optional || nullable || null_value;
+    let bound = |key: &str, round: fn(f64) -> f64, default: i128| match property.get(key) {
+        Some(v) => v
+            .as_i64()
+            .map(i128::from)
+            .or_else(|| v.as_u64().map(i128::from))
+            .or_else(|| v.as_f64().map(|f| round(f) as i128))
+            .ok_or_else(|| format!("property {header:?} has a {key} that isn't a number")),
+        None => Ok(default),
+    };
+
+    let state = match types[..] {
+        [] if nullable => IntermediateColumnType::Unknown(true),
+        [] => return Err(format!("property {header:?} has no type")),
+        ["boolean"] => IntermediateColumnType::Bool(optional),
+        ["integer"] => {
+            let min = bound("minimum", f64::ceil, i64::MIN.into())?;
+            let max = if min >= 0 {
+                bound("maximum", f64::floor, u64::MAX.into())?
+            } else {
+                bound("maximum", f64::floor, i64::MAX.into())?
+            };
+            IntermediateColumnType::Integer(min, max, optional)
+        }
+        ["number"] => IntermediateColumnType::Float(optional),
+        ["string"] => IntermediateColumnType::String(optional),
+        _ => {
+            return Err(format!(
+                "property {header:?} has type {}, which isn't supported",
+                types.join(" or ")
+            ))
+        }
+    };
+
+    let mut patterns = Vec::new();
+    let all_of = property.get("allOf").and_then(Value::as_array);
+    for schema in std::iter::once(property).chain(all_of.into_iter().flatten()) {
+        if let Some(pattern) = schema.get("pattern").and_then(Value::as_str) {
+            patterns.push(pattern.to_string());
+        }
+    }
+
+    Ok(Property {
+        header: header.to_string(),
+        state,
+        values,
+        docs: description(property),
+        patterns,
+    })
+}
+
+/// The lines of a schema's `description`
+#[cfg(feature = "json-schema")]
+fn description(schema: &Value) -> Vec<String> {
+    schema
+        .get("description")
+        .and_then(Value::as_str)
+        .map(|d| d.lines().map(String::from).collect())
+        .unwrap_or_default()
+}
+
+#[cfg(feature = "json-schema")]
+#[test]
+fn test_read_written_schema() {
+    use crate::{emit::FieldDef, input_args::StringHandling};
+
+    let field = |header, r#type, values, docs, patterns| FieldDef {
+        header,
+        column: header,
+        name: util::header_to_identifier(header),
+        enum_name: String::new(),
+        r#type,
+        values,
+        variants: Vec::new(),
+        docs,
+        patterns,
+    };
+    let (docs, patterns) = (
+        ["The team".to_string()],
+        ["^[a-z]+$".to_string(), "e".to_string()],
+    );
+    let def = TypeDef {
+        typename: "Players".to_string(),
+        docs: &["Line 1".into(), "Line 2".into()],
+        fields: vec![
+            field("id", &ColumnType::U16(false), Vec::new(), &[], &[]),
+            field(
+                "team name",
+                &ColumnType::String(true),
+                vec!["blue", "red"],
+                &docs,
+                &patterns,
+            ),
+            field("score", &ColumnType::I8(true), Vec::new(), &[], &[]),
+        ],
+        key: &[],
+        string_handling: StringHandling::Static,
+        catch_all: false,
+    };
+
+    let mut buf = Vec::new();
+    write_schema(&mut buf, &def).unwrap();
+    let (docs, properties) = parse_schema(std::str::from_utf8(&buf).unwrap()).unwrap();
+
+    assert_eq!(docs, ["Line 1", "Line 2"]);
+    assert_eq!(properties.len(), 3);
+    for (property, column) in properties.iter().zip(&def.fields) {
+        assert_eq!(property.header, column.header);
+        assert_eq!(&property.state.finish(), column.r#type);
+        assert_eq!(property.values, column.values);
+        assert_eq!(property.docs, column.docs);
+        assert_eq!(property.patterns, column.patterns);
+    }
+
+    // Properties that aren't required may be empty
+    let (_, properties) =
+        parse_schema(r#"{ "properties": { "n": { "type": "integer", "minimum": 1 } } }"#).unwrap();
+    assert_eq!(properties[0].state.finish(), ColumnType::U64(true));
+    assert!(parse_schema(r#"{ "properties": { "n": { "type": "object" } } }"#).is_err());
+}
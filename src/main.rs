@@ -1,15 +1,45 @@
 use clap::Parser;
 use input_args::Commands;
 
+mod batch;
 mod column;
+mod emit;
 mod err;
+mod fixed_width;
 mod generate_csv;
+mod inference;
 mod input_args;
+mod json_schema;
+mod naming;
+#[cfg(feature = "parquet")]
+mod parquet_input;
+mod profile;
+mod python;
+mod sample;
+#[cfg(feature = "spreadsheet")]
+mod spreadsheet_input;
+mod sql;
+#[cfg(feature = "sqlite")]
+mod sqlite_input;
+mod syntax;
+mod template;
+mod typescript;
 mod util;
 
 fn main() -> Result<(), err::TypeGenErrors> {
     let args = Commands::parse();
 
+    if args.dir.is_some() {
+        return batch::generate_dir(args);
+    }
+
+    if args.profile.is_some() {
+        // Profiling reports on the input instead of generating code
+        return generate_csv::CsvFileInfo::new(args)
+            .analyze_input()?
+            .write_profile();
+    }
+
     let out_filename = args.get_output_filename();
     if out_filename.exists() && !args.force {
         // file already exists. don't overwrite it
@@ -21,10 +51,18 @@ fn main() -> Result<(), err::TypeGenErrors> {
     let out_file = std::fs::File::create(&out_filename)?;
     let mut buf = std::io::BufWriter::new(out_file);
 
-    generate_csv::CsvFileInfo::new(args)
+    let explain = args.explain;
+    let emitter = args.emit.emitter();
+    let info = generate_csv::CsvFileInfo::new(args)
         .analyze_input()?
-        .load_data_def()
-        .generate(&mut buf)?;
+        .load_data_def();
+
+    if explain {
+        info.write_explanation();
+    }
+    info.write_key_candidates();
+
+    emitter.emit(&info, &mut buf)?;
 
     println!("Generated {out_filename:?}");
 
@@ -0,0 +1,302 @@
+//! Names the fields generated for the columns of an input, and the enum variants of their values.
+//! Headers and values are turned into identifiers that are valid Rust, aren't keywords, and are
+//! distinct from each other, and every column that can't simply be named after its header is
+//! reported.
+
+use crate::util;
+use std::collections::HashSet;
+
+/// Rust's keywords, including those reserved for future use, which can't name a field or type
+const KEYWORDS: &[&str] = &[
+    "Self", "abstract", "as", "async", "await", "become", "box", "break", "const", "continue",
+    "crate", "do", "dyn", "else", "enum", "extern", "false", "final", "fn", "for", "gen", "if",
+    "impl", "in", "let", "loop", "macro", "match", "mod", "move", "mut", "override", "priv", "pub",
+    "ref", "return", "self", "static", "struct", "super", "trait", "true", "try", "type", "typeof",
+    "union", "unsafe", "unsized", "use", "virtual", "where", "while", "yield",
+];
+
+/// An identifier that isn't a keyword, with a trailing underscore if it would be one
+pub fn identifier(name: String) -> String {
+    if KEYWORDS.contains(&name.as_str()) {
+        name + "_"
+    } else {
+        name
+    }
+}
+
+/// The name of the field for a column name
+pub fn field_name(name: &str) -> String {
+    identifier(util::str_to_snake_case_identifier(name))
+}
+
+/// Converts text to camel case, treating underscores as separators like any other character that
+/// can't be in an identifier
+fn camel_case(s: &str) -> String {
+    util::str_to_camel_case_identifier(&s.replace('_', " "))
+}
+
+/// Types in Rust's prelude, which the generated code refers to by name, and the traits it derives
+const PRELUDE: &[&str] = &[
+    "AsMut",
+    "AsRef",
+    "Box",
+    "Clone",
+    "Copy",
+    "Debug",
+    "Default",
+    "DoubleEndedIterator",
+    "Drop",
+    "Eq",
+    "Err",
+    "ExactSizeIterator",
+    "Extend",
+    "Fn",
+    "FnMut",
+    "FnOnce",
+    "From",
+    "FromIterator",
+    "Hash",
+    "Into",
+    "IntoIterator",
+    "Iterator",
+    "None",
+    "Ok",
+    "Option",
+    "Ord",
+    "PartialEq",
+    "PartialOrd",
+    "Result",
+    "Send",
+    "Sized",
+    "Some",
+    "String",
+    "Sync",
+    "ToOwned",
+    "ToString",
+    "TryFrom",
+    "TryInto",
+    "Unpin",
+    "Vec",
+];
+
+/// The suffixes of the names of the types generated alongside a type, such as `{Type}Iterator`
+const GENERATED_SUFFIXES: &[&str] = &[
+    "",
+    "Iterator",
+    "Error",
+    "ValidationError",
+    "Key",
+    "Index",
+    "Columns",
+    "RecordBatches",
+];
+
+/// The name of a type, such as the enum of a string column's values, for a column name. Words
+/// separated by underscores are joined, so that the `_2` of a repeated column name becomes `2`,
+/// and a name that doesn't start with a letter is prefixed with `Column`.
+pub fn type_name(name: &str) -> String {
+    let camel = camel_case(name);
+    match camel.chars().next() {
+        Some(c) if c.is_alphabetic() => identifier(camel),
+        _ => format!("Column{camel}"),
+    }
+}
+
+/// Names the type of each of `columns`, such as the enum of a string column's values, as
+/// `type_name` does. A name that's taken by a type generated for `typename`, by a type in Rust's
+/// prelude, or by an earlier column's type is given a suffix of `2`, `3` and so on, and a warning
+/// is printed.
+pub fn type_names(typename: &str, columns: &[&str]) -> Vec<String> {
+    let mut taken = GENERATED_SUFFIXES
+        .iter()
+        .map(|suffix| format!("{typename}{suffix}"))
+        .chain(PRELUDE.iter().map(|name| name.to_string()))
+        .collect::<HashSet<_>>();
+
+    columns
+        .iter()
+        .map(|column| {
+            let mut name = type_name(column);
+            if taken.contains(&name) {
+                let renamed = (2..)
+                    .map(|n| format!("{name}{n}"))
+                    .find(|renamed| !taken.contains(renamed))
+                    .unwrap();
+                eprintln!(
+                    "Warning: the type for column {column} would be named {name}, which is taken, so it's named {renamed}"
+                );
+                name = renamed;
+            }
+            taken.insert(name.clone());
+            name
+        })
+        .collect()
+}
+
+/// Parses a type or variant name given as an argument, which must be a valid identifier that starts
+/// with a letter.
+pub fn parse_type_name(s: &str) -> Result<String, String> {
+    let starts_with_letter = s.chars().next().is_some_and(char::is_alphabetic);
+    if !starts_with_letter || !s.chars().all(|c| c.is_alphanumeric() || c == '_') {
+        return Err(format!("{s:?} is not a name that starts with a letter"));
+    }
+    if KEYWORDS.contains(&s) {
+        return Err(format!("{s} is a Rust keyword"));
+    }
+    Ok(s.to_string())
+}
+
+/// Names the enum variant for each of `values`, in camel case. A value that doesn't start with a
+/// letter is prefixed with `fallback`, and one with no letters or digits at all is named `fallback`
+/// and a number counting such values from 1. A variant whose name is taken by another value's, or
+/// by the `catch_all` variant, is given a suffix of `2`, `3` and so on.
+///
+/// Numbers and suffixes skip the names that any value is given by its own text, so a value's
+/// variant doesn't depend on the order of the values.
+pub fn variant_names(values: &[&str], fallback: &str, catch_all: Option<&str>) -> Vec<String> {
+    let natural = values
+        .iter()
+        .map(|value| {
+            let camel = camel_case(value);
+            match camel.chars().next() {
+                None => None,
+                Some(c) if !c.is_alphabetic() => Some(format!("{fallback}{camel}")),
+                Some(_) => Some(identifier(camel)),
+            }
+        })
+        .collect::<Vec<_>>();
+    let reserved = natural.iter().flatten().collect::<HashSet<_>>();
+
+    let mut taken = catch_all
+        .into_iter()
+        .map(String::from)
+        .collect::<HashSet<_>>();
+    let mut numbers = (1..)
+        .map(|n| format!("{fallback}{n}"))
+        .filter(|name| !reserved.contains(name));
+
+    natural
+        .iter()
+        .map(|name| {
+            let name = match name {
+                Some(name) => name.clone(),
+                None => numbers.next().unwrap(),
+            };
+
+            let name = if taken.contains(&name) {
+                (2..)
+                    .map(|n| format!("{name}{n}"))
+                    .find(|renamed| !taken.contains(renamed) && !reserved.contains(renamed))
+                    .unwrap()
+            } else {
+                name
+            };
+            taken.insert(name.clone());
+            name
+        })
+        .collect()
+}
+
+/// Names each column after its header, as `header_to_identifier` does. A column whose header has
+/// no characters of an identifier is named `column_N` after its position, and a column whose field
+/// would have the same name as an earlier one's is given a suffix of `_2`, `_3` and so on. A
+/// warning is printed for each column that's renamed, including those whose names are keywords.
+pub fn column_names(headers: &[String]) -> Vec<String> {
+    let mut fields = HashSet::new();
+    let mut names = Vec::with_capacity(headers.len());
+
+    for (i, header) in headers.iter().enumerate() {
+        let identifier = util::header_to_identifier(header);
+        let mut name = if identifier.chars().all(|c| c == '_') {
+            eprintln!("Warning: column {i} has no name, so it's named column_{i}");
+            format!("column_{i}")
+        } else {
+            identifier
+        };
+
+        if !fields.insert(field_name(&name)) {
+            let (renamed, field) = (2..)
+                .map(|n| format!("{name}_{n}"))
+                .map(|renamed| {
+                    let field = field_name(&renamed);
+                    (renamed, field)
+                })
+                .find(|(_, field)| !fields.contains(field))
+                .unwrap();
+            eprintln!(
+                "Warning: column {header:?} has the same name as an earlier column, so it's named {renamed}"
+            );
+            fields.insert(field);
+            name = renamed;
+        }
+
+        let field = field_name(&name);
+        if field != util::str_to_snake_case_identifier(&name) {
+            eprintln!("Warning: {name} is a Rust keyword, so its field is named {field}");
+        }
+        names.push(name);
+    }
+
+    names
+}
+
+#[test]
+fn test_column_names() {
+    let headers = ["id", "type", "()", "Name", "name", "NAME", "", "self"]
+        .map(String::from)
+        .to_vec();
+    let names = column_names(&headers);
+    assert_eq!(
+        names,
+        ["id", "type", "column_2", "Name", "name_2", "NAME_3", "column_6", "self"]
+    );
+    assert_eq!(
+        names.iter().map(|n| field_name(n)).collect::<Vec<_>>(),
+        ["id", "type_", "column_2", "name", "name_2", "name_3", "column_6", "self_"]
+    );
+    assert_eq!(type_name("self"), "Self_");
+    assert_eq!(type_name("name_2"), "Name2");
+    assert_eq!(type_name("column_6"), "Column6");
+    assert_eq!(type_name("_2024"), "Column2024");
+}
+
+#[test]
+fn test_type_names() {
+    let columns = [
+        "iris", "option", "result", "iris_key", "Name", "name_2", "name2",
+    ];
+    assert_eq!(
+        type_names("Iris", &columns),
+        ["Iris2", "Option2", "Result2", "IrisKey2", "Name", "Name2", "Name22"]
+    );
+}
+
+#[test]
+fn test_variant_names() {
+    let values = ["", "1st", "?", "N/A", "Other", "foo bar", "foo-bar", "self"];
+    assert_eq!(
+        variant_names(&values, "Value", Some("Other")),
+        ["Value1", "Value1st", "Value2", "NA", "Other2", "FooBar", "FooBar2", "Self_"]
+    );
+
+    // Values named by their own text keep their names wherever they are
+    let values = ["", "Value1", "?", "Value12", "snake_case"];
+    assert_eq!(
+        variant_names(&values, "Value", None),
+        ["Value2", "Value1", "Value3", "Value12", "SnakeCase"]
+    );
+    let values = ["Value1", "", "Value12", "?", "snake_case"];
+    assert_eq!(
+        variant_names(&values, "Value", None),
+        ["Value1", "Value2", "Value12", "Value3", "SnakeCase"]
+    );
+    let values = ["Other", "Other2", "other"];
+    assert_eq!(
+        variant_names(&values, "Value", Some("Other")),
+        ["Other3", "Other2", "Other4"]
+    );
+
+    assert!(parse_type_name("Other").is_ok());
+    assert!(parse_type_name("1st").is_err());
+    assert!(parse_type_name("Self").is_err());
+}
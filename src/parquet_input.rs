@@ -0,0 +1,453 @@
+//! Reads the column types of a Parquet file from its footer, and its rows as text, for
+//! generating a type from Parquet input.
+
+use crate::{
+    column::{ColumnInference, IntermediateColumnType},
+    err::TypeGenErrors,
+    inference::{InferenceOptions, InputStats},
+};
+use parquet::{
+    basic::{ConvertedType, LogicalType, Repetition, TimeUnit, Type as PhysicalType},
+    file::{
+        metadata::ParquetMetaData,
+        reader::{FileReader, SerializedFileReader},
+        statistics::Statistics,
+    },
+    record::{Field, Row},
+    schema::types::Type,
+};
+use std::{fs::File, path::Path};
+
+/// Reads the names of the columns of a Parquet file.
+pub fn read_headers(path: &Path) -> Result<Vec<String>, TypeGenErrors> {
+    let reader = SerializedFileReader::new(File::open(path)?)?;
+    let headers = reader
+        .metadata()
+        .file_metadata()
+        .schema()
+        .get_fields()
+        .iter()
+        .map(|field| field.name().to_string())
+        .collect();
+
+    Ok(headers)
+}
+
+/// Determines the type of each column of a Parquet file from its schema and the statistics in its
+/// footer, rather than from its values.
+///
+/// Rows are only read when their values are needed, such as for `--strings enum` or `--profile`,
+/// and then only as many as `InferenceOptions::num_rows`.
+pub fn analyze_file(path: &Path, options: &InferenceOptions) -> Result<InputStats, TypeGenErrors> {
+    let reader = SerializedFileReader::new(File::open(path)?)?;
+    let metadata = reader.metadata();
+    let num_rows = metadata.file_metadata().num_rows() as u64;
+
+    let mut stats = InputStats::new(options);
+    if options.collect_values || options.profile {
+        for row in reader.get_row_iter(None)?.take(options.num_rows) {
+            let record = row_to_record(&row?, stats.rows);
+            stats.observe(&record, options);
+        }
+    } else {
+        // Without reading any rows, there are no values to detect keys from
+        for column in &mut stats.columns {
+            column.value_hashes = None;
+        }
+        stats.rows = num_rows;
+    }
+
+    let fields = metadata.file_metadata().schema().get_fields();
+    for (i, (field, column)) in fields.iter().zip(&mut stats.columns).enumerate() {
+        let (state, source_type, scale) = column_type(field, metadata, i, num_rows)?;
+        column.inference = ColumnInference::from_state(state);
+        column.source_type = source_type;
+        column.scale = scale;
+    }
+
+    Ok(stats)
+}
+
+/// Converts a row to a record of the text each value would have in a CSV file, in the same way
+/// as the generated code, so that values are parsed the same way as CSV values. Null values are
+/// empty, and the record's position is its row number.
+fn row_to_record(row: &Row, index: u64) -> csv::StringRecord {
+    let mut record = csv::StringRecord::new();
+    for (_, field) in row.get_column_iter() {
+        record.push_field(&field_text(field));
+    }
+
+    let mut position = csv::Position::new();
+    position.set_record(index).set_line(index + 1);
+    record.set_position(Some(position));
+    record
+}
+
+/// The text of a value, as the generated `parquet_text` function reads it. Dates and timestamps
+/// are ISO 8601 text in UTC, and decimals are the integer of their digits.
+fn field_text(field: &Field) -> String {
+    // A timestamp's date is formatted as a Parquet date, and its time of day from the remainder
+    let timestamp = |t: i64, per_second: i64, digits: usize| {
+        let per_day = per_second * 86_400;
+        let date = Field::Date(t.div_euclid(per_day) as i32);
+        let time = t.rem_euclid(per_day);
+        let seconds = time / per_second;
+        format!(
+            "{date}T{:02}:{:02}:{:02}.{:0digits$}Z",
+            seconds / 3600,
+            seconds / 60 % 60,
+            seconds % 60,
+            time % per_second
+        )
+    };
+
+    match field {
+        Field::Null => String::new(),
+        Field::Str(s) => s.clone(),
+        Field::Bytes(b) => String::from_utf8_lossy(b.data()).into_owned(),
+        Field::TimestampMillis(t) => timestamp(*t, 1_000, 3),
+        Field::TimestampMicros(t) => timestamp(*t, 1_000_000, 6),
+        Field::Decimal(d) => decimal_digits(d.data()).to_string(),
+        field => field.to_string(),
+    }
+}
+
+/// The integer of a decimal's digits, from their big-endian two's complement bytes
+fn decimal_digits(data: &[u8]) -> i128 {
+    let data = &data[data.len().saturating_sub(16)..];
+    let fill = if data.first().is_some_and(|b| b & 0x80 != 0) {
+        0xff
+    } else {
+        0
+    };
+    let mut bytes = [fill; 16];
+    bytes[16 - data.len()..].copy_from_slice(data);
+    i128::from_be_bytes(bytes)
+}
+
+/// Determines the type of column `i` from its schema and the statistics of every row group.
+/// Dates and timestamps are read as ISO 8601 text, times as numbers, and decimals as the integer of
+/// their digits along with their scale, which is described by the returned source type.
+fn column_type(
+    field: &Type,
+    metadata: &ParquetMetaData,
+    i: usize,
+    num_rows: u64,
+) -> Result<(IntermediateColumnType, Option<String>, Option<u32>), TypeGenErrors> {
+    let info = field.get_basic_info();
+    if !field.is_primitive() || info.repetition() == Repetition::REPEATED {
+        return Err(TypeGenErrors::Other(
+            format!(
+                "Column {} is nested or repeated, which isn't supported",
+                field.name()
+            )
+            .into(),
+        ));
+    }
+
+    let statistics = metadata
+        .row_groups()
+        .iter()
+        .map(|rg| rg.column(i).statistics())
+        .collect::<Vec<_>>();
+    let null_count = statistics
+        .iter()
+        .map(|s| s.and_then(|s| s.null_count_opt()))
+        .sum::<Option<u64>>();
+
+    if num_rows > 0 && null_count == Some(num_rows) {
+        // Every value is null
+        return Ok((IntermediateColumnType::Unknown(true), None, None));
+    }
+
+    let since_midnight = |unit: &TimeUnit| match unit {
+        TimeUnit::MILLIS(_) => "milliseconds",
+        TimeUnit::MICROS(_) => "microseconds",
+        TimeUnit::NANOS(_) => "nanoseconds",
+    };
+    let unsupported = |what: &str| {
+        Err(TypeGenErrors::Other(
+            format!("Column {} is {what}, which isn't supported", field.name()).into(),
+        ))
+    };
+    let physical = field.get_physical_type();
+    let (state, source_type, scale) = match (info.logical_type(), info.converted_type(), physical) {
+        (Some(LogicalType::Decimal { scale, precision }), _, _) => {
+            decimal_type(field.name(), &statistics, physical, precision, scale)?
+        }
+        (_, ConvertedType::DECIMAL, _) => decimal_type(
+            field.name(),
+            &statistics,
+            physical,
+            field.get_precision(),
+            field.get_scale(),
+        )?,
+        (Some(LogicalType::Date), _, _) | (_, ConvertedType::DATE, _) => (
+            IntermediateColumnType::String(false),
+            Some("a Parquet DATE, as ISO 8601 text".to_string()),
+            None,
+        ),
+        // Rows have nanosecond timestamps as plain integers, so their text can't be told apart
+        // from an integer column's
+        (
+            Some(LogicalType::Timestamp {
+                unit: TimeUnit::NANOS(_),
+                ..
+            }),
+            _,
+            _,
+        ) => return unsupported("a TIMESTAMP in nanoseconds"),
+        (Some(LogicalType::Timestamp { .. }), _, _)
+        | (_, ConvertedType::TIMESTAMP_MILLIS | ConvertedType::TIMESTAMP_MICROS, _)
+        | (_, _, PhysicalType::INT96) => (
+            IntermediateColumnType::String(false),
+            Some("a Parquet TIMESTAMP, as ISO 8601 text in UTC".to_string()),
+            None,
+        ),
+        (Some(LogicalType::Time { unit, .. }), _, _) => (
+            integer_type(&statistics, 64, true),
+            Some(format!(
+                "a Parquet TIME, as the number of {} since midnight",
+                since_midnight(&unit)
+            )),
+            None,
+        ),
+        (
+            Some(LogicalType::Integer {
+                bit_width,
+                is_signed,
+            }),
+            _,
+            _,
+        ) => (
+            integer_type(&statistics, bit_width as u32, is_signed),
+            None,
+            None,
+        ),
+        (_, ConvertedType::UINT_8, _) => (integer_type(&statistics, 8, false), None, None),
+        (_, ConvertedType::UINT_16, _) => (integer_type(&statistics, 16, false), None, None),
+        (_, ConvertedType::UINT_32, _) => (integer_type(&statistics, 32, false), None, None),
+        (_, ConvertedType::UINT_64, _) => (integer_type(&statistics, 64, false), None, None),
+        (_, _, PhysicalType::INT32) => (integer_type(&statistics, 32, true), None, None),
+        (_, _, PhysicalType::INT64) => (integer_type(&statistics, 64, true), None, None),
+        (_, _, PhysicalType::BOOLEAN) => (IntermediateColumnType::Bool(false), None, None),
+        (_, _, PhysicalType::FLOAT | PhysicalType::DOUBLE) => {
+            (IntermediateColumnType::Float(false), None, None)
+        }
+        (Some(LogicalType::Float16), _, _) => (IntermediateColumnType::Float(false), None, None),
+        (_, _, PhysicalType::BYTE_ARRAY | PhysicalType::FIXED_LEN_BYTE_ARRAY) => {
+            (IntermediateColumnType::String(false), None, None)
+        }
+    };
+
+    // A column that may be null but has no nulls in this file is still required, as it would be
+    // for a CSV file without any empty values
+    let optional = info.repetition() == Repetition::OPTIONAL && null_count != Some(0);
+    let state = if optional {
+        state.set_optional()
+    } else {
+        state
+    };
+
+    Ok((state, source_type, scale))
+}
+
+/// The type of a decimal column, read as the integer of its digits, and its scale. Decimals with
+/// more digits than an `i64` holds are rejected rather than rounded.
+fn decimal_type(
+    name: &str,
+    statistics: &[Option<&Statistics>],
+    physical: PhysicalType,
+    precision: i32,
+    scale: i32,
+) -> Result<(IntermediateColumnType, Option<String>, Option<u32>), TypeGenErrors> {
+    if precision > 18 {
+        return Err(TypeGenErrors::Other(
+            format!(
+                "Column {name} is a DECIMAL({precision}, {scale}), which has more digits than an i64 holds"
+            )
+            .into(),
+        ));
+    }
+
+    // Decimals stored as integers have statistics of their digits
+    let state = match physical {
+        PhysicalType::INT32 => integer_type(statistics, 32, true),
+        PhysicalType::INT64 => integer_type(statistics, 64, true),
+        _ => {
+            let max = 10i128.pow(precision as u32) - 1;
+            IntermediateColumnType::Integer(-max, max, false)
+        }
+    };
+    let source_type =
+        format!("a Parquet DECIMAL({precision}, {scale}), as the integer of its digits");
+    Ok((state, Some(source_type), Some(scale as u32)))
+}
+
+/// The integer type of a column with the given width and signedness, narrowed to the range of its
+/// values when every row group has statistics.
+fn integer_type(
+    statistics: &[Option<&Statistics>],
+    bits: u32,
+    signed: bool,
+) -> IntermediateColumnType {
+    let (type_min, type_max) = if signed {
+        (-(1i128 << (bits - 1)), (1i128 << (bits - 1)) - 1)
+    } else {
+        (0, (1i128 << bits) - 1)
+    };
+
+    // Unsigned values are stored in signed physical types, so they're reinterpreted
+    let range = |s: &Statistics| -> Option<(i128, i128)> {
+        match s {
+            Statistics::Int32(v) if signed => Some((*v.min_opt()? as i128, *v.max_opt()? as i128)),
+            Statistics::Int32(v) => {
+                Some((*v.min_opt()? as u32 as i128, *v.max_opt()? as u32 as i128))
+            }
+            Statistics::Int64(v) if signed => Some((*v.min_opt()? as i128, *v.max_opt()? as i128)),
+            Statistics::Int64(v) => {
+                Some((*v.min_opt()? as u64 as i128, *v.max_opt()? as u64 as i128))
+            }
+            _ => None,
+        }
+    };
+
+    let ranges = statistics
+        .iter()
+        .map(|s| s.and_then(range))
+        .collect::<Option<Vec<_>>>();
+    match ranges {
+        Some(ranges) if !ranges.is_empty() => {
+            let min = ranges.iter().map(|r| r.0).min().unwrap();
+            let max = ranges.iter().map(|r| r.1).max().unwrap();
+            IntermediateColumnType::Integer(min, max, false)
+        }
+        _ => IntermediateColumnType::Integer(type_min, type_max, false),
+    }
+}
+
+#[test]
+fn test_integer_type() {
+    let a = Statistics::int32(Some(-5), Some(10), None, Some(0), false);
+    let b = Statistics::int32(Some(3), Some(300), None, Some(0), false);
+    assert_eq!(
+        integer_type(&[Some(&a), Some(&b)], 32, true),
+        IntermediateColumnType::Integer(-5, 300, false)
+    );
+
+    // Unsigned values past the signed range are stored as negative numbers
+    let c = Statistics::int32(Some(1), Some(-1), None, Some(0), false);
+    assert_eq!(
+        integer_type(&[Some(&c)], 32, false),
+        IntermediateColumnType::Integer(1, u32::MAX as i128, false)
+    );
+
+    // Without statistics for every row group, the whole width is used
+    assert_eq!(
+        integer_type(&[Some(&a), None], 16, true),
+        IntermediateColumnType::Integer(i16::MIN as i128, i16::MAX as i128, false)
+    );
+}
+
+#[test]
+fn test_converted_types() {
+    use parquet::{
+        column::writer::ColumnWriter, file::writer::SerializedFileWriter,
+        schema::parser::parse_message_type,
+    };
+    use std::sync::Arc;
+
+    // Rows are only written to files of INT32 and INT64 columns
+    let write = |name: &str, schema: &str, values: &[[i64; 2]]| {
+        let path = std::env::temp_dir().join(format!(
+            "generate-type-{}-{name}.parquet",
+            std::process::id()
+        ));
+        let schema = Arc::new(parse_message_type(schema).unwrap());
+        let mut writer =
+            SerializedFileWriter::new(File::create(&path).unwrap(), schema, Default::default())
+                .unwrap();
+        if !values.is_empty() {
+            let mut row_group = writer.next_row_group().unwrap();
+            let mut values = values.iter();
+            while let Some(mut column) = row_group.next_column().unwrap() {
+                let values = values.next().unwrap();
+                match column.untyped() {
+                    ColumnWriter::Int32ColumnWriter(w) => {
+                        w.write_batch(&values.map(|v| v as i32), None, None)
+                    }
+                    ColumnWriter::Int64ColumnWriter(w) => w.write_batch(values, None, None),
+                    _ => unreachable!(),
+                }
+                .unwrap();
+                column.close().unwrap();
+            }
+            row_group.close().unwrap();
+        }
+        writer.close().unwrap();
+        path
+    };
+
+    let path = write(
+        "converted",
+        "message row {
+            required int32 day (DATE);
+            required int64 at (TIMESTAMP(MILLIS, true));
+            required int64 amount (DECIMAL(10, 2));
+        }",
+        &[[19723, -1], [1704067201500, -1], [-1234, 5]],
+    );
+    let reader = SerializedFileReader::new(File::open(&path).unwrap()).unwrap();
+    std::fs::remove_file(&path).unwrap();
+    let metadata = reader.metadata();
+    let fields = metadata.file_metadata().schema().get_fields();
+    let types = fields
+        .iter()
+        .enumerate()
+        .map(|(i, field)| column_type(field, metadata, i, 2).unwrap())
+        .collect::<Vec<_>>();
+
+    // Dates and timestamps are text, and decimals keep their digits and scale
+    assert_eq!(types[0].0, IntermediateColumnType::String(false));
+    assert_eq!(types[1].0, IntermediateColumnType::String(false));
+    assert_eq!(
+        types[2],
+        (
+            IntermediateColumnType::Integer(-1234, 5, false),
+            Some("a Parquet DECIMAL(10, 2), as the integer of its digits".to_string()),
+            Some(2)
+        )
+    );
+
+    let rows = reader
+        .get_row_iter(None)
+        .unwrap()
+        .enumerate()
+        .map(|(i, row)| row_to_record(&row.unwrap(), i as u64))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        rows[0],
+        vec!["2024-01-01", "2024-01-01T00:00:01.500Z", "-1234"]
+    );
+    assert_eq!(rows[1], vec!["1969-12-31", "1969-12-31T23:59:59.999Z", "5"]);
+
+    // Values that can't be converted without losing precision are rejected
+    let path = write(
+        "rejected",
+        "message row {
+            required int32 day (DATE);
+            required int64 at (TIMESTAMP(NANOS, true));
+            required fixed_len_byte_array(16) amount (DECIMAL(30, 2));
+        }",
+        &[],
+    );
+    let reader = SerializedFileReader::new(File::open(&path).unwrap());
+    std::fs::remove_file(&path).unwrap();
+    let reader = reader.unwrap();
+    let metadata = reader.metadata();
+    let fields = metadata.file_metadata().schema().get_fields();
+    assert!(column_type(&fields[0], metadata, 0, 0).is_ok());
+    for (i, field) in fields.iter().enumerate().skip(1) {
+        assert!(column_type(field, metadata, i, 0).is_err());
+    }
+}
@@ -0,0 +1,580 @@
+use crate::{column::ColumnInference, input_args::ProfileFormat, util};
+use std::{collections::HashMap, io::Write};
+
+/// How many distinct values are counted exactly before falling back to a HyperLogLog estimate
+const MAX_EXACT_DISTINCT: usize = 10_000;
+
+/// How many of the most common values are reported
+const TOP_K: usize = 5;
+
+/// Estimates the number of distinct values in a column in constant memory.
+///
+/// See Flajolet et al., "HyperLogLog: the analysis of a near-optimal cardinality estimation algorithm".
+#[derive(Clone, Debug)]
+struct HyperLogLog {
+    registers: Vec<u8>,
+}
+
+impl HyperLogLog {
+    /// The number of bits of the hash used to select a register
+    const P: u32 = 12;
+
+    fn new() -> Self {
+        HyperLogLog {
+            registers: vec![0; 1 << Self::P],
+        }
+    }
+
+    fn insert(&mut self, value: &str) {
+        let mut hasher = util::Fnv1a::new();
+        hasher.write(value.as_bytes());
+
+        // FNV's high bits are poorly distributed for short inputs, so mix them (splitmix64's finalizer)
+        let mut h = hasher.finish();
+        h = (h ^ (h >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        h = (h ^ (h >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        h ^= h >> 31;
+
+        let index = (h >> (64 - Self::P)) as usize;
+        let rank = ((h << Self::P) | (1 << (Self::P - 1))).leading_zeros() as u8 + 1;
+        self.registers[index] = self.registers[index].max(rank);
+    }
+
+    fn merge(&mut self, other: &HyperLogLog) {
+        for (r, o) in self.registers.iter_mut().zip(&other.registers) {
+            *r = (*r).max(*o);
+        }
+    }
+
+    fn estimate(&self) -> u64 {
+        let m = self.registers.len() as f64;
+        let alpha = 0.7213 / (1.0 + 1.079 / m);
+        let sum: f64 = self.registers.iter().map(|r| 2f64.powi(-(*r as i32))).sum();
+        let estimate = alpha * m * m / sum;
+
+        let zeros = self.registers.iter().filter(|r| **r == 0).count();
+        if estimate <= 2.5 * m && zeros > 0 {
+            // Linear counting is more accurate for small cardinalities
+            (m * (m / zeros as f64).ln()).round() as u64
+        } else {
+            estimate.round() as u64
+        }
+    }
+}
+
+/// Statistics about the values seen in a single column.
+#[derive(Clone, Debug)]
+pub struct ColumnProfile {
+    count: u64,
+    nulls: u64,
+    /// Exact counts of each value, until there are too many to track
+    value_counts: HashMap<String, u64>,
+    /// Set once `value_counts` is full; new values are only counted by `hll`
+    counts_truncated: bool,
+    hll: HyperLogLog,
+    numeric_count: u64,
+    min: f64,
+    max: f64,
+    sum: f64,
+    min_len: usize,
+    max_len: usize,
+    total_len: u64,
+    /// Counts of string lengths in power-of-two buckets: 0, 1, 2-3, 4-7, ...
+    length_histogram: Vec<u64>,
+}
+
+impl Default for ColumnProfile {
+    fn default() -> Self {
+        ColumnProfile {
+            count: 0,
+            nulls: 0,
+            value_counts: HashMap::new(),
+            counts_truncated: false,
+            hll: HyperLogLog::new(),
+            numeric_count: 0,
+            min: f64::INFINITY,
+            max: f64::NEG_INFINITY,
+            sum: 0.0,
+            min_len: usize::MAX,
+            max_len: 0,
+            total_len: 0,
+            length_histogram: Vec::new(),
+        }
+    }
+}
+
+impl ColumnProfile {
+    /// Records a value from the column.
+    pub fn observe(&mut self, value: &str) {
+        self.count += 1;
+
+        if value.is_empty() {
+            self.nulls += 1;
+            return;
+        }
+
+        if let Some(count) = self.value_counts.get_mut(value) {
+            *count += 1;
+        } else if self.value_counts.len() < MAX_EXACT_DISTINCT {
+            self.value_counts.insert(value.to_string(), 1);
+        } else {
+            self.counts_truncated = true;
+        }
+        self.hll.insert(value);
+
+        if let Ok(v) = value.parse::<f64>() {
+            self.numeric_count += 1;
+            self.min = self.min.min(v);
+            self.max = self.max.max(v);
+            self.sum += v;
+        }
+
+        let len = value.chars().count();
+        self.min_len = self.min_len.min(len);
+        self.max_len = self.max_len.max(len);
+        self.total_len += len as u64;
+
+        let bucket = (usize::BITS - len.leading_zeros()) as usize;
+        if self.length_histogram.len() <= bucket {
+            self.length_histogram.resize(bucket + 1, 0);
+        }
+        self.length_histogram[bucket] += 1;
+    }
+
+    /// Combines the statistics of another part of the same column into this one.
+    pub fn merge(&mut self, other: ColumnProfile) {
+        self.count += other.count;
+        self.nulls += other.nulls;
+
+        for (value, count) in other.value_counts {
+            if let Some(c) = self.value_counts.get_mut(&value) {
+                *c += count;
+            } else if self.value_counts.len() < MAX_EXACT_DISTINCT {
+                self.value_counts.insert(value, count);
+            } else {
+                self.counts_truncated = true;
+            }
+        }
+        self.counts_truncated |= other.counts_truncated;
+        self.hll.merge(&other.hll);
+
+        self.numeric_count += other.numeric_count;
+        self.min = self.min.min(other.min);
+        self.max = self.max.max(other.max);
+        self.sum += other.sum;
+
+        self.min_len = self.min_len.min(other.min_len);
+        self.max_len = self.max_len.max(other.max_len);
+        self.total_len += other.total_len;
+
+        if self.length_histogram.len() < other.length_histogram.len() {
+            self.length_histogram
+                .resize(other.length_histogram.len(), 0);
+        }
+        for (c, o) in self.length_histogram.iter_mut().zip(other.length_histogram) {
+            *c += o;
+        }
+    }
+
+    /// The number of distinct non-empty values, and whether this is an estimate
+    pub fn distinct(&self) -> (u64, bool) {
+        if self.counts_truncated {
+            (self.hll.estimate(), true)
+        } else {
+            (self.value_counts.len() as u64, false)
+        }
+    }
+
+    /// The most common non-empty values, most common first, and whether their counts are
+    /// estimates, since values aren't counted once there are too many distinct ones
+    pub fn top_values(&self) -> (Vec<(&str, u64)>, bool) {
+        let mut values = self
+            .value_counts
+            .iter()
+            .map(|(v, c)| (v.as_str(), *c))
+            .collect::<Vec<_>>();
+        values.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(b.0)));
+        values.truncate(TOP_K);
+        (values, self.counts_truncated)
+    }
+
+    /// The `(min, max, mean)` of values that parse as numbers
+    pub fn numeric_range(&self) -> Option<(f64, f64, f64)> {
+        if self.numeric_count == 0 {
+            return None;
+        }
+
+        Some((self.min, self.max, self.sum / self.numeric_count as f64))
+    }
+
+    /// The `(min, max, mean)` length in characters of non-empty values
+    pub fn length_range(&self) -> Option<(usize, usize, f64)> {
+        let non_null = self.count - self.nulls;
+        if non_null == 0 {
+            return None;
+        }
+
+        Some((
+            self.min_len,
+            self.max_len,
+            self.total_len as f64 / non_null as f64,
+        ))
+    }
+
+    /// The length histogram as `(label, count)` pairs, omitting empty buckets
+    pub fn length_histogram(&self) -> Vec<(String, u64)> {
+        self.length_histogram
+            .iter()
+            .enumerate()
+            .filter(|(_, count)| **count > 0)
+            .map(|(bucket, count)| {
+                let label = match bucket {
+                    0 => "0".to_string(),
+                    1 => "1".to_string(),
+                    b => format!("{}-{}", 1usize << (b - 1), (1usize << b) - 1),
+                };
+                (label, *count)
+            })
+            .collect()
+    }
+}
+
+/// Writes a profile report of `columns`, given as `(header, inference, profile)`, in the specified format.
+pub fn write_profile<W: Write>(
+    buf: &mut W,
+    format: ProfileFormat,
+    columns: &[(&str, &ColumnInference, &ColumnProfile)],
+) -> Result<(), std::io::Error> {
+    match format {
+        ProfileFormat::Text => write_text(buf, columns),
+        ProfileFormat::Markdown => write_markdown(buf, columns),
+        ProfileFormat::Json => write_json(buf, columns),
+    }
+}
+
+fn write_text<W: Write>(
+    buf: &mut W,
+    columns: &[(&str, &ColumnInference, &ColumnProfile)],
+) -> Result<(), std::io::Error> {
+    for (header, inference, profile) in columns {
+        writeln!(buf, "{header}")?;
+        writeln!(
+            buf,
+            "    type:     {} ({})",
+            inference.state,
+            inference.reason()
+        )?;
+        writeln!(
+            buf,
+            "    values:   {} ({} empty)",
+            profile.count, profile.nulls
+        )?;
+
+        let (distinct, estimated) = profile.distinct();
+        let approx = if estimated { "~" } else { "" };
+        writeln!(buf, "    distinct: {approx}{distinct}")?;
+
+        if let Some((min, max, mean)) = profile.numeric_range() {
+            writeln!(buf, "    numeric:  min={min} max={max} mean={mean:.4}")?;
+        }
+
+        if let Some((min, max, mean)) = profile.length_range() {
+            writeln!(buf, "    length:   min={min} max={max} mean={mean:.2}")?;
+            let histogram = profile
+                .length_histogram()
+                .iter()
+                .map(|(label, count)| format!("{label}: {count}"))
+                .collect::<Vec<_>>()
+                .join(", ");
+            writeln!(buf, "    lengths:  {histogram}")?;
+        }
+
+        let (top, estimated) = profile.top_values();
+        if !top.is_empty() {
+            let approx = if estimated { "~" } else { "" };
+            let top = top
+                .iter()
+                .map(|(v, c)| format!("{v:?} ({approx}{c})"))
+                .collect::<Vec<_>>()
+                .join(", ");
+            writeln!(buf, "    top:      {top}")?;
+        }
+
+        for w in inference.widenings() {
+            writeln!(buf, "    widened:  {w}")?;
+        }
+
+        writeln!(buf)?;
+    }
+
+    Ok(())
+}
+
+fn write_markdown<W: Write>(
+    buf: &mut W,
+    columns: &[(&str, &ColumnInference, &ColumnProfile)],
+) -> Result<(), std::io::Error> {
+    // Values are inside inline code spans, so only backticks need special treatment, and line
+    // breaks, which would end a heading, list item or table row. In table cells, a pipe ends the
+    // cell even inside a code span unless it's escaped.
+    let code = |s: &str| {
+        let s = s.replace('`', "'").replace("\r\n", " ");
+        format!("`{}`", s.replace(['\r', '\n'], " "))
+    };
+    let cell = |s: &str| code(s).replace('|', r"\|");
+
+    writeln!(
+        buf,
+        "| Column | Type | Values | Empty | Distinct | Min | Max | Mean | Length (min/max/mean) |"
+    )?;
+    writeln!(buf, "|---|---|---:|---:|---:|---:|---:|---:|---|")?;
+    for (header, inference, profile) in columns {
+        let (distinct, estimated) = profile.distinct();
+        let approx = if estimated { "~" } else { "" };
+        let (min, max, mean) = match profile.numeric_range() {
+            Some((min, max, mean)) => (min.to_string(), max.to_string(), format!("{mean:.4}")),
+            None => Default::default(),
+        };
+        let lengths = match profile.length_range() {
+            Some((min, max, mean)) => format!("{min}/{max}/{mean:.2}"),
+            None => String::new(),
+        };
+
+        writeln!(
+            buf,
+            "| {} | {} | {} | {} | {approx}{distinct} | {min} | {max} | {mean} | {lengths} |",
+            cell(header),
+            inference.state,
+            profile.count,
+            profile.nulls,
+        )?;
+    }
+    writeln!(buf)?;
+
+    for (header, inference, profile) in columns {
+        writeln!(buf, "## {}", code(header))?;
+        writeln!(buf)?;
+        writeln!(
+            buf,
+            "Inferred as {} because {}.",
+            inference.state,
+            inference.reason()
+        )?;
+        writeln!(buf)?;
+
+        let (top, estimated) = profile.top_values();
+        if !top.is_empty() {
+            let approx = if estimated { "~" } else { "" };
+            writeln!(buf, "Most common values:")?;
+            writeln!(buf)?;
+            for (v, c) in top {
+                writeln!(buf, "* {} ({approx}{c})", code(v))?;
+            }
+            writeln!(buf)?;
+        }
+
+        let histogram = profile.length_histogram();
+        if !histogram.is_empty() {
+            writeln!(buf, "| Length | Count |")?;
+            writeln!(buf, "|---|---:|")?;
+            for (label, count) in histogram {
+                writeln!(buf, "| {label} | {count} |")?;
+            }
+            writeln!(buf)?;
+        }
+
+        if !inference.widenings().is_empty() {
+            writeln!(buf, "Type widened by:")?;
+            writeln!(buf)?;
+            for w in inference.widenings() {
+                writeln!(
+                    buf,
+                    "* line {}: {} ({} to {})",
+                    w.line,
+                    code(&w.value),
+                    w.from,
+                    w.to
+                )?;
+            }
+            writeln!(buf)?;
+        }
+    }
+
+    Ok(())
+}
+
+fn write_json<W: Write>(
+    buf: &mut W,
+    columns: &[(&str, &ColumnInference, &ColumnProfile)],
+) -> Result<(), std::io::Error> {
+    let number = |v: f64| {
+        if v.is_finite() {
+            v.to_string()
+        } else {
+            "null".to_string()
+        }
+    };
+
+    writeln!(buf, "[")?;
+    for (i, (header, inference, profile)) in columns.iter().enumerate() {
+        let (distinct, estimated) = profile.distinct();
+
+        writeln!(buf, "  {{")?;
+        writeln!(buf, "    \"column\": {},", util::json_string(header))?;
+        writeln!(
+            buf,
+            "    \"type\": {},",
+            util::json_string(&inference.state.to_string())
+        )?;
+        writeln!(
+            buf,
+            "    \"reason\": {},",
+            util::json_string(&inference.reason())
+        )?;
+        writeln!(buf, "    \"count\": {},", profile.count)?;
+        writeln!(buf, "    \"nulls\": {},", profile.nulls)?;
+        writeln!(buf, "    \"distinct\": {distinct},")?;
+        writeln!(buf, "    \"distinct_estimated\": {estimated},")?;
+
+        match profile.numeric_range() {
+            Some((min, max, mean)) => writeln!(
+                buf,
+                "    \"numeric\": {{ \"min\": {}, \"max\": {}, \"mean\": {} }},",
+                number(min),
+                number(max),
+                number(mean)
+            )?,
+            None => writeln!(buf, "    \"numeric\": null,")?,
+        }
+
+        match profile.length_range() {
+            Some((min, max, mean)) => writeln!(
+                buf,
+                "    \"length\": {{ \"min\": {min}, \"max\": {max}, \"mean\": {} }},",
+                number(mean)
+            )?,
+            None => writeln!(buf, "    \"length\": null,")?,
+        }
+
+        let histogram = profile
+            .length_histogram()
+            .iter()
+            .map(|(label, count)| format!("{}: {count}", util::json_string(label)))
+            .collect::<Vec<_>>()
+            .join(", ");
+        writeln!(buf, "    \"length_histogram\": {{ {histogram} }},")?;
+
+        let (top, top_estimated) = profile.top_values();
+        let top = top
+            .iter()
+            .map(|(v, c)| format!("{{ \"value\": {}, \"count\": {c} }}", util::json_string(v)))
+            .collect::<Vec<_>>()
+            .join(", ");
+        writeln!(buf, "    \"top_values\": [{top}],")?;
+        writeln!(buf, "    \"top_values_estimated\": {top_estimated},")?;
+
+        let widenings = inference
+            .widenings()
+            .iter()
+            .map(|w| {
+                format!(
+                    "{{ \"line\": {}, \"value\": {}, \"from\": {}, \"to\": {} }}",
+                    w.line,
+                    util::json_string(&w.value),
+                    util::json_string(&w.from.to_string()),
+                    util::json_string(&w.to.to_string())
+                )
+            })
+            .collect::<Vec<_>>()
+            .join(", ");
+        writeln!(buf, "    \"widenings\": [{widenings}]")?;
+
+        if i + 1 < columns.len() {
+            writeln!(buf, "  }},")?;
+        } else {
+            writeln!(buf, "  }}")?;
+        }
+    }
+    writeln!(buf, "]")?;
+
+    Ok(())
+}
+
+#[test]
+fn test_hyperloglog() {
+    let mut hll = HyperLogLog::new();
+    for i in 0..50_000 {
+        hll.insert(&i.to_string());
+    }
+
+    let estimate = hll.estimate() as f64;
+    assert!((estimate - 50_000.0).abs() / 50_000.0 < 0.05);
+}
+
+#[test]
+fn test_length_histogram() {
+    let mut profile = ColumnProfile::default();
+    for v in ["a", "bb", "ccc", "dddd", ""] {
+        profile.observe(v);
+    }
+
+    assert_eq!(
+        profile.length_histogram(),
+        vec![
+            ("1".to_string(), 1),
+            ("2-3".to_string(), 2),
+            ("4-7".to_string(), 1)
+        ]
+    );
+    assert_eq!(profile.nulls, 1);
+}
+
+#[test]
+fn test_markdown_escapes() {
+    let mut profile = ColumnProfile::default();
+    let mut inference = ColumnInference::default();
+    for v in ["x|y", "z"] {
+        profile.observe(v);
+        inference.agg(v, 2);
+    }
+
+    let mut buf = Vec::new();
+    write_markdown(&mut buf, &[("a|b", &inference, &profile)]).unwrap();
+    let markdown = String::from_utf8(buf).unwrap();
+
+    // A pipe is escaped in table cells, but not in headings and lists
+    assert!(markdown.contains("\n| `a\\|b` | string | 2 |"));
+    assert!(markdown.contains("\n## `a|b`\n"));
+    assert!(markdown.contains("\n* `x|y` (1)\n"));
+
+    // Line breaks become spaces, so they don't end a row, heading or list item
+    let mut profile = ColumnProfile::default();
+    profile.observe("x\r\ny");
+    let mut buf = Vec::new();
+    write_markdown(&mut buf, &[("a\nb", &inference, &profile)]).unwrap();
+    let markdown = String::from_utf8(buf).unwrap();
+    assert!(markdown.contains("\n| `a b` | string | 1 |"));
+    assert!(markdown.contains("\n## `a b`\n"));
+    assert!(markdown.contains("\n* `x y` (1)\n"));
+}
+
+#[test]
+fn test_estimated_counts() {
+    let mut profile = ColumnProfile::default();
+    for i in 0..MAX_EXACT_DISTINCT {
+        profile.observe(&i.to_string());
+    }
+    profile.observe("0");
+    assert!(!profile.top_values().1);
+
+    // Once values stop being counted, the counts of the most common are estimates
+    profile.observe("new");
+    let (top, estimated) = profile.top_values();
+    assert_eq!(top[0], ("0", 2));
+    assert!(estimated);
+
+    let inference = ColumnInference::default();
+    let mut buf = Vec::new();
+    write_markdown(&mut buf, &[("a", &inference, &profile)]).unwrap();
+    assert!(String::from_utf8(buf).unwrap().contains("\n* `0` (~2)\n"));
+}
@@ -0,0 +1,278 @@
+//! Writes a Python dataclass or pydantic model for the row type, for `--emit python` and
+//! `--emit pydantic`.
+
+use crate::{
+    column::ColumnType,
+    emit::{FieldDef, TypeDef},
+    util,
+};
+use std::io::Write;
+
+/// Names that can't be used for attributes
+const KEYWORDS: &[&str] = &[
+    "False", "None", "True", "and", "as", "assert", "async", "await", "break", "class", "continue",
+    "def", "del", "elif", "else", "except", "finally", "for", "from", "global", "if", "import",
+    "in", "is", "lambda", "nonlocal", "not", "or", "pass", "raise", "return", "try", "while",
+    "with", "yield",
+];
+
+/// A name that can be used for an attribute, with a trailing underscore if it's a keyword
+fn identifier(name: String) -> String {
+    if KEYWORDS.contains(&name.as_str()) {
+        name + "_"
+    } else {
+        name
+    }
+}
+
+/// The type of a field's values, other than `None`. Integers of every width are an `int`.
+fn value_type(field: &FieldDef, def: &TypeDef) -> String {
+    match field.r#type {
+        ColumnType::Unit => "None".to_string(),
+        ColumnType::Bool(_) => "bool".to_string(),
+        ColumnType::F64(_) => "float".to_string(),
+        // Values that weren't seen aren't members of the enum, so they're kept as strings
+        ColumnType::String(_) if field.is_open_enum(def) => {
+            format!("Union[{}, str]", field.enum_name)
+        }
+        ColumnType::String(_) if field.is_enum(def.string_handling) => field.enum_name.clone(),
+        ColumnType::String(_) if !field.values.is_empty() => {
+            let values = field
+                .values
+                .iter()
+                .map(|v| util::json_string(v))
+                .collect::<Vec<_>>();
+            format!("Literal[{}]", values.join(", "))
+        }
+        ColumnType::String(_) => "str".to_string(),
+        _ => "int".to_string(),
+    }
+}
+
+/// Writes a docstring, a single line if it can be.
+fn write_docstring<W: Write>(buf: &mut W, indent: &str, docs: &[String]) -> std::io::Result<()> {
+    let docs = docs
+        .iter()
+        .map(|line| line.replace('\\', "\\\\").replace("\"\"\"", "\\\"\\\"\\\""))
+        .collect::<Vec<_>>();
+
+    match &docs[..] {
+        [] => {}
+        [line] => writeln!(buf, "{indent}\"\"\"{line}\"\"\"")?,
+        [first, lines @ ..] => {
+            writeln!(buf, "{indent}\"\"\"{first}")?;
+            for line in lines {
+                if line.is_empty() {
+                    writeln!(buf)?;
+                } else {
+                    writeln!(buf, "{indent}{line}")?;
+                }
+            }
+            writeln!(buf, "{indent}\"\"\"")?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Writes a dataclass, or a pydantic model, with an attribute for each field, documented by an
+/// attribute docstring. With `--strings enum`, a string field's values are a string `Enum` named
+/// like the Rust enum, and with `--strings static` they're a `Literal`. An optional field's type
+/// is `Optional`.
+///
+/// A pydantic model reads a row as it's read from a CSV file, such as by `csv.DictReader`: each
+/// attribute with a name other than its header has the header as its alias, integers are bounded
+/// by the range of their Rust type, string values are checked against the '.def' file's patterns,
+/// and empty values of optional fields are `None`.
+pub fn write_class<W: Write>(
+    buf: &mut W,
+    def: &TypeDef,
+    pydantic: bool,
+) -> Result<(), std::io::Error> {
+    let names = def
+        .fields
+        .iter()
+        .map(|f| identifier(f.name.clone()))
+        .collect::<Vec<_>>();
+
+    // The arguments to each attribute's pydantic `Field`
+    let arguments = def
+        .fields
+        .iter()
+        .zip(&names)
+        .map(|(field, name)| {
+            let mut arguments = Vec::new();
+            if !pydantic {
+                return arguments;
+            }
+            if field.header != name {
+                arguments.push(format!("alias={}", util::json_string(field.header)));
+            }
+            if let Some((min, max)) = field.r#type.integer_bounds() {
+                arguments.push(format!("ge={min}"));
+                arguments.push(format!("le={max}"));
+            }
+            if let (ColumnType::String(_), [pattern]) = (field.r#type, field.patterns) {
+                arguments.push(format!("pattern={}", util::json_string(pattern)));
+            }
+            arguments
+        })
+        .collect::<Vec<_>>();
+
+    // A field with several patterns is checked by a validator, rather than by its `Field`
+    let patterned = def
+        .fields
+        .iter()
+        .zip(&names)
+        .filter(|(field, _)| {
+            pydantic && matches!(field.r#type, ColumnType::String(_)) && field.patterns.len() > 1
+        })
+        .collect::<Vec<_>>();
+    let empty_to_none = def
+        .fields
+        .iter()
+        .zip(&names)
+        .filter(|(field, _)| {
+            pydantic && (field.r#type.is_optional() || field.r#type == &ColumnType::Unit)
+        })
+        .map(|(_, name)| util::json_string(name))
+        .collect::<Vec<_>>();
+
+    // Imports
+    {
+        let mut typing = Vec::new();
+        if def
+            .fields
+            .iter()
+            .any(|f| !f.values.is_empty() && !f.is_enum(def.string_handling))
+        {
+            typing.push("Literal");
+        }
+        if def.fields.iter().any(|f| f.r#type.is_optional()) {
+            typing.push("Optional");
+        }
+        if def.fields.iter().any(|f| f.is_open_enum(def)) {
+            typing.push("Union");
+        }
+
+        if !patterned.is_empty() {
+            writeln!(buf, "import re")?;
+        }
+        if !pydantic {
+            writeln!(buf, "from dataclasses import dataclass")?;
+        }
+        if def.fields.iter().any(|f| f.is_enum(def.string_handling)) {
+            writeln!(buf, "from enum import Enum")?;
+        }
+        if !typing.is_empty() {
+            writeln!(buf, "from typing import {}", typing.join(", "))?;
+        }
+
+        if pydantic {
+            let mut imports = vec!["BaseModel"];
+            if arguments.iter().flatten().any(|a| a.starts_with("alias=")) {
+                imports.push("ConfigDict");
+            }
+            if arguments.iter().any(|a| !a.is_empty()) {
+                imports.push("Field");
+            }
+            if !patterned.is_empty() || !empty_to_none.is_empty() {
+                imports.push("field_validator");
+            }
+            writeln!(buf)?;
+            writeln!(buf, "from pydantic import {}", imports.join(", "))?;
+        }
+    }
+
+    for field in &def.fields {
+        if field.is_enum(def.string_handling) {
+            writeln!(buf)?;
+            writeln!(buf)?;
+            writeln!(buf, "class {}(str, Enum):", field.enum_name)?;
+            for (value, variant) in field.values.iter().zip(&field.variants) {
+                writeln!(
+                    buf,
+                    "    {} = {}",
+                    identifier(variant.clone()),
+                    util::json_string(value)
+                )?;
+            }
+        }
+    }
+
+    writeln!(buf)?;
+    writeln!(buf)?;
+    if pydantic {
+        writeln!(buf, "class {}(BaseModel):", def.typename)?;
+    } else {
+        writeln!(buf, "@dataclass")?;
+        writeln!(buf, "class {}:", def.typename)?;
+    }
+    if !def.docs.is_empty() {
+        write_docstring(buf, "    ", def.docs)?;
+        writeln!(buf)?;
+    }
+    if arguments.iter().flatten().any(|a| a.starts_with("alias=")) {
+        // Rows are read by header, but can still be created by attribute name
+        writeln!(buf, "    model_config = ConfigDict(populate_by_name=True)")?;
+        writeln!(buf)?;
+    }
+
+    for ((field, name), arguments) in def.fields.iter().zip(&names).zip(&arguments) {
+        let mut r#type = value_type(field, def);
+        if field.r#type.is_optional() {
+            r#type = format!("Optional[{type}]");
+        }
+
+        if arguments.is_empty() {
+            writeln!(buf, "    {name}: {type}")?;
+        } else {
+            writeln!(buf, "    {name}: {type} = Field({})", arguments.join(", "))?;
+        }
+        write_docstring(buf, "    ", field.docs)?;
+    }
+
+    if !empty_to_none.is_empty() {
+        writeln!(buf)?;
+        writeln!(
+            buf,
+            "    @field_validator({}, mode=\"before\")",
+            empty_to_none.join(", ")
+        )?;
+        writeln!(buf, "    @classmethod")?;
+        writeln!(buf, "    def empty_to_none(cls, value):")?;
+        writeln!(buf, "        return None if value == \"\" else value")?;
+    }
+
+    for (field, name) in patterned {
+        let patterns = field
+            .patterns
+            .iter()
+            .map(|p| util::json_string(p))
+            .collect::<Vec<_>>();
+
+        writeln!(buf)?;
+        writeln!(buf, "    @field_validator({})", util::json_string(name))?;
+        writeln!(buf, "    @classmethod")?;
+        writeln!(buf, "    def check_{name}(cls, value):")?;
+        writeln!(buf, "        for pattern in ({}):", patterns.join(", "))?;
+        writeln!(
+            buf,
+            "            if value is not None and not re.search(pattern, value):"
+        )?;
+        writeln!(
+            buf,
+            "                raise ValueError(f\"{{value!r}} doesn't match {{pattern!r}}\")"
+        )?;
+        writeln!(buf, "        return value")?;
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_identifier() {
+    assert_eq!(identifier("class".to_string()), "class_");
+    assert_eq!(identifier("None".to_string()), "None_");
+    assert_eq!(identifier("order_id".to_string()), "order_id");
+}
@@ -0,0 +1,535 @@
+use crate::{
+    input_args::SampleStrategy,
+    util::{Fnv1a, Rng},
+};
+use std::collections::{BTreeMap, HashMap, VecDeque};
+
+/// How the rows used for inference are sampled from an input that is read in full.
+#[derive(Clone, Copy, Debug)]
+pub struct SampleOptions {
+    pub strategy: SampleStrategy,
+    /// How many rows to sample
+    pub size: usize,
+    pub seed: u64,
+    /// The index of the column whose values the sample is stratified by
+    pub stratify: Option<usize>,
+    pub trim_input: bool,
+}
+
+impl SampleOptions {
+    /// Creates the sampler for one chunk of the input.
+    pub fn sampler(&self, chunk: usize) -> Sampler {
+        // Each chunk has its own stream of random numbers so that the sample doesn't depend
+        // on which thread analyzed it
+        let seed = Rng::new(self.seed ^ chunk as u64).next_u64();
+
+        match (self.strategy, self.stratify) {
+            (_, Some(column)) => Sampler::Stratified(Stratified {
+                column,
+                trim_input: self.trim_input,
+                seed,
+                sample: Reservoir::new(self.size, seed),
+                strata: HashMap::new(),
+                others: 0,
+            }),
+            (SampleStrategy::Stride, None) => Sampler::Stride(Stride::new(self.size)),
+            (SampleStrategy::Tail, None) => Sampler::Tail(Tail::new(self.size)),
+            (SampleStrategy::Head | SampleStrategy::Reservoir, None) => {
+                Sampler::Reservoir(Reservoir::new(self.size, seed))
+            }
+        }
+    }
+
+    /// Combines the samples of each chunk of the input, in file order, into a single sample
+    /// of the whole input, returned in the order the records appeared.
+    pub fn merge(&self, samplers: Vec<Sampler>) -> Vec<csv::StringRecord> {
+        let mut sample = match (self.strategy, self.stratify) {
+            (_, Some(column)) => {
+                let mut samples = Vec::new();
+                let mut strata = BTreeMap::<String, Vec<Reservoir>>::new();
+                let mut others = 0;
+                for sampler in samplers {
+                    if let Sampler::Stratified(s) = sampler {
+                        samples.push(s.sample);
+                        for (key, reservoir) in s.strata {
+                            strata.entry(key).or_default().push(reservoir);
+                        }
+                        others += s.others;
+                    }
+                }
+                if others > 0 {
+                    eprintln!(
+                        "Warning: the column to stratify by has more than {MAX_STRATA} values, so \
+                         rows with values past the first {MAX_STRATA} may not be represented"
+                    );
+                }
+                let key = |r: &csv::StringRecord| {
+                    let key = r.get(column).unwrap_or_default();
+                    let key = if self.trim_input { key.trim() } else { key };
+                    key.to_string()
+                };
+                Stratified::merge(samples, strata, key, self.size, self.seed)
+            }
+            (SampleStrategy::Stride, None) => {
+                let strides = samplers.into_iter().filter_map(|s| match s {
+                    Sampler::Stride(s) => Some(s),
+                    _ => None,
+                });
+                Stride::merge(strides.collect(), self.size)
+            }
+            (SampleStrategy::Tail, None) => {
+                let mut tail = Tail::new(self.size);
+                for sampler in samplers {
+                    if let Sampler::Tail(t) = sampler {
+                        for record in t.records {
+                            tail.push(&record);
+                        }
+                    }
+                }
+                tail.records.into()
+            }
+            (SampleStrategy::Head | SampleStrategy::Reservoir, None) => {
+                let reservoirs = samplers.into_iter().filter_map(|s| match s {
+                    Sampler::Reservoir(r) => Some(r),
+                    _ => None,
+                });
+                Reservoir::merge(reservoirs.collect(), self.size, self.seed)
+            }
+        };
+
+        sample.sort_by_key(|r| r.position().map(|p| p.line()));
+        sample
+    }
+}
+
+/// Collects a sample of the records in one chunk of the input.
+pub enum Sampler {
+    Reservoir(Reservoir),
+    Stride(Stride),
+    Tail(Tail),
+    Stratified(Stratified),
+}
+
+impl Sampler {
+    pub fn push(&mut self, record: &csv::StringRecord) {
+        match self {
+            Sampler::Reservoir(r) => r.push(record),
+            Sampler::Stride(s) => s.push(record),
+            Sampler::Tail(t) => t.push(record),
+            Sampler::Stratified(s) => s.push(record),
+        }
+    }
+
+    /// Adjusts the line numbers of sampled records for a part of the input that didn't start on line 1.
+    pub fn offset_lines(&mut self, offset: u64) {
+        let records: Box<dyn Iterator<Item = &mut csv::StringRecord>> = match self {
+            Sampler::Reservoir(r) => Box::new(r.records.iter_mut()),
+            Sampler::Stride(s) => Box::new(s.records.iter_mut().map(|(_, r)| r)),
+            Sampler::Tail(t) => Box::new(t.records.iter_mut()),
+            Sampler::Stratified(s) => Box::new(
+                [&mut s.sample]
+                    .into_iter()
+                    .chain(s.strata.values_mut())
+                    .flat_map(|r| r.records.iter_mut()),
+            ),
+        };
+
+        for record in records {
+            if let Some(pos) = record.position() {
+                let mut pos = pos.clone();
+                pos.set_line(pos.line() + offset);
+                record.set_position(Some(pos));
+            }
+        }
+    }
+}
+
+/// A uniform random sample of up to `capacity` records from a stream of unknown length,
+/// using Vitter's Algorithm R.
+pub struct Reservoir {
+    capacity: usize,
+    /// How many records have been offered to the reservoir
+    seen: u64,
+    records: Vec<csv::StringRecord>,
+    rng: Rng,
+}
+
+impl Reservoir {
+    pub fn new(capacity: usize, seed: u64) -> Self {
+        Reservoir {
+            capacity,
+            seen: 0,
+            records: Vec::new(),
+            rng: Rng::new(seed),
+        }
+    }
+
+    pub fn push(&mut self, record: &csv::StringRecord) {
+        self.seen += 1;
+
+        if self.records.len() < self.capacity {
+            self.records.push(record.clone());
+        } else {
+            let j = self.rng.below(self.seen) as usize;
+            if j < self.capacity {
+                self.records[j] = record.clone();
+            }
+        }
+    }
+
+    /// Draws a uniform sample of up to `capacity` records from the union of the streams
+    /// sampled by `reservoirs`.
+    ///
+    /// Each pick chooses a reservoir in proportion to how many of its stream's records remain
+    /// unpicked, so the number taken from each follows the same (hypergeometric) distribution
+    /// as sampling the concatenated streams directly.
+    pub fn merge(reservoirs: Vec<Reservoir>, capacity: usize, seed: u64) -> Vec<csv::StringRecord> {
+        let mut rng = Rng::new(seed);
+        let mut remaining = reservoirs.iter().map(|r| r.seen).collect::<Vec<_>>();
+        let mut pools = reservoirs
+            .into_iter()
+            .map(|r| r.records)
+            .collect::<Vec<_>>();
+        let mut sample = Vec::new();
+
+        while sample.len() < capacity {
+            let total = remaining.iter().sum::<u64>();
+            if total == 0 {
+                break;
+            }
+
+            let mut pick = rng.below(total);
+            let index = remaining
+                .iter()
+                .position(|r| {
+                    if pick < *r {
+                        true
+                    } else {
+                        pick -= r;
+                        false
+                    }
+                })
+                .expect("pick is below the total");
+
+            remaining[index] -= 1;
+            let pool = &mut pools[index];
+            let j = rng.below(pool.len() as u64) as usize;
+            sample.push(pool.swap_remove(j));
+        }
+
+        sample
+    }
+}
+
+/// An evenly spaced sample of a stream of unknown length. Every `stride`th record is kept, and
+/// whenever twice the target number are held, every other one is dropped and the stride doubles.
+pub struct Stride {
+    capacity: usize,
+    stride: u64,
+    seen: u64,
+    /// The kept records and their indexes in the stream
+    records: Vec<(u64, csv::StringRecord)>,
+}
+
+impl Stride {
+    pub fn new(capacity: usize) -> Self {
+        Stride {
+            capacity,
+            stride: 1,
+            seen: 0,
+            records: Vec::new(),
+        }
+    }
+
+    pub fn push(&mut self, record: &csv::StringRecord) {
+        let index = self.seen;
+        self.seen += 1;
+
+        if index.is_multiple_of(self.stride) {
+            self.records.push((index, record.clone()));
+
+            if self.records.len() >= 2 * self.capacity.max(1) {
+                self.stride *= 2;
+                let stride = self.stride;
+                self.records.retain(|(i, _)| i.is_multiple_of(stride));
+            }
+        }
+    }
+
+    /// Picks up to `capacity` evenly spaced records from the records kept by `strides`, which
+    /// sampled consecutive parts of the same stream, in order.
+    fn merge(strides: Vec<Stride>, capacity: usize) -> Vec<csv::StringRecord> {
+        // Each part counts its records from its own start, so its indexes are offset by the
+        // records in the parts before it
+        let mut total = 0;
+        let mut kept = Vec::new();
+        for stride in strides {
+            kept.extend(stride.records.into_iter().map(|(i, r)| (total + i, r)));
+            total += stride.seen;
+        }
+
+        if kept.len() <= capacity {
+            return kept.into_iter().map(|(_, r)| r).collect();
+        }
+
+        // The parts may have kept different strides from different starting points, so each of
+        // `capacity` evenly spaced positions in the whole stream takes the nearest kept record
+        let step = total as f64 / capacity as f64;
+        let mut picked = Vec::with_capacity(capacity);
+        let mut next = 0;
+        for i in 0..capacity {
+            let target = (i as f64 * step) as u64;
+            let after = next + kept[next..].partition_point(|(index, _)| *index < target);
+            let nearest = match after {
+                _ if after == kept.len() => after - 1,
+                _ if after > next && target - kept[after - 1].0 <= kept[after].0 - target => {
+                    after - 1
+                }
+                _ => after,
+            };
+            picked.push(nearest);
+            next = nearest + 1;
+            if next == kept.len() {
+                break;
+            }
+        }
+
+        let mut kept = kept.into_iter().map(|(_, r)| Some(r)).collect::<Vec<_>>();
+        picked.into_iter().filter_map(|i| kept[i].take()).collect()
+    }
+}
+
+/// The last `capacity` records of a stream.
+pub struct Tail {
+    capacity: usize,
+    records: VecDeque<csv::StringRecord>,
+}
+
+impl Tail {
+    pub fn new(capacity: usize) -> Self {
+        Tail {
+            capacity,
+            records: VecDeque::new(),
+        }
+    }
+
+    pub fn push(&mut self, record: &csv::StringRecord) {
+        if self.capacity == 0 {
+            return;
+        }
+        if self.records.len() == self.capacity {
+            self.records.pop_front();
+        }
+        self.records.push_back(record.clone());
+    }
+}
+
+/// How many distinct values of the key column each chunk keeps a record of, so that they're
+/// represented in the sample.
+const MAX_STRATA: usize = 64;
+
+/// A uniform random sample, in which each distinct value of a key column is represented in
+/// proportion to how often it occurs, along with one record of each value, so that rare values
+/// of the key are represented in the combined sample.
+///
+/// Only the uniform sample holds up to `capacity` records, so a chunk holds no more than
+/// `capacity + MAX_STRATA` however many values the key has.
+pub struct Stratified {
+    column: usize,
+    trim_input: bool,
+    seed: u64,
+    sample: Reservoir,
+    /// A single record of each of the first `MAX_STRATA` values of the key
+    strata: HashMap<String, Reservoir>,
+    /// How many records had keys first seen after `MAX_STRATA` others
+    others: u64,
+}
+
+impl Stratified {
+    pub fn push(&mut self, record: &csv::StringRecord) {
+        self.sample.push(record);
+
+        let key = record.get(self.column).unwrap_or_default();
+        let key = if self.trim_input { key.trim() } else { key };
+
+        if !self.strata.contains_key(key) {
+            if self.strata.len() == MAX_STRATA {
+                self.others += 1;
+                return;
+            }
+            let seed = self.seed ^ key_hash(key);
+            self.strata.insert(key.to_string(), Reservoir::new(1, seed));
+        }
+
+        self.strata.get_mut(key).unwrap().push(record);
+    }
+
+    /// Samples `capacity` records uniformly, which represents each stratum in proportion to its
+    /// size, and then replaces records of the best-represented strata with one record of each
+    /// stratum that was missed, exceeding `capacity` only when every stratum has a single record.
+    fn merge(
+        samples: Vec<Reservoir>,
+        strata: BTreeMap<String, Vec<Reservoir>>,
+        key: impl Fn(&csv::StringRecord) -> String,
+        capacity: usize,
+        seed: u64,
+    ) -> Vec<csv::StringRecord> {
+        let mut rng = Rng::new(seed);
+        let mut sample = Reservoir::merge(samples, capacity, seed);
+        let mut counts = BTreeMap::<String, usize>::new();
+        for record in &sample {
+            *counts.entry(key(record)).or_default() += 1;
+        }
+
+        for (stratum, parts) in strata {
+            if counts.contains_key(&stratum) {
+                continue;
+            }
+
+            let largest = counts
+                .iter()
+                .max_by_key(|(_, n)| **n)
+                .filter(|(_, n)| **n > 1)
+                .map(|(k, _)| k.clone());
+            if let Some(largest) = largest {
+                let positions = (0..sample.len())
+                    .filter(|&i| key(&sample[i]) == largest)
+                    .collect::<Vec<_>>();
+                let j = positions[rng.below(positions.len() as u64) as usize];
+                sample.swap_remove(j);
+                *counts.get_mut(&largest).unwrap() -= 1;
+            }
+
+            sample.extend(Reservoir::merge(parts, 1, seed ^ key_hash(&stratum)));
+            counts.insert(stratum, 1);
+        }
+
+        sample
+    }
+}
+
+fn key_hash(key: &str) -> u64 {
+    let mut hasher = Fnv1a::new();
+    hasher.write(key.as_bytes());
+    hasher.finish()
+}
+
+#[cfg(test)]
+fn test_records(lines: std::ops::RangeInclusive<u64>) -> Vec<csv::StringRecord> {
+    lines
+        .map(|line| {
+            let mut r = csv::StringRecord::from(vec![(line % 10).to_string()]);
+            let mut pos = csv::Position::new();
+            pos.set_line(line);
+            r.set_position(Some(pos));
+            r
+        })
+        .collect()
+}
+
+#[test]
+fn test_reservoir_merge() {
+    let options = SampleOptions {
+        strategy: SampleStrategy::Reservoir,
+        size: 10,
+        seed: 3,
+        stratify: None,
+        trim_input: false,
+    };
+
+    let mut first = options.sampler(0);
+    let mut second = options.sampler(1);
+    test_records(1..=100).iter().for_each(|r| first.push(r));
+    test_records(1..=5).iter().for_each(|r| second.push(r));
+    second.offset_lines(100);
+
+    let sample = options.merge(vec![first, second]);
+    let lines = sample
+        .iter()
+        .map(|r| r.position().unwrap().line())
+        .collect::<Vec<_>>();
+
+    assert_eq!(lines.len(), 10);
+    assert!(lines.windows(2).all(|w| w[0] < w[1]));
+    assert!(lines.iter().all(|l| (1..=105).contains(l)));
+}
+
+#[test]
+fn test_stride_and_stratified() {
+    let mut stride = Stride::new(10);
+    test_records(1..=1000).iter().for_each(|r| stride.push(r));
+    let lines = Stride::merge(vec![stride], 10)
+        .iter()
+        .map(|r| r.position().unwrap().line())
+        .collect::<Vec<_>>();
+    assert_eq!(lines, vec![1, 129, 193, 321, 385, 513, 577, 705, 769, 897]);
+
+    // Parts of the stream sampled separately are spaced by their rows in the whole stream
+    let options = SampleOptions {
+        strategy: SampleStrategy::Stride,
+        size: 10,
+        seed: 0,
+        stratify: None,
+        trim_input: false,
+    };
+    let (mut first, mut second) = (options.sampler(0), options.sampler(1));
+    test_records(1..=300).iter().for_each(|r| first.push(r));
+    test_records(1..=700).iter().for_each(|r| second.push(r));
+    second.offset_lines(300);
+    let lines = options
+        .merge(vec![first, second])
+        .iter()
+        .map(|r| r.position().unwrap().line())
+        .collect::<Vec<_>>();
+    assert_eq!(lines, vec![1, 97, 193, 301, 429, 493, 621, 685, 813, 877]);
+
+    // One rare key among many common ones still appears in the sample
+    let options = SampleOptions {
+        strategy: SampleStrategy::Reservoir,
+        size: 10,
+        seed: 0,
+        stratify: Some(0),
+        trim_input: false,
+    };
+    let mut sampler = options.sampler(0);
+    for record in test_records(1..=1000) {
+        if record[0] != *"7" || record.position().unwrap().line() == 7 {
+            sampler.push(&record);
+        }
+    }
+    let sample = options.merge(vec![sampler]);
+    assert_eq!(sample.len(), 10);
+    assert_eq!(sample.iter().filter(|r| &r[0] == "7").count(), 1);
+}
+
+#[test]
+fn test_max_strata() {
+    let options = SampleOptions {
+        strategy: SampleStrategy::Reservoir,
+        size: 10,
+        seed: 0,
+        stratify: Some(0),
+        trim_input: false,
+    };
+
+    // Keys past the first `MAX_STRATA` aren't kept, and the others keep one record each besides
+    // the uniform sample
+    let mut sampler = options.sampler(0);
+    for key in 0..1000 {
+        sampler.push(&csv::StringRecord::from(vec![key.to_string()]));
+    }
+    let Sampler::Stratified(stratified) = &sampler else {
+        panic!("expected a stratified sampler");
+    };
+    assert_eq!(stratified.strata.len(), MAX_STRATA);
+    assert!(stratified.strata.values().all(|r| r.records.len() == 1));
+    assert_eq!(stratified.others, 1000 - MAX_STRATA as u64);
+    assert_eq!(stratified.sample.records.len(), 10);
+
+    // Each stratum is still represented, with the uniform sample's records of other keys
+    let sample = options.merge(vec![sampler]);
+    assert!(sample.len() >= MAX_STRATA && sample.len() <= MAX_STRATA + 10);
+    for key in 0..MAX_STRATA {
+        assert!(sample.iter().any(|r| r[0] == key.to_string()));
+    }
+}
@@ -0,0 +1,141 @@
+//! Reads a worksheet of an Excel or OpenDocument workbook as text records, using the type of each
+//! cell as a hint when inferring the column types.
+
+use crate::{
+    column::IntermediateColumnType,
+    err::TypeGenErrors,
+    inference::{InferenceOptions, InputStats},
+    input_args::CellRange,
+};
+use calamine::{Data, DataType, Reader};
+use std::path::Path;
+
+/// The rows of a worksheet that aren't empty, with their one-based row numbers
+pub struct Sheet {
+    /// The name of the worksheet, which the generated code reads
+    pub name: String,
+    pub rows: Vec<(u64, Vec<Data>)>,
+}
+
+/// Reads the worksheet named `sheet`, or else the first worksheet, limited to the cells in `range`.
+pub fn read_sheet(
+    path: &Path,
+    sheet: Option<&str>,
+    range: Option<CellRange>,
+) -> Result<Sheet, TypeGenErrors> {
+    let mut workbook = calamine::open_workbook_auto(path)?;
+    let names = workbook.sheet_names();
+    let name = match sheet {
+        Some(sheet) if names.iter().any(|n| n == sheet) => sheet.to_string(),
+        Some(sheet) => {
+            return Err(TypeGenErrors::Other(
+                format!(
+                    "{} has no worksheet named '{sheet}'; its worksheets are {names:?}",
+                    path.display()
+                )
+                .into(),
+            ))
+        }
+        None => names
+            .first()
+            .cloned()
+            .ok_or_else(|| format!("{} has no worksheets", path.display()))?,
+    };
+
+    let mut cells = workbook.worksheet_range(&name)?;
+    if let Some(range) = range {
+        let end = range
+            .end
+            .unwrap_or_else(|| cells.end().unwrap_or(range.start));
+        cells = cells.range(range.start, end);
+    }
+
+    let first_row = cells.start().map_or(0, |(row, _)| row as u64);
+    let rows = cells
+        .rows()
+        .enumerate()
+        .filter(|(_, cells)| !cells.iter().all(|c| text(c).is_empty()))
+        .map(|(i, cells)| (first_row + i as u64 + 1, cells.to_vec()))
+        .collect();
+
+    Ok(Sheet { name, rows })
+}
+
+/// The text of a cell, in the same way as the generated code. Dates are ISO 8601 text, durations
+/// are a number of days, and errors such as `#DIV/0!` are empty.
+pub fn text(cell: &Data) -> String {
+    match cell {
+        Data::Empty | Data::Error(_) => String::new(),
+        Data::DateTime(d) if d.is_duration() => d.as_f64().to_string(),
+        Data::DateTime(d) => match cell.as_datetime() {
+            Some(dt) if d.as_f64().fract() == 0.0 => dt.date().to_string(),
+            Some(dt) => format!("{}T{}", dt.date(), dt.time()),
+            None => d.as_f64().to_string(),
+        },
+        cell => cell.to_string(),
+    }
+}
+
+/// Converts a row of cells to a record of their text, positioned at its row of the worksheet.
+pub fn to_record(cells: &[Data], index: u64, row: u64) -> csv::StringRecord {
+    let mut record = cells.iter().map(text).collect::<csv::StringRecord>();
+    let mut position = csv::Position::new();
+    position.set_record(index).set_line(row);
+    record.set_position(Some(position));
+    record
+}
+
+/// The kinds of cells in a column that aren't empty
+#[derive(Default)]
+struct CellKinds {
+    values: usize,
+    strings: usize,
+    dates: usize,
+    durations: usize,
+}
+
+/// Infers the column types from the text of the rows after any header row, then adjusts them by
+/// the types of the cells: a column of text cells is a string even if its values are numbers, and
+/// dates and durations are described as converted.
+pub fn analyze_rows(rows: &[(u64, Vec<Data>)], options: &InferenceOptions) -> InputStats {
+    let mut stats = InputStats::new(options);
+    let mut kinds = (0..options.num_columns)
+        .map(|_| CellKinds::default())
+        .collect::<Vec<_>>();
+
+    for (i, (row, cells)) in rows.iter().take(options.num_rows).enumerate() {
+        stats.observe(&to_record(cells, i as u64, *row), options);
+
+        for (kinds, cell) in kinds.iter_mut().zip(cells) {
+            match cell {
+                Data::Empty | Data::Error(_) => continue,
+                Data::String(_) => kinds.strings += 1,
+                Data::DateTime(d) if d.is_duration() => kinds.durations += 1,
+                Data::DateTime(_) | Data::DateTimeIso(_) => kinds.dates += 1,
+                _ => {}
+            }
+            kinds.values += 1;
+        }
+    }
+
+    for (column, kinds) in stats.columns.iter_mut().zip(kinds) {
+        if kinds.values == 0 {
+            continue;
+        }
+
+        let state = &mut column.inference.state;
+        if kinds.strings == kinds.values && !matches!(state, IntermediateColumnType::String(_)) {
+            *state = IntermediateColumnType::String(state.is_optional());
+            column.source_type = Some(
+                "text in the spreadsheet, although its values look like numbers or bools"
+                    .to_string(),
+            );
+        } else if kinds.dates == kinds.values {
+            column.source_type = Some("a spreadsheet date, as ISO 8601 text".to_string());
+        } else if kinds.durations == kinds.values {
+            column.source_type = Some("a spreadsheet duration, as a number of days".to_string());
+        }
+    }
+
+    stats
+}
@@ -0,0 +1,310 @@
+//! Writes a SQL `CREATE TABLE` statement for the inferred columns, for `--emit sql`, and the
+//! statements that load the input into it.
+
+use crate::{
+    column::ColumnType,
+    emit::{FieldDef, TypeDef},
+    input_args::{Commands, SqlDialect, SqlLoad},
+    util,
+};
+use std::{collections::HashSet, io::Write, path::PathBuf};
+
+impl SqlDialect {
+    /// Quotes the name of a table, column or type.
+    fn quote_identifier(self, name: &str) -> String {
+        match self {
+            SqlDialect::Mysql => format!("`{}`", name.replace('`', "``")),
+            SqlDialect::Postgres | SqlDialect::Sqlite => util::quote_identifier(name),
+        }
+    }
+
+    /// Quotes a string literal. MySQL also treats backslashes as escapes.
+    fn quote_string(self, s: &str) -> String {
+        let s = s.replace('\'', "''");
+        match self {
+            SqlDialect::Mysql => format!("'{}'", s.replace('\\', "\\\\")),
+            SqlDialect::Postgres | SqlDialect::Sqlite => format!("'{s}'"),
+        }
+    }
+
+    /// The parameter for the `i`th column of an `INSERT` statement
+    fn placeholder(self, i: usize) -> String {
+        match self {
+            SqlDialect::Postgres => format!("${}", i + 1),
+            SqlDialect::Sqlite | SqlDialect::Mysql => "?".to_string(),
+        }
+    }
+
+    /// The SQL type that holds every value of a column type. Integers are narrowed as far as the
+    /// dialect allows, and unsigned integers take the next larger signed type where there is no
+    /// unsigned one.
+    fn sql_type(self, r#type: &ColumnType) -> &'static str {
+        use ColumnType::*;
+
+        match (self, r#type) {
+            (_, Unit | String(_)) => "TEXT",
+            (_, Bool(_)) => "BOOLEAN",
+
+            (SqlDialect::Postgres, I8(_) | I16(_) | U8(_)) => "SMALLINT",
+            (SqlDialect::Postgres, I32(_) | U16(_)) => "INTEGER",
+            (SqlDialect::Postgres, I64(_) | U32(_)) => "BIGINT",
+            (SqlDialect::Postgres, U64(_)) => "NUMERIC(20)",
+            (SqlDialect::Postgres, F64(_)) => "DOUBLE PRECISION",
+
+            // SQLite stores every integer in up to 8 bytes
+            (SqlDialect::Sqlite, I8(_) | I16(_) | I32(_) | I64(_)) => "INTEGER",
+            (SqlDialect::Sqlite, U8(_) | U16(_) | U32(_) | U64(_)) => "INTEGER",
+            (SqlDialect::Sqlite, F64(_)) => "REAL",
+
+            (SqlDialect::Mysql, I8(_)) => "TINYINT",
+            (SqlDialect::Mysql, I16(_)) => "SMALLINT",
+            (SqlDialect::Mysql, I32(_)) => "INT",
+            (SqlDialect::Mysql, I64(_)) => "BIGINT",
+            (SqlDialect::Mysql, U8(_)) => "TINYINT UNSIGNED",
+            (SqlDialect::Mysql, U16(_)) => "SMALLINT UNSIGNED",
+            (SqlDialect::Mysql, U32(_)) => "INT UNSIGNED",
+            (SqlDialect::Mysql, U64(_)) => "BIGINT UNSIGNED",
+            (SqlDialect::Mysql, F64(_)) => "DOUBLE",
+        }
+    }
+}
+
+/// The name of each field's SQL column: its header, or the name of its column when the header is
+/// empty or repeats an earlier one. Names are compared without case, as SQLite and MySQL do.
+fn column_names(fields: &[FieldDef]) -> Vec<String> {
+    let mut seen = HashSet::new();
+    let mut names = Vec::with_capacity(fields.len());
+
+    for field in fields {
+        let mut name = if field.header.trim().is_empty() {
+            field.column.to_string()
+        } else {
+            field.header.to_string()
+        };
+
+        if seen.contains(&name.to_lowercase()) {
+            name = field.column.to_string();
+        }
+        if seen.contains(&name.to_lowercase()) {
+            name = (2..)
+                .map(|n| format!("{}_{n}", field.column))
+                .find(|n| !seen.contains(&n.to_lowercase()))
+                .unwrap();
+        }
+
+        seen.insert(name.to_lowercase());
+        names.push(name);
+    }
+
+    names
+}
+
+/// Writes the `CREATE TABLE` statement, with a column named by the header of each field, preceded
+/// by a `CREATE TYPE` for each enumerated column in Postgres. Enumerated columns are an `ENUM` in
+/// MySQL and have a `CHECK` constraint in SQLite.
+pub fn write_create_table<W: Write>(
+    buf: &mut W,
+    dialect: SqlDialect,
+    table: &str,
+    def: &TypeDef,
+) -> Result<(), std::io::Error> {
+    let (columns, key) = (&def.fields, def.key);
+    for line in def.docs {
+        writeln!(buf, "-- {line}")?;
+    }
+
+    let quote_values = |values: &[&str]| {
+        values
+            .iter()
+            .map(|v| dialect.quote_string(v))
+            .collect::<Vec<_>>()
+            .join(", ")
+    };
+
+    let names = column_names(columns);
+    let mut definitions = Vec::new();
+    for (i, (column, name)) in columns.iter().zip(&names).enumerate() {
+        let quoted = dialect.quote_identifier(name);
+        let is_key = key.contains(&i);
+
+        let mut definition = String::new();
+        for line in column.docs {
+            definition += &format!("    -- {line}\n");
+        }
+
+        // An enum that holds values that weren't seen is stored as text of any value
+        let values: &[&str] = if column.is_open_enum(def) {
+            &[]
+        } else {
+            &column.values
+        };
+
+        let sql_type = match (dialect, values.is_empty()) {
+            (SqlDialect::Postgres, false) => {
+                // Postgres enums are types of their own, named after the table and column
+                let type_name = dialect.quote_identifier(&format!("{table}_{name}"));
+                writeln!(
+                    buf,
+                    "CREATE TYPE {type_name} AS ENUM ({});",
+                    quote_values(values)
+                )?;
+                type_name
+            }
+            (SqlDialect::Mysql, false) => format!("ENUM({})", quote_values(values)),
+            // MySQL can't index TEXT without a prefix length
+            (SqlDialect::Mysql, true)
+                if is_key && matches!(column.r#type, ColumnType::String(_)) =>
+            {
+                "VARCHAR(255)".to_string()
+            }
+            _ => dialect.sql_type(column.r#type).to_string(),
+        };
+
+        let null = if column.r#type.is_optional() || column.r#type == &ColumnType::Unit {
+            "NULL"
+        } else {
+            "NOT NULL"
+        };
+        definition += &format!("    {quoted} {sql_type} {null}");
+
+        if dialect == SqlDialect::Sqlite && !values.is_empty() {
+            definition += &format!(" CHECK ({quoted} IN ({}))", quote_values(values));
+        }
+        definitions.push(definition);
+    }
+
+    if !key.is_empty() {
+        let key = key
+            .iter()
+            .map(|&i| dialect.quote_identifier(&names[i]))
+            .collect::<Vec<_>>()
+            .join(", ");
+        definitions.push(format!("    PRIMARY KEY ({key})"));
+    }
+
+    writeln!(buf, "CREATE TABLE {} (", dialect.quote_identifier(table))?;
+    writeln!(buf, "{}", definitions.join(",\n"))?;
+    writeln!(buf, ");")?;
+
+    Ok(())
+}
+
+/// Writes the statements that load the input into the table: the dialect's bulk-load command
+/// for each of the CSV `files`, read with the `--delimiter` and `--no-header` arguments, or a single
+/// `INSERT` statement with a parameter for each column.
+pub fn write_load<W: Write>(
+    buf: &mut W,
+    args: &Commands,
+    load: SqlLoad,
+    table: &str,
+    columns: &[FieldDef],
+    files: &[PathBuf],
+) -> Result<(), std::io::Error> {
+    let dialect = args.dialect;
+    let has_header = !args.no_header;
+    let table = dialect.quote_identifier(table);
+    let names = column_names(columns)
+        .iter()
+        .map(|name| dialect.quote_identifier(name))
+        .collect::<Vec<_>>();
+
+    if load == SqlLoad::Insert {
+        let placeholders = (0..columns.len())
+            .map(|i| dialect.placeholder(i))
+            .collect::<Vec<_>>();
+        writeln!(
+            buf,
+            "INSERT INTO {table} ({}) VALUES ({});",
+            names.join(", "),
+            placeholders.join(", ")
+        )?;
+        return Ok(());
+    }
+
+    let delimiter = args.delimiter.to_string();
+    for file in files {
+        let path = dialect.quote_string(&file.display().to_string());
+        match dialect {
+            SqlDialect::Postgres => writeln!(
+                buf,
+                "COPY {table} ({}) FROM {path} WITH (FORMAT csv, HEADER {has_header}, DELIMITER {});",
+                names.join(", "),
+                dialect.quote_string(&delimiter)
+            )?,
+            SqlDialect::Sqlite => {
+                // Dot-commands of the sqlite3 shell, since SQLite has no statement for this
+                writeln!(buf, ".mode csv")?;
+                writeln!(buf, ".separator {delimiter:?}")?;
+                let skip = if has_header { "--skip 1 " } else { "" };
+                writeln!(buf, ".import {skip}{path} {table}")?;
+            }
+            SqlDialect::Mysql => {
+                // Empty values are loaded as NULL, rather than as 0 or an empty string, and bools
+                // from their text, which MySQL would otherwise read as 0
+                let (targets, converted): (Vec<_>, Vec<_>) = columns
+                    .iter()
+                    .zip(&names)
+                    .enumerate()
+                    .map(|(i, (column, name))| {
+                        let optional = column.r#type.is_optional();
+                        let value = match column.r#type {
+                            ColumnType::Bool(_) if optional => {
+                                format!("IF(@v{i} = '', NULL, @v{i} = 'true')")
+                            }
+                            ColumnType::Bool(_) => format!("(@v{i} = 'true')"),
+                            _ if optional => format!("NULLIF(@v{i}, '')"),
+                            _ => return (name.clone(), None),
+                        };
+                        (format!("@v{i}"), Some(format!("{name} = {value}")))
+                    })
+                    .unzip();
+                let converted = converted.into_iter().flatten().collect::<Vec<_>>();
+
+                writeln!(buf, "LOAD DATA LOCAL INFILE {path}")?;
+                writeln!(buf, "INTO TABLE {table}")?;
+                writeln!(
+                    buf,
+                    "FIELDS TERMINATED BY {} OPTIONALLY ENCLOSED BY '\"'",
+                    dialect.quote_string(&delimiter)
+                )?;
+                writeln!(buf, "LINES TERMINATED BY '\\n'")?;
+                if has_header {
+                    writeln!(buf, "IGNORE 1 LINES")?;
+                }
+                if converted.is_empty() {
+                    writeln!(buf, "({});", targets.join(", "))?;
+                } else {
+                    writeln!(buf, "({})", targets.join(", "))?;
+                    writeln!(buf, "SET {};", converted.join(", "))?;
+                }
+            }
+        }
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_sql_type() {
+    assert_eq!(
+        SqlDialect::Postgres.sql_type(&ColumnType::U8(false)),
+        "SMALLINT"
+    );
+    assert_eq!(
+        SqlDialect::Postgres.sql_type(&ColumnType::U32(true)),
+        "BIGINT"
+    );
+    assert_eq!(
+        SqlDialect::Mysql.sql_type(&ColumnType::U16(false)),
+        "SMALLINT UNSIGNED"
+    );
+    assert_eq!(
+        SqlDialect::Sqlite.sql_type(&ColumnType::I64(false)),
+        "INTEGER"
+    );
+    assert_eq!(SqlDialect::Mysql.quote_identifier("a`b"), "`a``b`");
+    assert_eq!(
+        SqlDialect::Mysql.quote_string("it's C:\\"),
+        "'it''s C:\\\\'"
+    );
+}
@@ -0,0 +1,192 @@
+//! Reads a table of a SQLite database as text records, using the declared type of each column and
+//! the storage class of each value as hints when inferring the column types.
+
+use crate::{
+    column::IntermediateColumnType,
+    err::TypeGenErrors,
+    inference::{InferenceOptions, InputStats},
+    util,
+};
+use rusqlite::{types::ValueRef, Connection, OpenFlags};
+use std::path::Path;
+
+/// A column of a table, with the type it was declared with, which may be empty
+pub struct TableColumn {
+    pub name: String,
+    pub declared_type: String,
+}
+
+/// The type affinity SQLite gives a column from its declared type, following the rules at
+/// <https://www.sqlite.org/datatype3.html#determination_of_column_affinity>
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Affinity {
+    Integer,
+    Text,
+    Blob,
+    Real,
+    Numeric,
+}
+
+impl Affinity {
+    fn of(declared_type: &str) -> Self {
+        let declared_type = declared_type.to_uppercase();
+        let contains = |s: &[&str]| s.iter().any(|s| declared_type.contains(s));
+
+        if contains(&["INT"]) {
+            Affinity::Integer
+        } else if contains(&["CHAR", "CLOB", "TEXT"]) {
+            Affinity::Text
+        } else if declared_type.is_empty() || contains(&["BLOB"]) {
+            Affinity::Blob
+        } else if contains(&["REAL", "FLOA", "DOUB"]) {
+            Affinity::Real
+        } else {
+            Affinity::Numeric
+        }
+    }
+}
+
+/// Opens a database read-only.
+pub fn open(path: &Path) -> Result<Connection, TypeGenErrors> {
+    let conn = Connection::open_with_flags(path, OpenFlags::SQLITE_OPEN_READ_ONLY)?;
+    Ok(conn)
+}
+
+/// Reads the columns of a table or view, in order.
+pub fn read_columns(
+    conn: &Connection,
+    path: &Path,
+    table: &str,
+) -> Result<Vec<TableColumn>, TypeGenErrors> {
+    let columns = conn
+        .prepare("SELECT name, type FROM pragma_table_info(?1)")?
+        .query_map([table], |row| {
+            Ok(TableColumn {
+                name: row.get(0)?,
+                declared_type: row.get(1)?,
+            })
+        })?
+        .collect::<Result<Vec<_>, _>>()?;
+
+    if columns.is_empty() {
+        let tables = conn
+            .prepare(
+                "SELECT name FROM sqlite_master WHERE type IN ('table', 'view') ORDER BY name",
+            )?
+            .query_map([], |row| row.get::<_, String>(0))?
+            .collect::<Result<Vec<_>, _>>()?;
+        return Err(TypeGenErrors::Other(
+            format!(
+                "{} has no table named '{table}'; its tables are {tables:?}",
+                path.display()
+            )
+            .into(),
+        ));
+    }
+
+    Ok(columns)
+}
+
+/// The text of a value, in the same way as the generated code. `NULL` is empty, a real number
+/// always has a decimal point or exponent, and a BLOB is read as UTF-8.
+pub fn text(value: ValueRef) -> String {
+    match value {
+        ValueRef::Null => String::new(),
+        ValueRef::Integer(i) => i.to_string(),
+        ValueRef::Real(f) => format!("{f:?}"),
+        ValueRef::Text(s) | ValueRef::Blob(s) => String::from_utf8_lossy(s).into_owned(),
+    }
+}
+
+/// The storage classes of the values in a column that aren't `NULL`
+#[derive(Default)]
+struct ValueKinds {
+    values: usize,
+    texts: usize,
+    blobs: usize,
+}
+
+/// Infers the column types from the text of the first rows of a table, then adjusts them by the
+/// declared type of each column and the storage class of its values: a column of `TEXT` values is
+/// a string even if its values are numbers, and a column with no values takes its type from its
+/// declaration.
+pub fn analyze_table(
+    conn: &Connection,
+    table: &str,
+    columns: &[TableColumn],
+    options: &InferenceOptions,
+) -> Result<InputStats, TypeGenErrors> {
+    let mut stats = InputStats::new(options);
+    let mut kinds = (0..options.num_columns)
+        .map(|_| ValueKinds::default())
+        .collect::<Vec<_>>();
+
+    // A negative limit reads every row
+    let limit = i64::try_from(options.num_rows).unwrap_or(-1);
+    let mut statement = conn.prepare(&format!(
+        "SELECT * FROM {} LIMIT ?1",
+        util::quote_identifier(table)
+    ))?;
+    let mut rows = statement.query([limit])?;
+
+    while let Some(row) = rows.next()? {
+        let mut record = csv::StringRecord::new();
+        for (i, kinds) in kinds.iter_mut().enumerate() {
+            let value = row.get_ref(i)?;
+            if value != ValueRef::Null {
+                kinds.values += 1;
+            }
+            match value {
+                ValueRef::Text(_) => kinds.texts += 1,
+                ValueRef::Blob(_) => kinds.blobs += 1,
+                ValueRef::Null | ValueRef::Integer(_) | ValueRef::Real(_) => {}
+            }
+            record.push_field(&text(value));
+        }
+
+        let mut position = csv::Position::new();
+        position.set_record(stats.rows).set_line(stats.rows + 1);
+        record.set_position(Some(position));
+        stats.observe(&record, options);
+    }
+
+    for ((column, kinds), table_column) in stats.columns.iter_mut().zip(kinds).zip(columns) {
+        let state = &mut column.inference.state;
+        let declared_type = &table_column.declared_type;
+
+        if kinds.values == 0 {
+            let declared = match Affinity::of(declared_type) {
+                Affinity::Integer => {
+                    IntermediateColumnType::Integer(i64::MIN.into(), i64::MAX.into(), true)
+                }
+                Affinity::Real => IntermediateColumnType::Float(true),
+                Affinity::Text => IntermediateColumnType::String(true),
+                Affinity::Blob | Affinity::Numeric => continue,
+            };
+            *state = declared;
+            column.source_type = Some(format!(
+                "declared {declared_type}, with no values in the rows read"
+            ));
+        } else if kinds.blobs > 0 {
+            column.source_type = Some("a BLOB, as UTF-8 text".to_string());
+        } else if kinds.texts == kinds.values && !matches!(state, IntermediateColumnType::String(_))
+        {
+            *state = IntermediateColumnType::String(state.is_optional());
+            column.source_type =
+                Some("TEXT in SQLite, although its values look like numbers or bools".to_string());
+        }
+    }
+
+    Ok(stats)
+}
+
+#[test]
+fn test_affinity() {
+    assert_eq!(Affinity::of("BIGINT"), Affinity::Integer);
+    assert_eq!(Affinity::of("varchar(20)"), Affinity::Text);
+    assert_eq!(Affinity::of(""), Affinity::Blob);
+    assert_eq!(Affinity::of("DOUBLE PRECISION"), Affinity::Real);
+    assert_eq!(Affinity::of("DECIMAL(10,5)"), Affinity::Numeric);
+    // "POINT" contains "INT", so SQLite treats it as an integer
+    assert_eq!(Affinity::of("FLOATING POINT"), Affinity::Integer);
+}
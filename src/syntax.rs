@@ -0,0 +1,133 @@
+//! Parses the generated Rust code into a syntax tree and prints it with `prettyplease`, so that
+//! whatever the templates write, the output is valid Rust laid out the same way every time.
+
+use crate::err::TypeGenErrors;
+use proc_macro2::{Ident, Literal, Span, TokenStream};
+use quote::quote;
+use std::io::Write;
+
+/// Parses `code` as a Rust file and writes it, formatted, after a comment recording how it was
+/// generated. Only doc comments survive formatting; other comments in `code` are dropped.
+pub fn write_formatted(code: &str, buf: &mut impl Write) -> Result<(), TypeGenErrors> {
+    let file = syn::parse_file(code).map_err(|e| {
+        let start = e.span().start();
+        let line = code.lines().nth(start.line.saturating_sub(1)).unwrap_or("");
+        format!(
+            "The generated code is not valid Rust: {e} at line {}, column {}:\n{line}",
+            start.line,
+            start.column + 1
+        )
+    })?;
+
+    write_header(buf)?;
+    write!(buf, "{}", prettyplease::unparse(&file))?;
+    Ok(())
+}
+
+/// Writes the comment at the top of every generated file, with the version of this tool and the
+/// arguments it was run with.
+fn write_header(buf: &mut impl Write) -> Result<(), std::io::Error> {
+    // The first argument is the binary; Cargo passes the subcommand's name as the second
+    let args = std::env::args().skip(1).map(|arg| shell_quote(&arg));
+    writeln!(
+        buf,
+        "// Generated by cargo-generate-type {}",
+        env!("CARGO_PKG_VERSION")
+    )?;
+    writeln!(buf, "// cargo {}", args.collect::<Vec<_>>().join(" "))?;
+    writeln!(buf)
+}
+
+/// Quotes an argument if a shell would otherwise split or expand it.
+fn shell_quote(arg: &str) -> String {
+    let plain = !arg.is_empty()
+        && arg
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || "-_./=:,+@%".contains(c));
+    if plain {
+        arg.to_string()
+    } else {
+        format!("'{}'", arg.replace('\'', r"'\''"))
+    }
+}
+
+/// A string literal, escaped as Rust source
+pub fn str_lit(s: &str) -> Literal {
+    Literal::string(s)
+}
+
+/// Parses code this tool writes itself, such as the name of a column's type, which is always
+/// valid Rust.
+pub fn parse<T: syn::parse::Parse>(code: &str) -> T {
+    syn::parse_str(code).unwrap_or_else(|e| panic!("{code:?} is not valid Rust: {e}"))
+}
+
+/// An identifier, from a name that [`check_ident`] accepts
+pub fn ident(name: &str) -> Ident {
+    Ident::new(name, Span::call_site())
+}
+
+/// Fails unless `name` is an identifier that isn't a keyword, so that [`ident`] can't panic.
+pub fn check_ident(name: &str) -> Result<(), TypeGenErrors> {
+    match syn::parse_str::<Ident>(name) {
+        Ok(_) => Ok(()),
+        Err(_) => Err(format!("{name:?} is not a Rust identifier").into()),
+    }
+}
+
+/// A doc comment line of `text`. Control characters are escaped, so that text from the input,
+/// such as a value with a line break, stays on one line.
+pub fn doc(text: &str) -> TokenStream {
+    let text = format!(" {}", doc_text(text));
+    quote! { #[doc = #text] }
+}
+
+/// `text` with its control characters escaped, for a doc comment
+pub fn doc_text(text: &str) -> String {
+    text.chars()
+        .map(|c| {
+            if c.is_control() {
+                c.escape_default().to_string()
+            } else {
+                c.to_string()
+            }
+        })
+        .collect()
+}
+
+#[test]
+fn test_write_formatted() {
+    let mut buf = Vec::new();
+    write_formatted("/// A row\npub struct Row{pub id:u8,// gone\n}", &mut buf).unwrap();
+    let code = String::from_utf8(buf).unwrap();
+    assert!(code.starts_with("// Generated by cargo-generate-type "));
+    assert!(code.ends_with("\n\n/// A row\npub struct Row {\n    pub id: u8,\n}\n"));
+
+    let err = write_formatted("pub struct Row {\n    pub type: u8,\n}", &mut Vec::new());
+    assert!(err.unwrap_err().to_string().contains("line 2"));
+}
+
+#[test]
+fn test_doc() {
+    assert_eq!(doc_text("a\nb\tc\u{1b}d"), r"a\nb\tc\u{1b}d");
+    assert_eq!(doc_text("plain 'text'"), "plain 'text'");
+
+    // A line break in the text can't end the comment early
+    let doc = doc("From 'line\nbreak'");
+    let mut buf = Vec::new();
+    write_formatted(&quote! { #doc pub struct Row; }.to_string(), &mut buf).unwrap();
+    let code = String::from_utf8(buf).unwrap();
+    assert!(code.ends_with("\n/// From 'line\\nbreak'\npub struct Row;\n"));
+
+    assert!(check_ident("Row").is_ok());
+    for name in ["type", "2020", "a b", ""] {
+        assert!(check_ident(name).is_err());
+    }
+}
+
+#[test]
+fn test_shell_quote() {
+    assert_eq!(shell_quote("--strings"), "--strings");
+    assert_eq!(shell_quote("data/*.csv"), "'data/*.csv'");
+    assert_eq!(shell_quote("it's"), r"'it'\''s'");
+}
@@ -0,0 +1,81 @@
+//! Renders the generated Rust code from a template, so that the derives, attributes and error
+//! type can be changed without changing this tool. The built-in template is used unless
+//! `--template` names a directory of templates.
+
+use crate::{err::TypeGenErrors, syntax};
+use minijinja::{path_loader, Environment, UndefinedBehavior, Value};
+use std::{io::Write, path::Path};
+
+/// The template that generates the built-in output
+const BUILTIN: &str = include_str!("templates/builtin.rs.jinja");
+
+/// The name under which the built-in template can be extended
+const BUILTIN_NAME: &str = "builtin.rs.jinja";
+
+/// The template that is rendered from a `--template` directory
+const ENTRY_NAME: &str = "type.rs.jinja";
+
+/// Renders the type from the templates in `dir`, or else the built-in template.
+pub fn render(
+    dir: Option<&Path>,
+    context: Value,
+    buf: &mut impl Write,
+) -> Result<(), TypeGenErrors> {
+    let mut env = Environment::new();
+    // Block tags take up no lines of their own, so templates can be laid out like the code
+    env.set_trim_blocks(true);
+    env.set_lstrip_blocks(true);
+    env.set_keep_trailing_newline(true);
+    env.set_undefined_behavior(UndefinedBehavior::Strict);
+    env.add_filter("doc", |text: &str| syntax::doc_text(text));
+    env.add_template(BUILTIN_NAME, BUILTIN)?;
+
+    let name = match dir {
+        Some(dir) => {
+            if !dir.join(ENTRY_NAME).is_file() {
+                return Err(format!("{} has no {ENTRY_NAME} template", dir.display()).into());
+            }
+            env.set_loader(path_loader(dir));
+            ENTRY_NAME
+        }
+        None => BUILTIN_NAME,
+    };
+
+    env.get_template(name)?.render_captured_to(context, buf)?;
+    Ok(())
+}
+
+/// The text of code written by a function, for the template's context
+pub fn code<E>(write: impl FnOnce(&mut Vec<u8>) -> Result<(), E>) -> Result<String, E> {
+    let mut buf = Vec::new();
+    write(&mut buf)?;
+    Ok(String::from_utf8_lossy(&buf).into_owned())
+}
+
+#[test]
+fn test_render_builtin() {
+    use minijinja::context;
+
+    let context = context! {
+        typename => "Row",
+        docs => vec!["A row"],
+        derives => vec!["Clone", "Debug"],
+        fields => vec![context! {
+            name => "id",
+            header => "ID",
+            type => "u8",
+            docs => Vec::<String>::new(),
+            comments => vec!["Inferred because it's a number"],
+        }],
+        enums => Vec::<Value>::new(),
+        key_type => Value::from(()),
+        code => context! { errors => "", methods => "impl Row {}\n", reader => "", test => "" },
+    };
+
+    let mut buf = Vec::new();
+    render(None, context, &mut buf).unwrap();
+    assert_eq!(
+        String::from_utf8(buf).unwrap(),
+        "#[derive(Clone, Debug)]\n/// A row\npub struct Row {\n    /// Inferred because it's a number\n    pub id: u8,\n}\n\nimpl Row {}\n"
+    );
+}
@@ -0,0 +1,141 @@
+//! Writes a TypeScript interface for the row type, for `--emit typescript`.
+
+use crate::{
+    column::ColumnType,
+    emit::{FieldDef, TypeDef},
+    util,
+};
+use std::io::Write;
+
+/// A union of string literal types
+fn union(values: &[&str]) -> String {
+    values
+        .iter()
+        .map(|v| util::json_string(v))
+        .collect::<Vec<_>>()
+        .join(" | ")
+}
+
+/// The type of a field's values, other than `null`. Numbers of every width are a `number`.
+fn value_type(field: &FieldDef, def: &TypeDef) -> String {
+    match field.r#type {
+        ColumnType::Unit => "null".to_string(),
+        ColumnType::Bool(_) => "boolean".to_string(),
+        ColumnType::String(_) if field.is_enum(def.string_handling) => field.enum_name.clone(),
+        ColumnType::String(_) if !field.values.is_empty() => union(&field.values),
+        ColumnType::String(_) => "string".to_string(),
+        _ => "number".to_string(),
+    }
+}
+
+/// Writes a documentation comment, a single line if it can be.
+fn write_doc<W: Write>(buf: &mut W, indent: &str, docs: &[String]) -> std::io::Result<()> {
+    let docs = docs
+        .iter()
+        .map(|line| line.replace("*/", "*\\/"))
+        .collect::<Vec<_>>();
+
+    match &docs[..] {
+        [] => {}
+        [line] => writeln!(buf, "{indent}/** {line} */")?,
+        lines => {
+            writeln!(buf, "{indent}/**")?;
+            for line in lines {
+                if line.is_empty() {
+                    writeln!(buf, "{indent} *")?;
+                } else {
+                    writeln!(buf, "{indent} * {line}")?;
+                }
+            }
+            writeln!(buf, "{indent} */")?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Writes an interface with a property for each field. With `--strings enum`, a string field's
+/// values are a union type named like the Rust enum, which also admits other strings if the enum
+/// holds values that weren't seen. With `--strings static` the union is written in place. An
+/// optional field may also be `null`.
+pub fn write_interface<W: Write>(buf: &mut W, def: &TypeDef) -> Result<(), std::io::Error> {
+    for field in &def.fields {
+        if field.is_enum(def.string_handling) {
+            // `string & {}` admits any other string without widening the union to `string`
+            let other = if field.is_open_enum(def) {
+                " | (string & {})"
+            } else {
+                ""
+            };
+            writeln!(
+                buf,
+                "export type {} = {}{other};",
+                field.enum_name,
+                union(&field.values)
+            )?;
+            writeln!(buf)?;
+        }
+    }
+
+    write_doc(buf, "", def.docs)?;
+    writeln!(buf, "export interface {} {{", def.typename)?;
+    for field in &def.fields {
+        write_doc(buf, "  ", field.docs)?;
+
+        let mut r#type = value_type(field, def);
+        if field.r#type.is_optional() {
+            r#type += " | null";
+        }
+        writeln!(buf, "  {}: {type};", field.name)?;
+    }
+    writeln!(buf, "}}")?;
+
+    Ok(())
+}
+
+#[test]
+fn test_write_interface() {
+    use crate::input_args::StringHandling;
+
+    let field = |name: &'static str, r#type, values| FieldDef {
+        header: "",
+        column: name,
+        name: name.to_string(),
+        enum_name: util::str_to_camel_case_identifier(name),
+        r#type,
+        values,
+        variants: Vec::new(),
+        docs: &[],
+        patterns: &[],
+    };
+    let mut def = TypeDef {
+        typename: "Row".to_string(),
+        docs: &[],
+        fields: vec![
+            field("team", &ColumnType::String(true), vec!["blue", "red"]),
+            field("count", &ColumnType::U64(false), Vec::new()),
+        ],
+        key: &[],
+        string_handling: StringHandling::Enum(10),
+        catch_all: false,
+    };
+
+    let mut buf = Vec::new();
+    write_interface(&mut buf, &def).unwrap();
+    let ts = String::from_utf8(buf).unwrap();
+    assert!(ts.starts_with("export type Team = \"blue\" | \"red\";\n"));
+    assert!(ts.contains("  team: Team | null;\n  count: number;\n"));
+
+    def.catch_all = true;
+    let mut buf = Vec::new();
+    write_interface(&mut buf, &def).unwrap();
+    let ts = String::from_utf8(buf).unwrap();
+    assert!(ts.starts_with("export type Team = \"blue\" | \"red\" | (string & {});\n"));
+
+    def.string_handling = StringHandling::Static;
+    let mut buf = Vec::new();
+    write_interface(&mut buf, &def).unwrap();
+    let ts = String::from_utf8(buf).unwrap();
+    assert!(ts.starts_with("export interface Row {\n"));
+    assert!(ts.contains("  team: \"blue\" | \"red\" | null;\n"));
+}
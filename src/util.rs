@@ -1,7 +1,7 @@
 use std::{
     collections::HashMap,
     io::{BufRead, BufReader},
-    path::Path,
+    path::{Path, PathBuf},
 };
 
 /// Attempts to produce valid Rust identifiers from a column name.
@@ -48,6 +48,25 @@ pub(crate) fn header_to_identifier(header: &str) -> String {
     result
 }
 
+/// The documentation and directives for one section of a '.def' file.
+#[derive(Debug, Default)]
+pub struct DefSection {
+    /// Lines of documentation
+    pub docs: Vec<String>,
+    /// Lines of the form `@key value`, such as `@pattern ^[A-Z]{3}$`
+    pub directives: Vec<(String, String)>,
+}
+
+impl DefSection {
+    /// Returns the values of all directives with the specified key.
+    pub fn directive<'a>(&'a self, key: &'a str) -> impl Iterator<Item = &'a str> + 'a {
+        self.directives
+            .iter()
+            .filter(move |(k, _)| k == key)
+            .map(|(_, v)| v.as_str())
+    }
+}
+
 /// Reads an '.def' file associated with an input dataset, using the .def as
 /// user-provided documentation to be included in generated code.
 ///
@@ -75,9 +94,15 @@ pub(crate) fn header_to_identifier(header: &str) -> String {
 /// [petal_length]
 /// The length of the flower's petal in centimeters.
 ///
+/// [class]
+/// The species of flower.
+/// @pattern ^Iris-[a-z]+$
+///
 /// (...)
 /// ```
-pub fn read_doc_file(data_filename: &Path) -> Option<HashMap<String, Vec<String>>> {
+///
+/// Lines starting with `@` are directives rather than documentation.
+pub fn read_doc_file(data_filename: &Path) -> Option<HashMap<String, DefSection>> {
     let dir = data_filename.parent()?.to_str()?;
     let stem = data_filename.file_stem()?.to_str()?;
     let filename = format!("{dir}/{stem}.def");
@@ -86,33 +111,41 @@ pub fn read_doc_file(data_filename: &Path) -> Option<HashMap<String, Vec<String>
     let buf = BufReader::new(fh);
 
     // The column name and its associated lines of text for documentation
-    let mut docs = HashMap::new();
+    let mut sections = HashMap::new();
 
     // The empty string represents the comments on the struct itself
-    let mut section = String::new();
-    let mut doc_lines: Vec<String> = Vec::new();
+    let mut name = String::new();
+    let mut section = DefSection::default();
 
     for line in buf.lines().map_while(Result::ok) {
         if line.starts_with('[') && line.ends_with(']') {
             // starting a new section. pop empty lines from the end
-            while !doc_lines.is_empty() && doc_lines[doc_lines.len() - 1].is_empty() {
-                doc_lines.pop();
+            while section.docs.last().is_some_and(|l| l.is_empty()) {
+                section.docs.pop();
             }
 
-            docs.insert(section, doc_lines);
+            sections.insert(name, section);
 
             let line = line.trim_matches(&['[', ']'][..]);
             let normalized = crate::util::header_to_identifier(line);
 
-            section = normalized;
-            doc_lines = Vec::new();
+            name = normalized;
+            section = DefSection::default();
+        } else if let Some(directive) = line.strip_prefix('@') {
+            let (key, value) = directive.split_once(' ').unwrap_or((directive, ""));
+            section
+                .directives
+                .push((key.to_string(), value.trim().to_string()));
         } else {
-            doc_lines.push(line);
+            section.docs.push(line);
         }
     }
 
-    docs.insert(section, doc_lines);
-    Some(docs)
+    while section.docs.last().is_some_and(|l| l.is_empty()) {
+        section.docs.pop();
+    }
+    sections.insert(name, section);
+    Some(sections)
 }
 
 fn is_identifier_char(c: char) -> bool {
@@ -172,6 +205,167 @@ pub fn str_to_camel_case_identifier(s: &str) -> String {
     result
 }
 
+/// Quotes a SQL identifier, such as a table name, doubling any quotes within it.
+pub(crate) fn quote_identifier(name: &str) -> String {
+    format!("\"{}\"", name.replace('"', "\"\""))
+}
+
+/// A 64-bit FNV-1a hash.
+///
+/// Unlike `std::collections::hash_map::DefaultHasher`, the output is stable across
+/// platforms and Rust versions, which makes it suitable for fingerprints that end up in
+/// generated code.
+pub(crate) struct Fnv1a(u64);
+
+impl Fnv1a {
+    pub fn new() -> Self {
+        Fnv1a(0xcbf2_9ce4_8422_2325)
+    }
+
+    pub fn write(&mut self, bytes: &[u8]) {
+        for b in bytes {
+            self.0 ^= *b as u64;
+            self.0 = self.0.wrapping_mul(0x0100_0000_01b3);
+        }
+    }
+
+    pub fn finish(&self) -> u64 {
+        self.0
+    }
+}
+
+/// A naive singular form of an English plural, such as a file name: `customers` becomes
+/// `customer` and `categories` becomes `category`.
+pub(crate) fn singular(s: &str) -> String {
+    if let Some(stem) = s.strip_suffix("ies") {
+        format!("{stem}y")
+    } else if let Some(stem) = s.strip_suffix('s').filter(|stem| !stem.ends_with('s')) {
+        stem.to_string()
+    } else {
+        s.to_string()
+    }
+}
+
+/// Expands `*` and `?` wildcards in the file name of each path into the files that match,
+/// sorted by name. Paths without wildcards are kept as they are.
+pub(crate) fn expand_globs(paths: &[PathBuf]) -> std::io::Result<Vec<PathBuf>> {
+    let mut expanded = Vec::new();
+
+    for path in paths {
+        let pattern = match path.file_name().and_then(|f| f.to_str()) {
+            Some(f) if f.contains(['*', '?']) => f,
+            _ => {
+                expanded.push(path.clone());
+                continue;
+            }
+        };
+
+        let dir = match path.parent() {
+            Some(p) if !p.as_os_str().is_empty() => p,
+            _ => Path::new("."),
+        };
+
+        let mut matches = std::fs::read_dir(dir)?
+            .filter_map(Result::ok)
+            .filter(|e| e.file_type().is_ok_and(|t| t.is_file()))
+            .filter(|e| {
+                e.file_name()
+                    .to_str()
+                    .is_some_and(|n| wildcard_match(pattern, n))
+            })
+            .map(|e| path.with_file_name(e.file_name()))
+            .collect::<Vec<_>>();
+
+        if matches.is_empty() {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::NotFound,
+                format!("No files match {}", path.display()),
+            ));
+        }
+
+        matches.sort();
+        expanded.extend(matches);
+    }
+
+    Ok(expanded)
+}
+
+/// Whether `name` matches `pattern`, where `*` matches any run of characters and `?` matches one.
+fn wildcard_match(pattern: &str, name: &str) -> bool {
+    let pattern = pattern.chars().collect::<Vec<_>>();
+    let name = name.chars().collect::<Vec<_>>();
+    let (mut p, mut n) = (0, 0);
+    // Where to resume after the most recent '*' if the rest of the pattern fails to match
+    let mut backtrack = None;
+
+    while n < name.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                backtrack = Some((p, n));
+                p += 1;
+            }
+            Some(c) if *c == '?' || *c == name[n] => {
+                p += 1;
+                n += 1;
+            }
+            _ => match backtrack {
+                Some((star, matched)) => {
+                    backtrack = Some((star, matched + 1));
+                    p = star + 1;
+                    n = matched + 1;
+                }
+                None => return false,
+            },
+        }
+    }
+
+    pattern[p..].iter().all(|c| *c == '*')
+}
+
+/// A small, seedable pseudo-random number generator (SplitMix64), so that sampling is
+/// reproducible for a given seed.
+pub(crate) struct Rng(u64);
+
+impl Rng {
+    pub fn new(seed: u64) -> Self {
+        Rng(seed)
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+
+    /// A number in `0..n`
+    pub fn below(&mut self, n: u64) -> u64 {
+        ((self.next_u64() as u128 * n as u128) >> 64) as u64
+    }
+}
+
+/// Quotes and escapes `s` as a JSON string.
+pub(crate) fn json_string(s: &str) -> String {
+    let mut result = String::with_capacity(s.len() + 2);
+    result.push('"');
+
+    for c in s.chars() {
+        match c {
+            '"' => result.push_str("\\\""),
+            '\\' => result.push_str("\\\\"),
+            '\n' => result.push_str("\\n"),
+            '\r' => result.push_str("\\r"),
+            '\t' => result.push_str("\\t"),
+            c if (c as u32) < 0x20 => result.push_str(&format!("\\u{:04x}", c as u32)),
+            c => result.push(c),
+        }
+    }
+
+    result.push('"');
+    result
+}
+
 #[test]
 fn test_header_to_identifier() {
     assert_eq!("first", header_to_identifier("first"));
@@ -213,3 +407,25 @@ fn test_str_to_camel_case_identifier() {
     let snake = str_to_camel_case_identifier("CLASS");
     assert_eq!(snake, "Class");
 }
+
+#[test]
+fn test_singular() {
+    assert_eq!(singular("customers"), "customer");
+    assert_eq!(singular("categories"), "category");
+    assert_eq!(singular("address"), "address");
+    assert_eq!(singular("stock"), "stock");
+}
+
+#[test]
+fn test_wildcard_match() {
+    assert!(wildcard_match(
+        "events-2026-10-*.csv",
+        "events-2026-10-01.csv"
+    ));
+    assert!(wildcard_match("*.csv", ".csv"));
+    assert!(wildcard_match("day-??.csv", "day-01.csv"));
+    assert!(wildcard_match("a*b*c", "axxbyyc"));
+    assert!(!wildcard_match("day-??.csv", "day-1.csv"));
+    assert!(!wildcard_match("*.csv", "events.tsv"));
+    assert!(!wildcard_match("a*b", "ab-c"));
+}